@@ -14,6 +14,5 @@ fn main() {
         eprintln!("ERROR: Problem reading the file:\n    {}", err);
         process::exit(2);
     });
-    // See stackoverflow.com/a/60581271 and reddit.com/r/rust/comments/cfybfa
-    println!("{}", lexemize(Box::leak(contents.into_boxed_str())));
+    println!("{}", lexemize(&contents));
 }
\ No newline at end of file
@@ -1,6 +1,14 @@
 #![deny(warnings, missing_docs)]
+// Unit tests always get `std`. The `#![no_std]` build is tested by
+// `tests/no_std.rs`, which links the library without `cfg(test)`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
 //! Opinionated library for transforming code to a vector of Lexemes.
+//! 
+//! The default `std` feature can be turned off, for a `no_std` build which
+//! only needs the `alloc` crate.
+
+extern crate alloc;
 
 /// 
 pub mod rust_2018;
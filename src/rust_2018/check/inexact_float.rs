@@ -0,0 +1,155 @@
+//! Flags decimal float literals which cannot be represented exactly as `f64`.
+
+use alloc::{format,vec};
+use alloc::string::{String,ToString};
+use alloc::vec::Vec;
+
+use super::super::detect::number::split_number_suffix;
+use super::super::lexeme::{Lexeme,LexemeKind,UnexpectedReason};
+const FLOAT: LexemeKind = LexemeKind::NumberDecimalFloat;
+const UNEXPECTED: LexemeKind = LexemeKind::Unexpected;
+
+/// Flags decimal float literals which cannot be represented exactly as `f64`.
+/// 
+/// A linter might want to warn about a literal like `0.1`, which is actually
+/// stored as `0.1000000000000000055511151231257827...`. Each decimal float is
+/// parsed, and the exact decimal expansion of the parsed `f64` is compared to
+/// the literal. A literal which overflows to infinity is also inexact.
+/// 
+/// Only `NumberDecimalFloat` Lexemes are checked, including `1f64`. Any `f32`
+/// or `f64` suffix is ignored, so `0.1f32` is checked as an `f64` too.
+/// Integers like `10` are `NumberDecimal`, so they are never flagged.
+/// 
+/// ### Arguments
+/// * `lexemes` The Lexemes found by `lexemize()`, which may be retagged
+/// 
+/// ### Returns
+/// `flag_inexact_floats()` retags each inexact float as `Unexpected`, and
+/// returns the `chr` of each one, with `UnexpectedReason::InexactFloat`.
+pub fn flag_inexact_floats(
+    lexemes: &mut [Lexeme],
+) -> Vec<(usize, UnexpectedReason)> {
+    let mut reasons = vec![];
+    // Step through each decimal float Lexeme.
+    for lexeme in lexemes.iter_mut() {
+        if lexeme.kind != FLOAT { continue }
+        // If it is inexact, flag it as Unexpected.
+        if is_inexact(lexeme.snippet) {
+            lexeme.kind = UNEXPECTED;
+            reasons.push((lexeme.chr, UnexpectedReason::InexactFloat));
+        }
+    }
+    reasons
+}
+
+// Returns true if a decimal float literal cannot be stored exactly as `f64`.
+fn is_inexact(snippet: &str) -> bool {
+    // The `f32` or `f64` suffix is not part of the value, and underscores are
+    // just visual separators, so remove them.
+    let (number, _suffix) = split_number_suffix(snippet);
+    let literal = number.replace('_', "");
+    // Parse the literal. If the parse fails, it’s not our job to flag it here.
+    let value: f64 = match literal.parse() { Ok(v) => v, Err(_) => return false };
+    // Infinity is never the value that the literal asked for.
+    if value.is_infinite() { return true }
+    // Expand the `f64` to its exact decimal value. Every finite `f64` has at
+    // most 1074 digits after the decimal point, so 1100 is always enough.
+    let expanded = format!("{:.1100}", value);
+    normalize(&literal) != normalize(&expanded)
+}
+
+// Converts a decimal literal like "012.50e1" to its significant digits and a
+// power-of-ten exponent, like ("125", 0). Zero is ("", 0). Returns `None` if
+// the exponent is too large to handle.
+fn normalize(literal: &str) -> Option<(String, i64)> {
+    // Split the literal into its mantissa, and its (optional) exponent.
+    let (mantissa, exponent) = match literal.find(['e', 'E']) {
+        Some(pos) => (&literal[..pos], literal[pos+1..].parse::<i64>().ok()?),
+        None => (literal, 0),
+    };
+    // Split the mantissa into its integer and fractional parts.
+    let (int, frac) = match mantissa.find('.') {
+        Some(pos) => (&mantissa[..pos], &mantissa[pos+1..]),
+        None => (mantissa, ""),
+    };
+    // Join the digits, and adjust the exponent to account for the fraction.
+    let digits = format!("{}{}", int, frac);
+    let mut exponent = exponent.checked_sub(frac.len() as i64)?;
+    // Remove leading zeros, and trailing zeros (which raise the exponent).
+    let digits = digits.trim_start_matches('0');
+    let trimmed = digits.trim_end_matches('0');
+    exponent = exponent.checked_add((digits.len() - trimmed.len()) as i64)?;
+    // Zero has no significant digits, so its exponent is irrelevant.
+    if trimmed.is_empty() { return Some(("".to_string(), 0)) }
+    Some((trimmed.to_string(), exponent))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::flag_inexact_floats as flag;
+    use super::super::super::lexeme::{LexemeKind,UnexpectedReason};
+    use super::super::super::test_helpers::lx;
+    use super::FLOAT as F;
+    use super::UNEXPECTED as X;
+    use UnexpectedReason::InexactFloat as I;
+
+    // Flags a single Lexeme, and returns its new kind and the reasons.
+    fn check(snippet: &'static str) -> (LexemeKind, Vec<(usize, UnexpectedReason)>) {
+        let mut lexemes = [lx(F,0,snippet)];
+        let reasons = flag(&mut lexemes);
+        (lexemes[0].kind, reasons)
+    }
+
+    #[test]
+    fn flag_inexact_floats_correct() {
+        assert_eq!(check("0.1"),     (X, vec![(0,I)])); // 0.1000000000000000055...
+        assert_eq!(check("0.3"),     (X, vec![(0,I)])); // 0.2999999999999999888...
+        assert_eq!(check("1e308"),   (X, vec![(0,I)])); // finite, but 10^308 needs
+                                                        // more than 53 bits
+        assert_eq!(check("1e309"),   (X, vec![(0,I)])); // overflows to infinity
+        assert_eq!(check("1e-400"),  (X, vec![(0,I)])); // underflows to zero
+        assert_eq!(check("0.000_1"), (X, vec![(0,I)])); // with underscores
+        assert_eq!(check("4E-1_0"),  (X, vec![(0,I)])); // 4 * 10^-10
+        assert_eq!(check("0.1f64"),  (X, vec![(0,I)])); // with a suffix
+        assert_eq!(check("0.3_f32"), (X, vec![(0,I)])); // checked as `f64` too
+    }
+
+    #[test]
+    fn flag_inexact_floats_incorrect() {
+        assert_eq!(check("0.5"),       (F, vec![])); // 2^-1
+        assert_eq!(check("0.0"),       (F, vec![])); // zero
+        assert_eq!(check("00.000"),    (F, vec![])); // zero, with extra zeros
+        assert_eq!(check("1.5e1"),     (F, vec![])); // 15
+        assert_eq!(check("1.1e1"),     (F, vec![])); // 11, even though 1.1 is not
+        assert_eq!(check("0.125"),     (F, vec![])); // 2^-3
+        assert_eq!(check("1e22"),      (F, vec![])); // 10^22 fits in 53 bits
+        assert_eq!(check("1_024.25"),  (F, vec![])); // with underscores
+        assert_eq!(check("123."),      (F, vec![])); // trailing dot
+        assert_eq!(check("2f64"),      (F, vec![])); // a float suffix
+        assert_eq!(check("0.5_f32"),   (F, vec![])); // with underscores
+    }
+
+    #[test]
+    fn flag_inexact_floats_ignores_other_kinds() {
+        let mut lexemes = [
+            lx(LexemeKind::NumberHex, 0, "0x1E"),
+            lx(LexemeKind::StringPlain, 4, "\"0.1\""),
+            lx(F, 9, "0.1"),
+            lx(LexemeKind::NumberDecimal, 13, "100000000000000000000000"),
+        ];
+        assert_eq!(flag(&mut lexemes), vec![(9,I)]);
+        assert_eq!(lexemes[0].kind, LexemeKind::NumberHex);
+        assert_eq!(lexemes[1].kind, LexemeKind::StringPlain);
+        assert_eq!(lexemes[2].kind, X);
+        assert_eq!(lexemes[3].kind, LexemeKind::NumberDecimal); // an integer
+    }
+
+    #[test]
+    fn flag_inexact_floats_will_not_panic() {
+        assert_eq!(flag(&mut []), vec![]); // no Lexemes
+        assert_eq!(check("1e99999999999999999999"), (X, vec![(0,I)])); // huge
+        assert_eq!(check("1e-99999999999999999999"), (X, vec![(0,I)]));
+        assert_eq!(check("1e"), (F, vec![])); // unparseable, not our job
+    }
+}
@@ -0,0 +1,86 @@
+//! Flags lifetimes which are named after a keyword, like `'fn`.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::super::detect::identifier::detect_identifier;
+use super::super::lexeme::{Lexeme,LexemeKind,UnexpectedReason};
+const KEYWORD: LexemeKind = LexemeKind::IdentifierKeyword;
+const LIFETIME: LexemeKind = LexemeKind::IdentifierLifetime;
+const UNEXPECTED: LexemeKind = LexemeKind::Unexpected;
+
+/// Flags lifetimes which are named after a keyword, like `'fn`.
+/// 
+/// `tag_lifetime()` is lexically permissive, so `'fn` and `'let` are tagged
+/// as Lifetimes, even though they are not valid Rust. `'static` is a keyword,
+/// but is always a valid lifetime, and so is the anonymous lifetime `'_`.
+/// 
+/// ### Arguments
+/// * `lexemes` The Lexemes found by `lexemize()`, which may be retagged
+/// 
+/// ### Returns
+/// `flag_keyword_lifetimes()` retags each keyword lifetime as `Unexpected`,
+/// and returns the `chr` of each one, with `UnexpectedReason::KeywordLifetime`.
+pub fn flag_keyword_lifetimes(
+    lexemes: &mut [Lexeme],
+) -> Vec<(usize, UnexpectedReason)> {
+    let mut reasons = vec![];
+    // Step through each Lifetime Lexeme.
+    for lexeme in lexemes.iter_mut() {
+        if lexeme.kind != LIFETIME { continue }
+        // Get the name, without the leading single quote. If it is a keyword
+        // other than `static`, flag it as Unexpected.
+        let name = &lexeme.snippet[1..];
+        if name != "static"
+        && detect_identifier(name, 0) == (KEYWORD, name.len()) {
+            lexeme.kind = UNEXPECTED;
+            reasons.push((lexeme.chr, UnexpectedReason::KeywordLifetime));
+        }
+    }
+    reasons
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::flag_keyword_lifetimes as flag;
+    use super::super::super::lexeme::{LexemeKind,UnexpectedReason};
+    use super::super::super::test_helpers::lx;
+    use super::LIFETIME as L;
+    use super::UNEXPECTED as X;
+    use UnexpectedReason::KeywordLifetime as K;
+
+    // Flags a single Lexeme, and returns its new kind and the reasons.
+    fn check(snippet: &'static str) -> (LexemeKind, Vec<(usize, UnexpectedReason)>) {
+        let mut lexemes = [lx(L,0,snippet)];
+        let reasons = flag(&mut lexemes);
+        (lexemes[0].kind, reasons)
+    }
+
+    #[test]
+    fn flag_keyword_lifetimes_correct() {
+        assert_eq!(check("'fn"),    (X, vec![(0,K)])); // 'fn
+        assert_eq!(check("'let"),   (X, vec![(0,K)])); // 'let
+        assert_eq!(check("'Self"),  (X, vec![(0,K)])); // 'Self
+        assert_eq!(check("'while"), (X, vec![(0,K)])); // 'while
+    }
+
+    #[test]
+    fn flag_keyword_lifetimes_incorrect() {
+        assert_eq!(check("'static"), (L, vec![])); // always fine
+        assert_eq!(check("'_"),      (L, vec![])); // anonymous lifetime
+        assert_eq!(check("'a"),      (L, vec![])); // ordinary lifetime
+        assert_eq!(check("'fnord"),  (L, vec![])); // starts with a keyword
+        assert_eq!(check("'u8"),     (L, vec![])); // StdType, not a keyword
+        // Only Lifetimes are looked at.
+        let mut lexemes = [lx(LexemeKind::IdentifierKeyword, 0, "fn")];
+        assert_eq!(flag(&mut lexemes), vec![]);
+        assert_eq!(lexemes[0].kind, LexemeKind::IdentifierKeyword);
+    }
+
+    #[test]
+    fn flag_keyword_lifetimes_will_not_panic() {
+        assert_eq!(flag(&mut []), vec![]); // no Lexemes
+        assert_eq!(check("'"), (L, vec![])); // no name
+    }
+}
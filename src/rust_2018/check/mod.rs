@@ -0,0 +1,9 @@
+//! Functions for flagging suspicious Lexemes in Rust 2018 code.
+//! 
+//! These only run if `LexemizeOptions::checked` is set. Each flagged Lexeme is
+//! retagged as `LexemeKind::Unexpected`, and the reason is recorded in
+//! `LexemizeResult::reasons`.
+
+pub mod inexact_float;
+pub mod keyword_lifetime;
+pub mod oversized_number;
@@ -0,0 +1,238 @@
+//! Flags integer literals which are too large for their type, like `256u8`.
+
+use alloc::vec;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::super::detect::number::split_number_suffix;
+use super::super::lexeme::{Lexeme,LexemeKind,UnexpectedReason};
+const UNEXPECTED: LexemeKind = LexemeKind::Unexpected;
+
+/// Flags integer literals which are too large for their type, like `256u8`.
+/// 
+/// `detect_number()` is just a scanner, so it happily accepts a literal like
+/// `0x1_0000_0000_0000_0000_0000_0000_0000_0000`, which does not fit in a
+/// `u128`. Each integer literal is parsed, ignoring underscores, and compared
+/// to the largest value of its type.
+/// 
+/// The type comes from a suffix, like the `u8` of `255u8`, which is part of
+/// the number’s snippet. An unsuffixed literal may be as large as `u128::MAX`. `isize` and
+/// `usize` are assumed to be 64 bits wide. A signed literal which follows a
+/// unary `-`, like the `128` of `-128i8` or `x * - 128i8`, may be one larger
+/// than its type’s `MAX`, because the compiler accepts it. A `-` which follows
+/// an operand, like the `-` of `x-128i8` or `f() - 128i8`, is a binary minus,
+/// so does not count. Floats like `1e5` or `1f32` are never flagged.
+/// 
+/// ### Arguments
+/// * `lexemes` The Lexemes found by `lexemize()`, which may be retagged
+/// 
+/// ### Returns
+/// `flag_oversized_numbers()` retags each oversized integer as `Unexpected`,
+/// and returns the `chr` of each one, with `UnexpectedReason::OversizedNumber`.
+pub fn flag_oversized_numbers(
+    lexemes: &mut [Lexeme],
+) -> Vec<(usize, UnexpectedReason)> {
+    let mut reasons = vec![];
+    for i in 0..lexemes.len() {
+        let lexeme = lexemes[i];
+        // Split off the suffix, if any, and find the radix and digits,
+        // skipping floats and non-numbers.
+        let (number, suffix) = split_number_suffix(lexeme.snippet);
+        let (radix, digits) = match lexeme.kind {
+            LexemeKind::NumberBinary => (2, &number[2..]),
+            LexemeKind::NumberOctal => (8, &number[2..]),
+            LexemeKind::NumberHex => (16, &number[2..]),
+            LexemeKind::NumberDecimal => (10, number),
+            _ => continue,
+        };
+        // A literal after a unary `-` may reach the signed minimum.
+        let negated = is_negated(lexemes, i);
+        let max = match max_for_suffix(suffix, negated) {
+            Some(max) => max,
+            None => continue, // not an integer suffix
+        };
+        // Parse the digits. A parse error here can only mean overflow.
+        let digits: String = digits.chars().filter(|c| *c != '_').collect();
+        if digits.is_empty() { continue }
+        let too_big = match u128::from_str_radix(&digits, radix) {
+            Ok(value) => value > max,
+            Err(_) => true,
+        };
+        if too_big {
+            lexemes[i].kind = UNEXPECTED;
+            reasons.push((lexeme.chr, UnexpectedReason::OversizedNumber));
+        }
+    }
+    reasons
+}
+
+// Returns true if the Lexeme at `i` follows a unary `-`, ignoring trivia. A
+// `-` after an operand, like the `-` of `x-128i8`, is a binary minus.
+fn is_negated(lexemes: &[Lexeme], i: usize) -> bool {
+    let mut before = lexemes[..i].iter().rev().filter(|l| ! l.is_trivia());
+    match before.next() {
+        Some(minus) if minus.kind == LexemeKind::Punctuation
+            && minus.snippet == "-" => ! before.next().is_some_and(is_operand),
+        _ => false,
+    }
+}
+
+// Returns true if `lexeme` can end an operand, like `x`, `1`, `"a"`, `)` or `]`.
+fn is_operand(lexeme: &Lexeme) -> bool {
+    let kind = lexeme.kind;
+    match lexeme.snippet {
+        ")" | "]" => kind == LexemeKind::Punctuation,
+        "self" | "Self" | "true" | "false" => true,
+        _ => kind.is_number() || kind.is_string() || kind.is_character()
+            || kind.is_identifier() && kind != LexemeKind::IdentifierKeyword,
+    }
+}
+
+// Returns the largest value allowed for an integer suffix like "u8", or for an
+// unsuffixed literal if `suffix` is empty. `None` means this is not an integer.
+fn max_for_suffix(suffix: &str, negated: bool) -> Option<u128> {
+    // The number of bits for the type, and whether it is signed.
+    let (bits, signed) = match suffix {
+        "" => (128, false),
+        "u8" => (8, false), "u16" => (16, false), "u32" => (32, false),
+        "u64" | "usize" => (64, false), "u128" => (128, false),
+        "i8" => (8, true), "i16" => (16, true), "i32" => (32, true),
+        "i64" | "isize" => (64, true), "i128" => (128, true),
+        _ => return None,
+    };
+    // The positive maximum, eg 255 for `u8`, 127 for `i8` or 128 for `-i8`.
+    let max = match (bits, signed) {
+        (128, false) => u128::MAX,
+        (_, false) => (1 << bits) - 1,
+        (_, true) => (1 << (bits - 1)) - 1 + negated as u128,
+    };
+    Some(max)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::flag_oversized_numbers as flag;
+    use super::super::super::lexeme::{LexemeKind,UnexpectedReason};
+    use super::super::super::test_helpers::lx;
+    use super::UNEXPECTED as X;
+    use LexemeKind::{NumberBinary as B,NumberDecimal as D,NumberHex as H};
+    use LexemeKind::NumberDecimalFloat as F;
+    use LexemeKind::{NumberOctal as O,Punctuation as P};
+    use LexemeKind::{IdentifierFreeword as I,IdentifierKeyword as K};
+    use LexemeKind::{IdentifierStdType as S,WhitespaceTrimmable as W};
+    use UnexpectedReason::OversizedNumber as Z;
+
+    // Flags a number, and returns its new kind and the reasons.
+    fn check(
+        kind: LexemeKind,
+        snippet: &'static str,
+    ) -> (LexemeKind, Vec<(usize, UnexpectedReason)>) {
+        let mut lexemes = vec![lx(kind,0,snippet)];
+        let reasons = flag(&mut lexemes);
+        (lexemes[0].kind, reasons)
+    }
+
+    #[test]
+    fn flag_oversized_numbers_correct() {
+        assert_eq!(check(D, "256u8"),     (X, vec![(0,Z)]));
+        assert_eq!(check(D, "128i8"),     (X, vec![(0,Z)]));
+        assert_eq!(check(D, "65_536u16"), (X, vec![(0,Z)]));
+        assert_eq!(check(H, "0x1_0000_0000u32"), (X, vec![(0,Z)]));
+        assert_eq!(check(B, "0b1_0000_0000u8"),  (X, vec![(0,Z)]));
+        assert_eq!(check(O, "0o400u8"),   (X, vec![(0,Z)]));
+        assert_eq!(check(D, "9223372036854775808isize"), (X, vec![(0,Z)]));
+        assert_eq!(check(D, "2_5_6_u8"),  (X, vec![(0,Z)])); // underscores
+        // One more than `u128::MAX`, unsuffixed.
+        assert_eq!(check(H, "0x1_0000_0000_0000_0000_0000_0000_0000_0000"),
+            (X, vec![(0,Z)]));
+        assert_eq!(check(H, "0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_F"),
+            (X, vec![(0,Z)]));
+        assert_eq!(check(D, "340282366920938463463374607431768211456"),
+            (X, vec![(0,Z)]));
+    }
+
+    #[test]
+    fn flag_oversized_numbers_incorrect() {
+        assert_eq!(check(D, "255u8"),     (D, vec![])); // `u8::MAX`
+        assert_eq!(check(D, "127i8"),     (D, vec![])); // `i8::MAX`
+        assert_eq!(check(D, "0u8"),       (D, vec![])); // zero
+        assert_eq!(check(D, "0_0_1u8"),   (D, vec![])); // leading zeros
+        assert_eq!(check(H, "0xFFu8"),    (H, vec![]));
+        assert_eq!(check(B, "0b1111_1111u8"), (B, vec![]));
+        assert_eq!(check(O, "0o377u8"),   (O, vec![]));
+        assert_eq!(check(H, "0xFF_u8"),   (H, vec![])); // an underscore
+        // `u128::MAX`, unsuffixed and suffixed.
+        assert_eq!(check(H, "0xFFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF"),
+            (H, vec![]));
+        assert_eq!(check(D, "340282366920938463463374607431768211455u128"),
+            (D, vec![]));
+        // Floats are never flagged, including integers with a float suffix.
+        assert_eq!(check(F, "1e999"),     (F, vec![]));
+        assert_eq!(check(F, "1000.0"),    (F, vec![]));
+        assert_eq!(check(F, "99999999999999999999999999999999999999999f64"),
+            (F, vec![]));
+    }
+
+    #[test]
+    fn flag_oversized_numbers_negated() {
+        // -128i8 is `i8::MIN`, so is fine.
+        let mut lexemes = [lx(P,0,"-"), lx(D,1,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![]);
+        // -129i8 is too small.
+        let mut lexemes = [lx(P,0,"-"), lx(D,1,"129i8")];
+        assert_eq!(flag(&mut lexemes), vec![(1,Z)]);
+        assert_eq!(lexemes[1].kind, X);
+        // - 128i8, with a space, is negated too.
+        let mut lexemes = [lx(P,0,"-"), lx(W,1," "), lx(D,2,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![]);
+        // x - -128i8, where the second `-` is unary.
+        let mut lexemes = [lx(I,0,"x"), lx(W,1," "), lx(P,2,"-"), lx(W,3," "),
+                           lx(P,4,"-"), lx(D,5,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![]);
+        // return -128i8, after a Keyword.
+        let mut lexemes = [lx(K,0,"return"), lx(W,6," "), lx(P,7,"-"),
+                           lx(D,8,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![]);
+        // -256u8 is still too large, because `u8` is unsigned.
+        let mut lexemes = [lx(P,0,"-"), lx(D,1,"256u8")];
+        assert_eq!(flag(&mut lexemes), vec![(1,Z)]);
+    }
+
+    #[test]
+    fn flag_oversized_numbers_binary_minus() {
+        // x-128i8 subtracts, so 128 is too large for `i8`.
+        let mut lexemes = [lx(I,0,"x"), lx(P,1,"-"), lx(D,2,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![(2,Z)]);
+        // 1 - 128i8, after a number.
+        let mut lexemes = [lx(D,0,"1"), lx(W,1," "), lx(P,2,"-"), lx(W,3," "),
+                           lx(D,4,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![(4,Z)]);
+        // f()-128i8 and a[0]-128i8, after a `)` or `]`.
+        let mut lexemes = [lx(P,0,")"), lx(P,1,"-"), lx(D,2,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![(2,Z)]);
+        let mut lexemes = [lx(P,0,"]"), lx(P,1,"-"), lx(D,2,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![(2,Z)]);
+        // self-128i8, after the Keyword `self`.
+        let mut lexemes = [lx(K,0,"self"), lx(P,4,"-"), lx(D,5,"128i8")];
+        assert_eq!(flag(&mut lexemes), vec![(5,Z)]);
+    }
+
+    #[test]
+    fn flag_oversized_numbers_needs_a_suffix() {
+        // `256 u8` is not a suffixed literal, so 256 is checked against u128.
+        let mut lexemes = [lx(D,0,"256"), lx(S,4,"u8")];
+        assert_eq!(flag(&mut lexemes), vec![]);
+        assert_eq!(lexemes[0].kind, D);
+    }
+
+    #[test]
+    fn flag_oversized_numbers_will_not_panic() {
+        assert_eq!(flag(&mut []), vec![]); // no Lexemes
+        assert_eq!(check(H, "0x"), (H, vec![])); // no digits
+        assert_eq!(check(H, "0x_u8"), (H, vec![])); // no digits, and a suffix
+        assert_eq!(check(D, "1_u8"), (D, vec![])); // trailing underscore
+        let mut lexemes = [lx(D,0,"256u8"), lx(S,6,"u8")];
+        assert_eq!(flag(&mut lexemes), vec![(0,Z)]);
+    }
+}
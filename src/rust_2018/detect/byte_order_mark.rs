@@ -0,0 +1,63 @@
+//! Detects a UTF-8 byte order mark at the very start of the input.
+
+use super::super::lexeme::LexemeKind;
+use super::is_char_start;
+const DETECTED: LexemeKind = LexemeKind::ByteOrderMark;
+const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
+
+/// Detects a UTF-8 byte order mark at the very start of the input.
+/// 
+/// Some editors save a file with a leading `U+FEFF` (bytes `EF BB BF`), which
+/// rustc strips before lexing. It is only a byte order mark at position `0`.
+/// Anywhere else, `U+FEFF` is not valid Rust, and is left as ‘Unidentifiable’.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `chr` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `chr` is `0` and `orig` starts with a byte order mark,
+/// `detect_byte_order_mark()` returns `LexemeKind::ByteOrderMark` and `3`.  
+/// Otherwise, `detect_byte_order_mark()` returns `LexemeKind::Undetected`
+/// and `0`.
+pub fn detect_byte_order_mark(
+    orig: &str,
+    chr: usize,
+) -> (
+    LexemeKind,
+    usize,
+) {
+    if chr != 0 || ! is_char_start(orig, chr) { return UNDETECTED }
+    if orig.starts_with('\u{FEFF}') { (DETECTED, 3) } else { UNDETECTED }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::detect_byte_order_mark as detect;
+    use super::DETECTED as D;
+    use super::UNDETECTED as U;
+
+    #[test]
+    fn detect_byte_order_mark_correct() {
+        assert_eq!(detect("\u{FEFF}", 0), (D,3));
+        assert_eq!(detect("\u{FEFF}fn main() {}", 0), (D,3));
+        assert_eq!(detect("\u{FEFF}\u{FEFF}", 0), (D,3)); // only the first
+    }
+
+    #[test]
+    fn detect_byte_order_mark_incorrect() {
+        assert_eq!(detect("fn main() {}", 0), U); // no BOM
+        assert_eq!(detect(" \u{FEFF}", 1), U); // not at the start
+        assert_eq!(detect("\u{FEFF}\u{FEFF}", 3), U); // not at the start
+        assert_eq!(detect("\u{FFFE}", 0), U); // a UTF-16 little-endian BOM
+    }
+
+    #[test]
+    fn detect_byte_order_mark_will_not_panic() {
+        assert_eq!(detect("", 0), U); // empty string
+        assert_eq!(detect("\u{FEFF}", 1), U); // part way into the BOM
+        assert_eq!(detect("\u{FEFF}", 3), U); // right on the end
+        assert_eq!(detect("abc", 100), U); // way out of range
+    }
+}
@@ -1,14 +1,20 @@
 //! Detects a `char` literal, like `'A'` or `'\u{03aB}'`.
 
+use alloc::string::ToString;
+
 use super::super::lexeme::LexemeKind;
+use super::{ascii_or_tilde,is_char_start};
+const BYTE:  LexemeKind = LexemeKind::CharacterByte;
 const HEX:  LexemeKind = LexemeKind::CharacterHex;
 const PLAIN:  LexemeKind = LexemeKind::CharacterPlain;
 const UNICODE:  LexemeKind = LexemeKind::CharacterUnicode;
+const UNEXPECTED:  LexemeKind = LexemeKind::Unexpected;
 const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
 
 /// Detects a `char` literal, like `'A'` or `'\u{03aB}'`.
 /// 
-/// @TODO `b` prefix, eg `b'A'`
+/// The `b` prefix, eg `b'A'` or `b'\xFF'`, gives a Byte char. A byte can not
+/// be written as a unicode escape, so `b'\u{41}'` is `LexemeKind::Unexpected`.
 /// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
@@ -17,6 +23,9 @@ const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
 /// ### Returns
 /// If `chr` begins a valid looking char literal, `detect_character()` returns
 /// the appropriate `LexemeKind::Character*` and the position after it ends.  
+/// A 7-bit char code which is out of range, like `'\x81'`, is clearly meant
+/// to be a char, so `detect_character()` returns `LexemeKind::Unexpected` and
+/// the position after it ends.  
 /// Otherwise, `detect_character()` returns `LexemeKind::Undetected` and `0`.
 pub fn detect_character(
     orig: &str,
@@ -25,11 +34,22 @@ pub fn detect_character(
     LexemeKind,
     usize,
 ) {
+    // If `chr` is part way into a multibyte char, bail out!
+    if ! is_char_start(orig, chr) { return UNDETECTED }
+    // A lowercase "b" followed by a single-quote could begin a Byte char. Any
+    // 8-bit char code is in range for a byte, but a unicode escape is not.
+    if ascii_or_tilde(orig, chr) == "b" && ascii_or_tilde(orig, chr+1) == "'" {
+        return match detect_character(orig, chr + 1) {
+            UNDETECTED => UNDETECTED,
+            (UNICODE, end) => (UNEXPECTED, end),
+            (_, end) => (BYTE, end),
+        }
+    }
     // Avoid panicking, if there would not be enough room for a char.
     let len = orig.len();
     if len < chr + 3 { return UNDETECTED } // chr + ' + A + '
     // If the current char is not a single-quote, then it does not begin a char.
-    let c0 = get_aot(orig, chr);
+    let c0 = ascii_or_tilde(orig, chr);
     if c0 != "'" { return UNDETECTED }
     // Get the next char, even if it’s not ascii.
     let mut c1_end = chr + 2;
@@ -40,34 +60,37 @@ pub fn detect_character(
     // If the next char is not a backslash:
     if c1 != "\\" {
         return
-            // If `c1` is a single quote:
-            if c1 == "'"
-                // We have found the string "''", which is not a valid char.
-                { UNDETECTED }
-            // Otherwise, if the char directly after `c1` is not a single quote:
-            else if get_aot(orig, c1_end) != "'"
-                // We have probably found a label, like "'static".
+            // If `c1` is a single quote, we have found the string "''", which
+            // is not a valid char. Or if the char directly after `c1` is not a
+            // single quote, we have probably found a label, like "'static".
+            if c1 == "'" || ascii_or_tilde(orig, c1_end) != "'"
                 { UNDETECTED }
             // Otherwise, this is a valid char literal, like "'A'" or "'±'".
             else { (PLAIN, c1_end + 1) }
     }
 
     // Now we know `c1` is a backslash, if the char after it is...
-    match get_aot(orig, chr+2) {
+    match ascii_or_tilde(orig, chr+2) {
         // ...one of Rust’s simple backslashable chars:
         "n" | "r" | "t" | "\\" | "0" | "\"" | "'" =>
             // Advance four places if the char after that is a single-quote.
             if len >= chr + 4
-            && get_aot(orig, chr+3) == "'"
+            && ascii_or_tilde(orig, chr+3) == "'"
                 { (PLAIN, chr + 4) } else { UNDETECTED },
         // ...lowercase x, signifying a 7-bit char code:
         "x" =>
-            // Advance 6 places if the chars after that are 0-7 and 0-9A-Fa-f.
+            // Advance 6 places if the chars after that are two hex digits and
+            // a single-quote. The first digit must be 0-7, or the char code is
+            // above 0x7F, so the char is out of range.
             if len >= chr + 6
-            && get_aot(orig, chr+3).chars().all(|c| c >= '0' && c <= '7')
-            && get_aot(orig, chr+4).chars().all(|c| c.is_ascii_hexdigit())
-            && get_aot(orig, chr+5) == "'"
-                { (HEX, chr + 6) } else { UNDETECTED },
+            && ascii_or_tilde(orig, chr+3).chars()
+                .all(|c| c.is_ascii_hexdigit())
+            && ascii_or_tilde(orig, chr+4).chars()
+                .all(|c| c.is_ascii_hexdigit())
+            && ascii_or_tilde(orig, chr+5) == "'" {
+                if ("0"..="7").contains(&ascii_or_tilde(orig, chr+3))
+                    { (HEX, chr + 6) } else { (UNEXPECTED, chr + 6) }
+            } else { UNDETECTED },
         // ...lowercase u, signifying a unicode char code:
         "u" =>
             // Advance to the position after the closing single-quote, if valid.
@@ -79,9 +102,6 @@ pub fn detect_character(
     }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, c: usize) -> &str { orig.get(c..c+1).unwrap_or("~") }
-
 // 24-bit Unicode character code, 1 to 6 digits, eg '\u{f}' to '\u{10abCD}'.
 fn detect_unicode_char(
     orig: &str,
@@ -93,13 +113,15 @@ fn detect_unicode_char(
 ) {
     // If `orig` is not even long enough for the shortest form, '\u{0}', or if
     // the "'\u" is not followed by an open curly bracket, this is not a char.
-    if len < chr + 7 || get_aot(orig, chr+3) != "{" { return UNDETECTED }
+    if len < chr + 7 || ascii_or_tilde(orig, chr+3) != "{" { return UNDETECTED }
     // Initialise variables which will be modified by the loop, below.
     let mut found_closing_curly_bracket = false;
     let mut codepoint = "".to_string();
     // Loop through the characters after "'\u{", to a maximum "'\u{123456}".
+    // Positions 4 to 9 may be the 6 hex digits, so 10 is the last place that
+    // the "}" can be found.
     for i in 4..11 {
-        let c = get_aot(orig, chr+i);
+        let c = ascii_or_tilde(orig, chr+i);
         if c == "}" { found_closing_curly_bracket = true; break }
         // If the current character is 0-9A-Fa-f, append it to `codepoint`.
         if c.chars().all(|c| c.is_ascii_hexdigit()) {
@@ -113,16 +135,19 @@ fn detect_unicode_char(
     // Get the position of the character which should be a closing single-quote.
     let l = codepoint.len() + 5;
     // If that char is not a single-quote, this is not a char.
-    if get_aot(orig, chr+l) != "'" { return UNDETECTED }
+    if ascii_or_tilde(orig, chr+l) != "'" { return UNDETECTED }
     // Parse the codepoint into a number.
     match u32::from_str_radix(&codepoint, 16) {
-        // This error conditional is actually unreachable, because we used
-        // `is_ascii_hexdigit()`, above.
+        // This error conditional is only reached by an empty "'\u{}'", because
+        // we used `is_ascii_hexdigit()`, above.
         Err(_) => UNDETECTED,
-        // Unicode escapes must be at most 10FFFF. If it’s not above that,
-        // return the position after the closing single-quote.
-        Ok(value) =>
-            if value > 0x10FFFF { UNDETECTED } else { (UNICODE, chr + l + 1) },
+        // Unicode escapes must be at most 10FFFF, and not a surrogate, from
+        // D800 to DFFF. If `value` is a valid `char`, return the position
+        // after the closing single-quote.
+        Ok(value) => match char::from_u32(value) {
+            Some(_) => (UNICODE, chr + l + 1),
+            None => UNDETECTED,
+        },
     }
 }
 
@@ -130,23 +155,12 @@ fn detect_unicode_char(
 #[cfg(test)]
 mod tests {
     use super::detect_character as detect;
+    use super::BYTE as B;
     use super::HEX as H;
     use super::PLAIN as P;
     use super::UNICODE as C;
     use super::UNDETECTED as U;
-
-    #[test]
-    fn get_ascii_or_tilde() {
-        // Test the logic of `get_aot()`.
-        let orig = "abcd€f";
-        assert_eq!(orig.get(0..0+1).unwrap_or("~"), "a");
-        assert_eq!(orig.get(1..1+1).unwrap_or("~"), "b");
-        assert_eq!(orig.get(4..4+1).unwrap_or("~"), "~"); // start of €
-        assert_eq!(orig.get(5..5+1).unwrap_or("~"), "~"); // middle of €
-        assert_eq!(orig.get(7..7+1).unwrap_or("~"), "f");
-        assert_eq!(orig.get(8..8+1).unwrap_or("~"), "~"); // right on the end
-        assert_eq!(orig.get(9..9+1).unwrap_or("~"), "~"); // past the end
-    }
+    use super::UNEXPECTED as X;
 
     #[test]
     fn detect_character_correct() {
@@ -174,6 +188,7 @@ mod tests {
         assert_eq!(detect("'\\t' ", 0), (P,4)); // '\t'
         assert_eq!(detect("'\\\\'", 0), (P,4)); // '\\'
         assert_eq!(detect(" '\\0'", 1), (P,5)); // '\0'
+        assert_eq!(detect("'\\0'9", 0), (P,4)); // '\0' then a 9 outside the quotes
         assert_eq!(detect("'\\\"'", 0), (P,4)); // '\"'
         assert_eq!(detect("'\\''", 0),  (P,4)); // '\''
         // 7-bit '\x00'.
@@ -183,6 +198,8 @@ mod tests {
         assert_eq!(detect(orig, 5),  U);    // '
         let orig = " - '\\x0f' - ";
         assert_eq!(detect(orig, 3), (H,9)); // '\x0f' advance 6 places
+        assert_eq!(detect("'\\x00'", 0), (H,6)); // minimum
+        assert_eq!(detect("'\\x7F'", 0), (H,6)); // maximum
         // Unicode '\u{0}'.
         assert_eq!(detect("'\\u{0}'",         0), (C,7));  // '\u{0}'
         assert_eq!(detect(" '\\u{C}'",        1), (C,8));  // '\u{C}'
@@ -194,11 +211,22 @@ mod tests {
         assert_eq!(detect(" '\\u{00000}'",    1), (C,12)); // '\u{00000}'
         assert_eq!(detect("'\\u{100abC}'",    0), (C,12)); // '\u{100abC}'
         assert_eq!(detect(" - '\\u{10FFFF}'", 3), (C,15)); // maximum
+        assert_eq!(detect("'\\u{D7FF}'",       0), (C,10)); // just below surrogates
+        assert_eq!(detect("'\\u{E000}'",       0), (C,10)); // just above surrogates
+        assert_eq!(detect("'\\u{00000F}'",    0), (C,12)); // 6 digits, zero padded
+        assert_eq!(detect("'\\u{000000}'",    0), (C,12)); // 6 digits, all zero
         assert_eq!(detect("'\\u{123}'€",      0), (C,9));  // '\u{123}'
         let orig = "'\\u{30aF}'";
         assert_eq!(detect(orig, 0), (C,10)); // '\u{30aF}' advance to end
         assert_eq!(detect(orig, 1),  U);     // \u{30aF}'
         assert_eq!(detect(orig, 2),  U);     // u{30aF}'
+        // Byte 'b'.
+        let orig = "x b'A' y";
+        assert_eq!(detect(orig, 2), (B,6)); // b'A' advance four places
+        assert_eq!(detect(orig, 3), (P,6)); // 'A' without its prefix
+        assert_eq!(detect("b'\\n'", 0),   (B,5)); // b'\n'
+        assert_eq!(detect("b'\\x00'", 0), (B,7)); // b'\x00' minimum
+        assert_eq!(detect("b'\\xFF'", 0), (B,7)); // b'\xFF' maximum
     }
 
     #[test]
@@ -212,11 +240,21 @@ mod tests {
         assert_eq!(detect("'\\~'", 0),  U); // '\~' no such backslash
         assert_eq!(detect(" '\\x'", 1), U); // '\x' would start 7-bit
         assert_eq!(detect("'\\u'", 0),  U); // '\x' would start unicode
+        // A null char is just '\0'. Rust has no octal escapes, and two
+        // digits need '\x', so '\0' followed by a digit is not a char.
+        assert_eq!(detect("'\\09'", 0),  U); // '\09' has an extra digit
+        assert_eq!(detect("'\\00'", 0),  U); // '\00' should be '\x00'
+        assert_eq!(detect("'\\012'", 0), U); // '\012' is not octal
+        assert_eq!(detect("'\\0x41'", 0), U); // '\0x41' is not hex
         // Incorrect 7-bit '\x00'.
         assert_eq!(detect("'\\x3' - ", 0), U); // '\x3' has no 2nd digit
         assert_eq!(detect("'\\x3f - ", 0), U); // '\x3f has no end quote
         assert_eq!(detect("'\\x0G'", 0),   U); // '\x0G' is not valid
-        assert_eq!(detect("'\\x81'", 0),   U); // '\x81' is out of range
+        assert_eq!(detect("'\\x8' - ", 0), U); // '\x8' has no 2nd digit
+        // Out of range 7-bit '\x80', which is clearly meant to be a char.
+        assert_eq!(detect("'\\x81'", 0), (X,6)); // '\x81' is out of range
+        assert_eq!(detect("'\\xFF'", 0), (X,6)); // '\xFF' is out of range
+        assert_eq!(detect("'\\x80'x", 0), (X,6)); // '\x80' is out of range
         // Incorrect Unicode '\u{0}'.
         assert_eq!(detect("'\\uxyz", 0), U); // missing {0}
         assert_eq!(detect("'\\u{xyz", 0), U); // missing 0}
@@ -228,9 +266,22 @@ mod tests {
         assert_eq!(detect("'\\u{abcde", 0), U); // missing }' at end
         assert_eq!(detect("'\\u{12i4}'", 0), U); // not a hex digit
         assert_eq!(detect("'\\u{100abCd}'", 0), U); // too long
+        assert_eq!(detect("'\\u{0000000}'", 0), U); // 7 digits, even if zero
+        assert_eq!(detect("'\\u{000000F}'", 0), U); // 7 digits, zero padded
+        assert_eq!(detect("'\\u{}'", 0), U); // no digits
+        assert_eq!(detect("'\\U{41}'", 0), U); // capital U is not unicode
+        assert_eq!(detect("'\\U{0}'", 0), U); // even the shortest form
         assert_eq!(detect("'\\u{1234}", 0), U); // missing ' at end
         assert_eq!(detect("'\\u{1234} ", 0), U); // no closing quote
         assert_eq!(detect("'\\u{110000}'", 0), U); // too high
+        assert_eq!(detect("'\\u{D800}'", 0), U); // lowest surrogate
+        assert_eq!(detect("'\\u{dfff}'", 0), U); // highest surrogate
+        assert_eq!(detect("'\\u{00DABC}'", 0), U); // zero padded surrogate
+        // Incorrect Byte b'A'.
+        assert_eq!(detect("b'\\u{41}'", 0), (X,9)); // no unicode in a byte
+        assert_eq!(detect("b''", 0),  U); // b'' missing char
+        assert_eq!(detect("b'ab'", 0), U); // b'ab' is more like a label
+        assert_eq!(detect("c'A'", 0), U); // c'A' is not a char prefix
     }
 
     #[test]
@@ -1,13 +1,28 @@
 //! Detects a multiline or inline comment.
 
 use super::super::lexeme::LexemeKind;
+use super::{ascii_or_tilde,is_char_start};
+const DOC_INLINE: LexemeKind = LexemeKind::CommentDocInline;
+const DOC_MULTILINE: LexemeKind = LexemeKind::CommentDocMultiline;
 const INLINE:  LexemeKind = LexemeKind::CommentInline;
 const MULTILINE: LexemeKind = LexemeKind::CommentMultiline;
 const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
 
+/// The maximum number of nested levels in a multiline comment, counting the
+/// outermost `/*` as level 1.
+/// 
+/// Deeper nesting is almost certainly malicious, so the comment is rejected.
+pub const MAX_COMMENT_DEPTH: usize = 256;
 
 /// Detects a multiline or inline comment.
 /// 
+/// Doc comments, like `/// foo`, `//! foo`, `/** foo */` and `/*! foo */`, are
+/// detected as `CommentDocInline` or `CommentDocMultiline`. As in rustc,
+/// `//// foo`, `/*** foo */` and the empty `/**/` are not doc comments.
+/// 
+/// A multiline comment which is nested more than `MAX_COMMENT_DEPTH` levels
+/// deep is not detected.
+/// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
 /// * `chr` The character position in `orig` to look at
@@ -26,11 +41,11 @@ pub fn detect_comment(
     // If the current char is the last or second-from-last in `orig`, it does not
     // begin a comment.
     let len = orig.len();
-    if len < chr + 2 { return UNDETECTED }
+    if len < chr + 2 || ! is_char_start(orig, chr) { return UNDETECTED }
     // If the current char is not a forward slash, it does not begin a comment.
-    if get_aot(orig, chr) != "/" { return UNDETECTED }
+    if ascii_or_tilde(orig, chr) != "/" { return UNDETECTED }
     // If the next char is:
-    match get_aot(orig, chr+1) {
+    match ascii_or_tilde(orig, chr+1) {
         // Also a forward slash, `chr` could begin an inline comment.
         "/" => detect_inline_comment(orig, chr, len),
         // An asterisk, `chr` could begin a multiline comment.
@@ -40,9 +55,6 @@ pub fn detect_comment(
     }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, c: usize) -> &str { orig.get(c..c+1).unwrap_or("~") }
-
 fn detect_inline_comment(
     orig: &str,
     chr: usize,
@@ -51,6 +63,10 @@ fn detect_inline_comment(
     LexemeKind,
     usize,
 ) {
+    // "//!" and "///" begin a doc comment, but "////" does not.
+    let c2 = ascii_or_tilde(orig, chr + 2);
+    let kind = if c2 == "!" || c2 == "/" && ascii_or_tilde(orig, chr + 3) != "/"
+        { DOC_INLINE } else { INLINE };
     // Step through each char, from `chr + 2` to the end of the input code.
     let mut i = chr + 2;
     while i < len - 1 {
@@ -60,13 +76,13 @@ fn detect_inline_comment(
         // If this char is a newline:
         if &orig[i..j] == "\n" { //@TODO maybe recognise Windows style "\r\n"?
             // Advance to the start of the newline.
-            return (INLINE, i)
+            return (kind, i)
         }
         // Step forward, ready for the next iteration.
         i = j;
     }
     // No newline was found, so advance to the end of the input code.
-    (INLINE, len)
+    (kind, len)
 }
 
 fn detect_multiline_comment(
@@ -77,6 +93,11 @@ fn detect_multiline_comment(
     LexemeKind,
     usize,
 ) {
+    // "/*!" and "/**" begin a doc comment, but "/***" and "/**/" do not.
+    let c2 = ascii_or_tilde(orig, chr + 2);
+    let c3 = ascii_or_tilde(orig, chr + 3);
+    let kind = if c2 == "!" || c2 == "*" && c3 != "*" && c3 != "/"
+        { DOC_MULTILINE } else { MULTILINE };
     // Track how deep into a nested multiline comment we are.
     let mut depth = 0;
     // Slightly hacky way to to skip forward while looping.
@@ -88,13 +109,13 @@ fn detect_multiline_comment(
         while !orig.is_char_boundary(j) { j += 1 }
         let c0 = &orig[i..j];
         // Get the next character, or tilde if it’s non-ascii.
-        let c1 = get_aot(orig, j);
+        let c1 = ascii_or_tilde(orig, j);
         // If this char is an asterisk, and the next is a forward slash:
         if c0 == "*" && c1 == "/" {
             // If the depth is zero (so we are at the outermost nesting level):
             if depth == 0 {
                 // Advance to the end of the "*/".
-                return (MULTILINE, i + 2)
+                return (kind, i + 2)
             // Otherwise we are some way inside a nested multiline comment:
             } else {
                 // Decrement the nesting-depth.
@@ -104,8 +125,11 @@ fn detect_multiline_comment(
             }
         // If this char is a forward slash, and the next is an asterisk:
         } else if c0 == "/" && c1 == "*" {
-            // Increment the nesting-depth.
+            // Increment the nesting-depth. If that goes beyond the limit, this
+            // is not a multiline comment. `depth` does not count the outermost
+            // level, hence the `+ 1`.
             depth += 1;
+            if depth + 1 > MAX_COMMENT_DEPTH { return UNDETECTED }
             // Skip the asterisk (avoids confusion in "/*/*/ */ */").
             j += 1;
         }
@@ -120,6 +144,9 @@ fn detect_multiline_comment(
 #[cfg(test)]
 mod tests {
     use super::detect_comment as detect;
+    use super::MAX_COMMENT_DEPTH;
+    use super::DOC_INLINE as DI;
+    use super::DOC_MULTILINE as DM;
     use super::INLINE as I;
     use super::MULTILINE as M;
     use super::UNDETECTED as U;
@@ -162,9 +189,9 @@ mod tests {
         assert_eq!(detect(orig, 3), (M,10)); // /*ok<NL>*/ adv. seven places
         assert_eq!(detect(orig, 4),  U);     // *ok<NL>*/z
         // Doc.
-        assert_eq!(detect("/** Here's a doc */", 0), (M,19));
-        assert_eq!(detect("/**A/*A*/*/", 0),         (M,11));
-        assert_eq!(detect("/**A/*A'*/*/", 0),        (M,12));
+        assert_eq!(detect("/** Here's a doc */", 0), (DM,19));
+        assert_eq!(detect("/**A/*A*/*/", 0),         (DM,11));
+        assert_eq!(detect("/**A/*A'*/*/", 0),        (DM,12));
         // To end of `orig`.
         let orig = "abc/*ok*/";
         assert_eq!(detect(orig, 2),  U);    // c/*ok*/
@@ -181,6 +208,25 @@ mod tests {
         assert_eq!(detect(orig, 4),  U); // *nope*
     }
   
+    #[test]
+    fn detect_comment_doc() {
+        // Inline.
+        assert_eq!(detect("/// outer\nx", 0), (DI,9)); // outer doc
+        assert_eq!(detect("//! inner\nx", 0), (DI,9)); // inner doc
+        assert_eq!(detect("///", 0),          (DI,3));  // empty outer doc
+        assert_eq!(detect("//!", 0),          (DI,3));  // empty inner doc
+        assert_eq!(detect("//// not doc", 0), (I,12));  // four slashes
+        assert_eq!(detect("//!/ doc", 0),     (DI,8));  // "//!" then "/"
+        // Multiline.
+        assert_eq!(detect("/** outer */", 0), (DM,12)); // outer doc
+        assert_eq!(detect("/*! inner */", 0), (DM,12)); // inner doc
+        assert_eq!(detect("/***/", 0),        (M,5));   // three asterisks
+        assert_eq!(detect("/*** no */", 0),   (M,10));  // three asterisks
+        assert_eq!(detect("/**/", 0),         (M,4));   // empty, not doc
+        assert_eq!(detect("/*!*/", 0),        (DM,5));  // empty inner doc
+        assert_eq!(detect("/** a\r\n * b */", 0), (DM,14)); // CRLF inside
+    }
+
     #[test]
     fn detect_comment_multiline_nested() {
         // Single nesting.
@@ -204,11 +250,44 @@ mod tests {
         assert_eq!(detect(orig, 0), (M,11)); // /*/* */* */ edge case is 4th *
         assert_eq!(detect(orig, 1),  U);     // */* */* */
         assert_eq!(detect(orig, 2), (M,7));  // /* */
+        // Ending exactly at the end of input, with no trailing newline.
+        let orig = "/* a */";
+        assert_eq!(detect(orig, 0), (M,7));  // depth 0, ends at `len`
+        let orig = "/* /* */ */";
+        assert_eq!(detect(orig, 0), (M,11)); // depth 1, ends at `len`
+        let orig = "/* /* /* */ */ */";
+        assert_eq!(detect(orig, 0), (M,17)); // depth 2, ends at `len`
+        let orig = "x/*/**/*/";
+        assert_eq!(detect(orig, 1), (M,9));  // tightly nested, ends at `len`
+        assert_eq!(detect("/* /* */ */ ", 0),  (M,11)); // trailing space
+        assert_eq!(detect("/* /* */ */\n", 0), (M,11)); // trailing newline
+        assert_eq!(detect("/* /* */", 0),  U);  // outer level not closed
+        assert_eq!(detect("/* /* */ *", 0), U); // last byte is not "/"
         // Invalid nesting.
         let orig = "/* outer /* inner */ missing trailing slash *";
         assert_eq!(detect(orig, 0),  U);
     }
 
+    #[test]
+    fn detect_comment_multiline_depth_limit() {
+        // Returns `levels` nested multiline comments, like "/*/**/*/" for 2.
+        fn nested(levels: usize) -> String {
+            "/*".repeat(levels) + &"*/".repeat(levels)
+        }
+        assert_eq!(MAX_COMMENT_DEPTH, 256);
+        let orig = nested(255);
+        assert_eq!(detect(&orig, 0), (M,1020)); // 255 levels is fine
+        let orig = nested(256);
+        assert_eq!(detect(&orig, 0), (M,1024)); // 256 levels is the limit
+        let orig = nested(257);
+        assert_eq!(detect(&orig, 0),  U);       // 257 levels is too deep
+        let orig = nested(300);
+        assert_eq!(detect(&orig, 0),  U);       // 300 levels is too deep
+        // Sequential comments at the same level do not add to the depth.
+        let orig = format!("/*{}*/", "/* */".repeat(300));
+        assert_eq!(detect(&orig, 0), (M,1504));
+    }
+
     #[test]
     fn detect_comment_will_not_panic() {
         // Near the end of `orig`.
@@ -1,20 +1,30 @@
 //! Detects a Freeword like `foo`, Keyword like `if` or StdType like `i8`.
 
-use super::super::lexeme::LexemeKind;
+use super::super::edition::Edition;
+use super::super::lexeme::{KeywordCategory,LexemeKind};
+use super::{ascii_or_tilde,is_char_start};
 const FREEWORD: LexemeKind = LexemeKind::IdentifierFreeword;
 const KEYWORD: LexemeKind = LexemeKind::IdentifierKeyword;
 const STD_TYPE: LexemeKind = LexemeKind::IdentifierStdType;
 const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
+const UNEXPECTED: LexemeKind = LexemeKind::Unexpected;
 
 /// Detects a Freeword like `foo`, Keyword like `if` or StdType like `i8`.
 /// 
 /// ‘Freeword’ is what we’re calling any identifier which is not a Keyword or
 /// StdType. For example the variable `i` or function name `get_widgets`.
 ///
+/// A lone `_` is never an Identifier, wherever it appears (including at the
+/// end of input), so `detect_punctuation()` picks it up as a wildcard. But an
+/// underscore followed by an underscore, letter or digit is a Freeword, like
+/// `__`, `_1` or `_a`.
+///
 /// Because of the way it’s used, `String` is categorised as a Freeword: @TODO maybe revisit this
 /// `let s = String::from("hello");`
 ///
-/// @TODO raw Identifiers, which have the `r#` prefix
+/// A raw Identifier, like `r#fn` or `r#foo`, is always a Freeword, even if
+/// the word after its `r#` prefix is a Keyword. `crate`, `self`, `super` and
+/// `Self` can never be raw Identifiers, so `r#crate` is `Unexpected`.
 ///
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
@@ -31,12 +41,44 @@ pub fn detect_identifier(
     LexemeKind,
     usize,
 ) {
-    // If the current char is past the last char in `orig`, bail out!
+    detect_identifier_for(orig, chr, Edition::Rust2018)
+}
+
+/// Detects an Identifier, like `detect_identifier()`, for a given edition.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to `edition`
+/// * `chr` The character position in `orig` to look at
+/// * `edition` The Rust edition, which decides whether words like `async`
+///   are Keywords
+///
+/// ### Returns
+/// See `detect_identifier()`.
+pub fn detect_identifier_for(
+    orig: &str,
+    chr: usize,
+    edition: Edition,
+) -> (
+    LexemeKind,
+    usize,
+) {
+    // If `chr` is past the last char in `orig`, or part way into a multibyte
+    // char, bail out!
     let len = orig.len();
-    if chr >= len { return UNDETECTED }
+    if ! is_char_start(orig, chr) { return UNDETECTED }
+
+    // If `chr` begins "r#" and then an Identifier (but not another raw one),
+    // this is a raw Identifier.
+    if orig.get(chr..chr+2) == Some("r#") {
+        let (kind, end) = detect_identifier_for(orig, chr + 2, edition);
+        if kind != UNDETECTED.0 && ! orig[chr+2..end].contains('#') {
+            return if RAW_FORBIDDEN.contains(&&orig[chr+2..end])
+                { (UNEXPECTED, end) } else { (FREEWORD, end) }
+        }
+    }
 
     // If the current char is not [_a-zA-Z], it does not begin an Identifier.
-    let c0 = get_aot(orig, chr);
+    let c0 = ascii_or_tilde(orig, chr);
     let c0_u = c0 == "_"; // true if the current char is an underscore
     if ! c0_u && ! c0.chars().all(char::is_alphabetic) { return UNDETECTED }
     // If the current char is the last in the input code:
@@ -55,50 +97,51 @@ pub fn detect_identifier(
         return if c0_u { UNDETECTED } else { (FREEWORD, chr + 1) }
     }
 
-    // Step through each char, from two places after `chr` to the end of input.
-    for i in chr+2..len {
-        let c = get_aot(orig, i);
-        // If this char is not an underscore, letter or digit, we detected
-        // a Freeword, Keyword or StdType.
-        if c != "_" && ! c.chars().all(char::is_alphanumeric) {
-            return (categorize_identifier(&orig[chr..i]), i)
-        }
-    }
-    // We reached the last char in the input code, so we detected a Freeword,
-    // Keyword or StdType.
-    (categorize_identifier(&orig[chr..len]), len)
+    // Scan the bytes from two places after `chr`, stopping at the first which
+    // is not an underscore, ascii letter or digit. This is much quicker than
+    // slicing `orig` for every char of a very long Identifier. Any non-ascii
+    // byte ends the Identifier, so `end` is always on a char boundary.
+    let end = orig.as_bytes()[chr+2..].iter()
+        .position(|b| *b != b'_' && ! b.is_ascii_alphanumeric())
+        .map_or(len, |offset| chr + 2 + offset);
+    // We detected a Freeword, Keyword or StdType.
+    (categorize_identifier_for(&orig[chr..end], edition), end)
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, c: usize) -> &str { orig.get(c..c+1).unwrap_or("~") }
-
-fn categorize_identifier(s: &str) -> LexemeKind {
-    // Look up the identifier in the `KEYWORDS` array.
-    if KEYWORDS.contains(&s) { return KEYWORD }
+/// Categorises a complete Identifier as a Freeword, Keyword or StdType.
+/// 
+/// `async`, `await`, `dyn` and `try` only became Keywords in Rust 2018, so for
+/// `Edition::Rust2015` they are categorised as Freewords.
+pub fn categorize_identifier_for(s: &str, edition: Edition) -> LexemeKind {
+    // Look up the identifier in the `*_KEYWORDS` arrays, unless it was not
+    // yet a Keyword in this edition. The Weak Keywords `macro_rules` and
+    // `union` are only Keywords in a definition, which `tag_macro_rules()` and
+    // `tag_union()` look for later.
+    let category = keyword_category(s);
+    if category.is_some() && category != Some(KeywordCategory::Weak) {
+        let is_2018_only = KEYWORDS_SINCE_2018.contains(&s);
+        if edition != Edition::Rust2015 || ! is_2018_only { return KEYWORD }
+    }
     // Look up the identifier in the `STD_TYPE` array.
     if PRIMATIVE_TYPES.contains(&s) { return STD_TYPE }
     // Not recognised as a Keyword or StdType, so must be a Freeword.
     FREEWORD
 }
 
-const KEYWORDS: [&str; 52] = [
-    "abstract",
+// Keywords which can only ever be used as Keywords.
+const STRICT_KEYWORDS: [&str; 38] = [
     "as",
     "async",
     "await",
-    "become",
-    "box",
     "break",
     "const",
     "continue",
     "crate",
-    "do",
     "dyn",
     "else",
     "enum",
     "extern",
     "false",
-    "final",
     "fn",
     "for",
     "if",
@@ -106,13 +149,10 @@ const KEYWORDS: [&str; 52] = [
     "in",
     "let",
     "loop",
-    "macro",
     "match",
     "mod",
     "move",
     "mut",
-    "override",
-    "priv",
     "pub",
     "ref",
     "return",
@@ -124,19 +164,94 @@ const KEYWORDS: [&str; 52] = [
     "super",
     "trait",
     "true",
-    "try",
     "type",
-    "typeof",
-    "union",
     "unsafe",
-    "unsized",
     "use",
-    "virtual",
     "where",
     "while",
+];
+
+// Keywords which are not used yet, but are reserved for future use.
+const RESERVED_KEYWORDS: [&str; 13] = [
+    "abstract",
+    "become",
+    "box",
+    "do",
+    "final",
+    "macro",
+    "override",
+    "priv",
+    "try",
+    "typeof",
+    "unsized",
+    "virtual",
     "yield",
 ];
 
+// Keywords which only have a special meaning in certain contexts.
+const WEAK_KEYWORDS: [&str; 2] = [
+    "macro_rules",
+    "union",
+];
+
+/// Categorises a Keyword as Strict, Reserved or Weak.
+/// 
+/// `keyword_category()` has no edition input, so it gives each Keyword’s
+/// category from Rust 2018 on, where `dyn` is Strict.
+/// `categorize_identifier_for()` decides which words were not yet Keywords in
+/// `Edition::Rust2015`.
+/// 
+/// ### Returns
+/// `keyword_category()` returns `None` if `s` is not a Keyword.
+pub fn keyword_category(s: &str) -> Option<KeywordCategory> {
+    if STRICT_KEYWORDS.contains(&s) { return Some(KeywordCategory::Strict) }
+    if RESERVED_KEYWORDS.contains(&s) { return Some(KeywordCategory::Reserved) }
+    if WEAK_KEYWORDS.contains(&s) { return Some(KeywordCategory::Weak) }
+    None
+}
+
+/// Returns `true` if the Identifier from `chr` to `end` is a reserved prefix.
+/// 
+/// From Rust 2024, an Identifier which is immediately followed by `#`, `"` or
+/// `'` is reserved for future syntax, like `k#foo` or `f"{x}"`. The prefixes
+/// which are already in use, like the `r` of the raw Identifier `r#foo` or
+/// the `b` of `b'x'`, are not reserved.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code
+/// * `chr` The position where the Identifier starts
+/// * `end` The position after the Identifier ends
+pub fn is_reserved_prefix(orig: &str, chr: usize, end: usize) -> bool {
+    let next = orig.get(end..end+1).unwrap_or("~");
+    (next == "#" || next == "\"" || next == "'")
+        && ! PREFIXES_IN_USE.contains(&orig.get(chr..end).unwrap_or(""))
+}
+
+// Prefixes which come before `#`, `"` or `'` in valid code.
+const PREFIXES_IN_USE: [&str; 5] = [
+    "b",
+    "br",
+    "c",
+    "cr",
+    "r",
+];
+
+// Keywords which can not be used as raw Identifiers, like `r#crate`.
+const RAW_FORBIDDEN: [&str; 4] = [
+    "crate",
+    "self",
+    "Self",
+    "super",
+];
+
+// Keywords which were ordinary identifiers in Rust 2015.
+const KEYWORDS_SINCE_2018: [&str; 4] = [
+    "async",
+    "await",
+    "dyn",
+    "try",
+];
+
 const PRIMATIVE_TYPES: [&str; 18] = [
     "bool",
     "char",
@@ -162,10 +277,14 @@ const PRIMATIVE_TYPES: [&str; 18] = [
 #[cfg(test)]
 mod tests {
     use super::detect_identifier as detect;
+    use super::detect_identifier_for as detect_for;
+    use super::is_reserved_prefix;
+    use super::Edition;
     use super::FREEWORD as F;
     use super::KEYWORD as K;
     use super::STD_TYPE as S;
     use super::UNDETECTED as U;
+    use super::UNEXPECTED as X;
 
     #[test]
     fn detect_identifier_correct() {
@@ -229,7 +348,6 @@ mod tests {
         assert_eq!(detect("match",    0), (K,5));
         assert_eq!(detect("super",    0), (K,5));
         assert_eq!(detect("trait",    0), (K,5));
-        assert_eq!(detect("union",    0), (K,5));
         assert_eq!(detect("where",    0), (K,5));
         assert_eq!(detect("while",    0), (K,5));
         assert_eq!(detect("yield",    0), (K,5));
@@ -247,6 +365,9 @@ mod tests {
         assert_eq!(detect("override", 0), (K,8));
         assert_eq!(detect("'static",  0),  U); // special case
         assert_eq!(detect("'static",  1), (K,7));
+        // A Weak Keyword which depends on its context.
+        assert_eq!(detect("macro_rules", 0), (F,11)); // see `tag_macro_rules()`
+        assert_eq!(detect("union", 0), (F,5)); // see `tag_union()`
 
         // PrimativeTypes basic.
         let orig = "bool i128 isize";
@@ -284,6 +405,101 @@ mod tests {
         assert_eq!(detect(orig, 2), U); // 2X is not a valid Identifier
     }
 
+    #[test]
+    fn detect_identifier_underscores() {
+        // A lone "_" is the wildcard, which is Punctuation, not an Identifier.
+        assert_eq!(detect("_", 0),     U);    // _ at the end of input
+        assert_eq!(detect("_ ", 0),    U);    // _ followed by a space
+        assert_eq!(detect("(_, x)", 1), U);   // _ in a tuple pattern
+        assert_eq!(detect("(_, x)", 4), (F,5)); // x
+        assert_eq!(detect("a _", 2),   U);    // _ after other code
+        // An underscore followed by an underscore, letter or digit.
+        assert_eq!(detect("__", 0),    (F,2)); // __
+        assert_eq!(detect("_1", 0),    (F,2)); // _1
+        assert_eq!(detect("_a", 0),    (F,2)); // _a
+        assert_eq!(detect("___ ", 0),  (F,3)); // ___
+        assert_eq!(detect("_a_1;", 0), (F,4)); // _a_1
+    }
+
+    #[test]
+    fn detect_identifier_edition() {
+        // Words which became Keywords in Rust 2018.
+        let orig = "async await dyn try";
+        assert_eq!(detect_for(orig, 0,  Edition::Rust2015), (F,5));  // async
+        assert_eq!(detect_for(orig, 6,  Edition::Rust2015), (F,11)); // await
+        assert_eq!(detect_for(orig, 12, Edition::Rust2015), (F,15)); // dyn
+        assert_eq!(detect_for(orig, 16, Edition::Rust2015), (F,19)); // try
+        assert_eq!(detect_for(orig, 0,  Edition::Rust2018), (K,5));  // async
+        assert_eq!(detect_for(orig, 6,  Edition::Rust2018), (K,11)); // await
+        assert_eq!(detect_for(orig, 12, Edition::Rust2018), (K,15)); // dyn
+        assert_eq!(detect_for(orig, 16, Edition::Rust2018), (K,19)); // try
+        // Other Keywords and StdTypes are the same in both editions.
+        assert_eq!(detect_for("fn", 0, Edition::Rust2015), (K,2));
+        assert_eq!(detect_for("u8", 0, Edition::Rust2015), (S,2));
+        assert_eq!(detect_for("foo", 0, Edition::Rust2015), (F,3));
+        // `detect_identifier()` assumes Rust 2018.
+        assert_eq!(detect("async", 0), (K,5));
+    }
+
+    #[test]
+    fn detect_identifier_reserved_prefix() {
+        assert!(is_reserved_prefix("foo#bar", 0, 3));    // foo then #
+        assert!(is_reserved_prefix("foo\"x\"", 0, 3));  // foo then "
+        assert!(is_reserved_prefix("k'x'", 0, 1));       // k then '
+        assert!(is_reserved_prefix("a.foo#", 2, 5));     // foo then #
+        assert!(! is_reserved_prefix("foo #bar", 0, 3)); // space after foo
+        assert!(! is_reserved_prefix("foo", 0, 3));      // end of input
+        assert!(! is_reserved_prefix("r#foo", 0, 1));    // raw Identifier
+        assert!(! is_reserved_prefix("b'x'", 0, 1));     // byte char
+        assert!(! is_reserved_prefix("br\"x\"", 0, 2)); // raw byte string
+        assert!(! is_reserved_prefix("c\"x\"", 0, 1));  // C string
+        assert!(! is_reserved_prefix("", 0, 0));         // empty string
+        assert!(! is_reserved_prefix("€", 1, 2));        // not on a boundary
+    }
+
+    #[test]
+    fn detect_identifier_raw() {
+        // Raw Identifiers are always Freewords.
+        assert_eq!(detect("r#fn", 0),    (F,4));
+        assert_eq!(detect("r#match", 0), (F,7));
+        assert_eq!(detect("r#u8", 0),    (F,4));
+        assert_eq!(detect("r#foo_1 x", 0), (F,7));
+        assert_eq!(detect_for("r#async", 0, Edition::Rust2015), (F,7));
+        // These four Keywords can never be raw Identifiers.
+        assert_eq!(detect("r#crate", 0), (X,7));
+        assert_eq!(detect("r#self", 0),  (X,6));
+        assert_eq!(detect("r#Self", 0),  (X,6));
+        assert_eq!(detect("r#super", 0), (X,7));
+        // But longer words which start with them are fine.
+        assert_eq!(detect("r#selfish", 0), (F,9));
+        // Not raw Identifiers, so just the `r`.
+        assert_eq!(detect("r#", 0),      (F,1));
+        assert_eq!(detect("r#_", 0),     (F,1)); // `_` is not an Identifier
+        assert_eq!(detect("r#1", 0),     (F,1));
+        assert_eq!(detect("r#\"x\"#", 0), (F,1)); // a Raw string
+        assert_eq!(detect("r#r#x", 0),   (F,1)); // only one `r#` prefix
+        assert_eq!(detect("r #x", 0),    (F,1));
+        assert_eq!(detect("br#x", 0),    (F,2));
+    }
+
+    #[test]
+    fn detect_identifier_very_long() {
+        // A 100KB Identifier, then a non-identifier char.
+        let orig = "a_1".repeat(100_000 / 3) + "_9 ";
+        assert_eq!(detect(&orig, 0), (F,100_001));
+        assert_eq!(detect(&orig, 49_998), (F,100_001)); // from the middle
+        // A 100KB Identifier which ends at the end of input.
+        let orig = "Z".repeat(100_000);
+        assert_eq!(detect(&orig, 0), (F,100_000));
+        // A 100KB Identifier which ends at a non-ascii char.
+        let orig = "x".repeat(100_000) + "€";
+        assert_eq!(detect(&orig, 0), (F,100_000));
+        // Keywords and StdTypes are still categorised, at any position.
+        let orig = "x".repeat(100_000) + " while u128";
+        assert_eq!(detect(&orig, 100_001), (K,100_006));
+        assert_eq!(detect(&orig, 100_007), (S,100_011));
+    }
+
     #[test]
     fn detect_identifier_will_not_panic() {
         // Near the end of `orig`.
@@ -1,9 +1,104 @@
 //! Functions for detecting Lexemes in Rust 2018 code.
 
+pub mod byte_order_mark;
 pub mod character;
 pub mod comment;
 pub mod identifier;
 pub mod number;
 pub mod punctuation;
+pub mod shebang;
 pub mod string;
+pub mod unexpected;
 pub mod whitespace;
+
+// Returns `true` if `chr` is the start of a character in `orig`. A `chr` which
+// is past the end of `orig`, or part way into a multibyte character, can never
+// begin a Lexeme, so every `detect_*()` function bails out early if not.
+fn is_char_start(orig: &str, chr: usize) -> bool {
+    chr < orig.len() && orig.is_char_boundary(chr)
+}
+
+/// Returns the ascii character at a position, or tilde if invalid or non-ascii.
+/// 
+/// The `detect_*()` functions use `ascii_or_tilde()` to peek at the byte at
+/// `c` without risking a panic. A tilde is returned if `c` is at or past the
+/// end of `orig`, or if `c..c+1` does not fall on char boundaries — that is,
+/// if `c` is part way into a multibyte character, or is the first byte of one.
+/// 
+/// Tilde can never begin or continue any of the Lexemes which are detected
+/// this way, so callers can treat it as “no match” without special-casing.
+pub(crate) fn ascii_or_tilde(orig: &str, c: usize) -> &str {
+    orig.get(c..=c).unwrap_or("~")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::ascii_or_tilde;
+    use super::super::lexeme::LexemeKind;
+    use super::super::lexemize::Detector;
+    use super::byte_order_mark::detect_byte_order_mark;
+    use super::character::detect_character;
+    use super::comment::detect_comment;
+    use super::identifier::detect_identifier;
+    use super::number::detect_number;
+    use super::punctuation::detect_punctuation;
+    use super::shebang::detect_shebang;
+    use super::string::detect_string;
+    use super::unexpected::detect_unexpected;
+    use super::whitespace::detect_whitespace;
+    const U: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
+
+    #[test]
+    fn get_ascii_or_tilde() {
+        let orig = "abcd€f";
+        assert_eq!(ascii_or_tilde(orig, 0), "a");
+        assert_eq!(ascii_or_tilde(orig, 1), "b");
+        assert_eq!(ascii_or_tilde(orig, 4), "~"); // start of €
+        assert_eq!(ascii_or_tilde(orig, 5), "~"); // middle of €
+        assert_eq!(ascii_or_tilde(orig, 6), "~"); // end of €
+        assert_eq!(ascii_or_tilde(orig, 7), "f");
+        assert_eq!(ascii_or_tilde(orig, 8), "~"); // right on the end
+        assert_eq!(ascii_or_tilde(orig, 9), "~"); // past the end
+        assert_eq!(ascii_or_tilde("", 0), "~");   // empty `orig`
+        assert_eq!(ascii_or_tilde("~", 0), "~");  // a real tilde
+        assert_eq!(ascii_or_tilde("a", usize::MAX), "~"); // will not overflow
+    }
+
+    #[test]
+    fn detect_at_every_byte_will_not_panic() {
+        let detectors: [Detector; 10] = [
+            detect_byte_order_mark,
+            detect_character,
+            detect_comment,
+            detect_identifier,
+            detect_number,
+            detect_punctuation,
+            detect_shebang,
+            detect_string,
+            detect_unexpected,
+            detect_whitespace,
+        ];
+        // `€` is 3 bytes and `𝕏` is 4 bytes, so most offsets are mid-char.
+        let fixtures = [
+            "€𝕏",
+            "'€' '𝕏' \"€\" r#\"𝕏\"# // €\n/* 𝕏 */ a€ 1𝕏 €\u{a0}𝕏 \"𝕏",
+            "x€",
+            "𝕏",
+            "\u{FEFF}x\u{FEFF}",
+            "\u{FEFF}#!€\n𝕏",
+        ];
+        for orig in fixtures.iter() {
+            for chr in 0..orig.len() + 2 {
+                for detector in detectors.iter() {
+                    let result = detector(orig, chr);
+                    if ! orig.is_char_boundary(chr) || chr >= orig.len() {
+                        assert_eq!(result, U, "{:?} at {}", orig, chr);
+                    } else if result != U {
+                        assert!(orig.is_char_boundary(result.1), "{:?} at {}", orig, chr);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,39 @@
 //! Detects a number literal, like `12.34` or `0b100100`.
 
 use super::super::lexeme::LexemeKind;
+use super::{ascii_or_tilde,is_char_start};
 const BINARY:  LexemeKind = LexemeKind::NumberBinary;
 const DECIMAL: LexemeKind = LexemeKind::NumberDecimal;
+const FLOAT:   LexemeKind = LexemeKind::NumberDecimalFloat;
 const HEX:     LexemeKind = LexemeKind::NumberHex;
 const OCTAL:   LexemeKind = LexemeKind::NumberOctal;
 const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
 
 /// Detects a number literal, like `12.34` or `0b100100`.
 /// 
+/// An integer type suffix, like the `u8` of `1u8`, is part of the number.
+/// Underscores may come between the digits and the suffix, so `1_u8`,
+/// `0xFF_u16` and `1_000_i32` are each a single number. A `0b`, `0o` or `0x`
+/// literal still needs at least one digit before its suffix, so `0xu8` is not
+/// a number, but `0x0u8` is. A longer identifier, like the `u8x` of `1u8x`,
+/// is not a suffix, so the number ends before it. `split_number_suffix()`
+/// separates a number’s digits from its suffix.
+/// 
+/// A float type suffix, `f32` or `f64`, is part of a decimal number, whether
+/// it is written as an integer, like `1f64`, or as a float, like `1.0f32`. But
+/// a trailing dot never takes a suffix, because `1.f32` would be a field
+/// access. There are no hex floats, so the `f32` of `0x1f32` is just digits.
+/// 
+/// A decimal number with a `.`, an exponent or a float suffix is a
+/// `NumberDecimalFloat`. Any other decimal number is a `NumberDecimal`. An
+/// integer suffix is never taken after a dot or an exponent, so `1.0u8` is the
+/// `NumberDecimalFloat` `1.0`, followed by the Identifier `u8`.
+/// 
+/// Underscores may come straight after the `0b`, `0o` or `0x` prefix, like
+/// `0x__C_`, as long as there is a digit somewhere, so `0x__` is not a number.
+/// A decimal number must start with a digit, so `0_0` is a number, but `_0`
+/// is left for `detect_identifier()`.
+/// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
 /// * `chr` The character position in `orig` to look at
@@ -24,18 +49,19 @@ pub fn detect_number(
     LexemeKind,
     usize,
 ) {
-    // If the current char is past the last char in `orig`, bail out!
+    // If `chr` is past the last char in `orig`, or part way into a multibyte
+    // char, bail out!
     let len = orig.len();
-    if chr >= len { return UNDETECTED }
-    let c = get_aot(orig, chr);
+    if ! is_char_start(orig, chr) { return UNDETECTED }
+    let c = ascii_or_tilde(orig, chr);
     // If the current char is not a digit, then it does not begin a number.
-    if c < "0" || c > "9" { return UNDETECTED }
+    if !("0"..="9").contains(&c) { return UNDETECTED }
     // If the digit is the input code’s last character, we’re finished.
     if len == chr + 1 { return (DECIMAL, len) }
     // If the digit at `chr` is not zero, this is a decimal number:
     if c != "0" { return detect_number_decimal(orig, chr, len) }
     // If the digit is zero, and the next char is "b", "x" or "o":
-    match get_aot(orig, chr + 1) {
+    match ascii_or_tilde(orig, chr + 1) {
         // Use the binary, hex or octal detector function, as appropriate.
         "b" => detect_number_binary(orig, chr, len),
         "x" => detect_number_hex(orig, chr, len),
@@ -45,8 +71,128 @@ pub fn detect_number(
     }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, c: usize) -> &str { orig.get(c..c+1).unwrap_or("~") }
+/// Detects a tuple index, like the `1` of `x.1`, which is just ascii digits.
+/// 
+/// `detect_number()` has no context, so it would read the `1e2` of `x.1e2`
+/// as a float, or the `0.0` of `x.0.0` as a float. A Number which directly
+/// follows a lone `.` is a tuple index, so `tag_tuple_index()` uses this
+/// stricter detector to find where the index really ends. There are no
+/// underscores, exponents or dots in a tuple index.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `chr` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `chr` begins a tuple index, `detect_number_tuple_index()` returns
+/// `LexemeKind::NumberDecimal` and the position after the last digit.  
+/// Otherwise, `detect_number_tuple_index()` returns `LexemeKind::Undetected`
+/// and `0`.
+pub fn detect_number_tuple_index(
+    orig: &str,
+    chr: usize,
+) -> (
+    LexemeKind,
+    usize,
+) {
+    if ! is_char_start(orig, chr) { return UNDETECTED }
+    let digits = orig[chr..].bytes().take_while(|b| b.is_ascii_digit()).count();
+    if digits == 0 { UNDETECTED } else { (DECIMAL, chr + digits) }
+}
+
+/// Detects a float with no integer part, like `.5` or `.5e3`, which Rust does
+/// not allow.
+/// 
+/// Only used if `LexemizeOptions::allow_leading_dot_float` is set, for tools
+/// which want to recognise the intent of `.5`, rather than `.` and `5`. The
+/// `.` must be followed by a digit, and the rest must be a decimal number with
+/// no dot, like `5` or `5e3`.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code
+/// * `chr` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `chr` begins a float with a leading dot, `detect_number_leading_dot()`
+/// returns `LexemeKind::NumberDecimalFloat` and the position after it ends.  
+/// Otherwise, `detect_number_leading_dot()` returns `LexemeKind::Undetected`
+/// and `0`.
+pub fn detect_number_leading_dot(
+    orig: &str,
+    chr: usize,
+) -> (
+    LexemeKind,
+    usize,
+) {
+    if ! is_char_start(orig, chr) || orig.as_bytes().get(chr) != Some(&b'.')
+    || ! orig.as_bytes().get(chr + 1).is_some_and(u8::is_ascii_digit) {
+        return UNDETECTED
+    }
+    // The part after the dot must be a decimal integer, with an optional
+    // exponent. Something like `.5.3` is rejected entirely. This is a float,
+    // so an integer suffix, like the `u8` of `.5u8`, is not part of it.
+    match detect_number(orig, chr + 1) {
+        (DECIMAL | FLOAT, end) if ! orig[chr+1..end].contains('.') => {
+            let (number, suffix) = split_number_suffix(&orig[chr+1..end]);
+            let is_integer_suffix = INTEGER_SUFFIXES.contains(&suffix);
+            (FLOAT, if is_integer_suffix { chr + 1 + number.len() } else { end })
+        },
+        _ => UNDETECTED,
+    }
+}
+
+/// Splits a number literal into its digits and its type suffix, like `1_u8`
+/// into `1_` and `u8`.
+/// 
+/// ### Arguments
+/// * `snippet` A number literal, as found by `detect_number()`
+/// 
+/// ### Returns
+/// `split_number_suffix()` returns the part before the suffix, and the
+/// suffix, which is empty if there is none.
+pub fn split_number_suffix(snippet: &str) -> (&str, &str) {
+    // A hex number’s digits may end `f32` or `f64`, so it has no float suffix.
+    let float_suffixes: &[&str] =
+        if snippet.starts_with("0x") { &[] } else { &FLOAT_SUFFIXES };
+    match INTEGER_SUFFIXES.iter().chain(float_suffixes).find(|suffix|
+        snippet.len() > suffix.len() && snippet.ends_with(*suffix)) {
+        Some(suffix) => snippet.split_at(snippet.len() - suffix.len()),
+        None => (snippet, ""),
+    }
+}
+
+// Type suffixes which may follow an integer literal, in any base.
+const INTEGER_SUFFIXES: [&str; 12] = [
+    "i128",
+    "i16",
+    "i32",
+    "i64",
+    "i8",
+    "isize",
+    "u128",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "usize",
+];
+
+// Type suffixes which may follow a decimal literal.
+const FLOAT_SUFFIXES: [&str; 2] = [
+    "f32",
+    "f64",
+];
+
+// Returns the position after one of `suffixes`, if it starts at `end`.
+// Otherwise, returns `end`. A longer identifier, like `u8x` or `u8_`, is not a
+// suffix.
+fn take_suffix(orig: &str, end: usize, suffixes: &[&str]) -> usize {
+    let rest = orig.get(end..).unwrap_or("");
+    let is_suffix = |suffix: &&&str| rest.starts_with(**suffix)
+        && ! rest.as_bytes().get(suffix.len()).is_some_and(|b|
+            b.is_ascii_alphanumeric() || *b == b'_' || ! b.is_ascii());
+    suffixes.iter().find(is_suffix).map_or(end, |suffix| end + suffix.len())
+}
 
 fn detect_number_binary(
     orig: &str,
@@ -58,20 +204,21 @@ fn detect_number_binary(
 ) {
     let mut has_digit = false; // binary literals must have at least one digit
     for i in chr+2..len { // +2, because we already found "0b"
-        let c = get_aot(orig, i);
+        let c = ascii_or_tilde(orig, i);
         // If the character is an underscore, do nothing.
         if c == "_" {
         // Otherwise, if this char is a binary digit:
         } else if c == "0" || c == "1" {
             has_digit = true;
         // Otherwise, if this is a digit (can only be 2 to 9, here) or a dot:
-        } else if (c >= "0" && c <= "9") || c == "." {
+        } else if ("0"..="9").contains(&c) || c == "." {
             // Reject the whole of 0b101021, don’t just accept the 0b1010 part.
             // And reject the whole of 0b11.1, don’t just accept the 0b11 part.
             return UNDETECTED
         } else {
-            // Advance to the character after the binary number.
-            return if has_digit { (BINARY, i) } else { UNDETECTED }
+            // Advance to the character after the binary number, and its suffix.
+            if ! has_digit { return UNDETECTED }
+            return (BINARY, take_suffix(orig, i, &INTEGER_SUFFIXES))
         }
     }
     // We’ve reached the end of the input string.
@@ -94,7 +241,7 @@ fn detect_number_decimal(
     let mut pos_s = 0; // helps detect numbers with invalid signs, like "10E+"
 
     for i in chr+1..len { // +1, because we already found a digit, 0 to 9
-        let c = get_aot(orig, i);
+        let c = ascii_or_tilde(orig, i);
 
         // If the character is an underscore:
         if c == "_" {
@@ -108,6 +255,13 @@ fn detect_number_decimal(
             // Guard against a dangling plus or minus sign, eg "7.5e-".
             pos_s = i + 1
 
+        // If this char and the next are both dots, like the range "1..2":
+        } else if c == "." && ascii_or_tilde(orig, i + 1) == "." {
+            // The number ends before the range operator, so "1..2" is "1", then
+            // "..", then "2", not "1." followed by ".2".
+            return if i == pos_e || i == pos_s || i == pos_eu
+                { UNDETECTED } else { decimal(orig, has_dot || has_e, i) }
+
         // If we haven’t found a decimal point yet, and this char is a dot:
         } else if ! has_dot && c == "." {
             // Reject a number like "1e2.3", where the exponent contains a dot.
@@ -125,18 +279,29 @@ fn detect_number_decimal(
             pos_e = i + 1;
 
         // Otherwise, if this char is not a digit:
-        } else if c < "0" || c > "9" {
+        } else if !("0"..="9").contains(&c) {
             // We’ve reached a char which can’t be part of a valid number.
             // Numbers can’t end "e", "E", "+", "-", "e_" or "E_".
             return if i == pos_e || i == pos_s || i == pos_eu
-                { UNDETECTED } else { (DECIMAL, i) }
+                { UNDETECTED } else { decimal(orig, has_dot || has_e, i) }
         }
     }
 
     // We’ve reached the end of the input string.
     // Numbers can’t end "e", "E", "+", "-", "e_" or "E_".
     if len == pos_e || len == pos_s || len == pos_eu
-        { UNDETECTED } else { (DECIMAL, len) }
+        { UNDETECTED } else { decimal(orig, has_dot || has_e, len) }
+}
+
+// Returns `NumberDecimalFloat` and the position after any float suffix, if a
+// decimal number which ends at `end` has a dot, an exponent or a float suffix.
+// Otherwise, returns `NumberDecimal` and the position after any integer suffix.
+fn decimal(orig: &str, has_dot_or_e: bool, end: usize) -> (LexemeKind, usize) {
+    // A trailing dot takes no suffix, because `1.f32` would be a field access.
+    if orig.as_bytes()[end-1] == b'.' { return (FLOAT, end) }
+    let float_end = take_suffix(orig, end, &FLOAT_SUFFIXES);
+    if has_dot_or_e || float_end != end { return (FLOAT, float_end) }
+    (DECIMAL, take_suffix(orig, end, &INTEGER_SUFFIXES))
 }
 
 fn detect_number_hex(
@@ -149,19 +314,19 @@ fn detect_number_hex(
 ) {
     let mut has_digit = false; // hex literals must have at least one digit
     for i in chr+2..len { // +2, because we already found "0x"
-        let c = get_aot(orig, i);
+        let c = ascii_or_tilde(orig, i);
         // If the character is an underscore, do nothing.
         if c == "_" {
         // Otherwise, if this char is a hex digit 0-9A-Fa-f:
         } else if c.chars().all(|c| c.is_ascii_hexdigit()) {
             has_digit = true;
-        // Otherwise, if this char is a point:
-        } else if c == "." {
-            // Reject the whole of 0xAB.C, don’t just accept the 0xAB part.
-            return UNDETECTED
         } else {
-            // Advance to the character after the hex number.
-            return if has_digit { (HEX, i) } else { UNDETECTED }
+            // Advance to the character after the hex number, and its suffix.
+            // There are no hex floats, so a point is not part of it. Accept
+            // the 0xAB part of 0xAB.C, and leave .C to be lexed as a field
+            // access.
+            if ! has_digit { return UNDETECTED }
+            return (HEX, take_suffix(orig, i, &INTEGER_SUFFIXES))
         }
     }
     // We’ve reached the end of the input string.
@@ -178,19 +343,20 @@ fn detect_number_octal(
 ) {
     let mut has_digit = false; // octal literals must have at least one digit
     for i in chr+2..len { // +2, because we already found "0o"
-        let c = get_aot(orig, i);
+        let c = ascii_or_tilde(orig, i);
         // If the character is an underscore, do nothing.
         if c == "_" {
         // Otherwise, if this char is a digit 0-7:
-        } else if c >= "0" && c <= "7" {
+        } else if ("0"..="7").contains(&c) {
             has_digit = true;
         // Otherwise, if this char is a point:
         } else if c == "." {
             // Reject the whole of 0o56.7, don’t just accept the 0o56 part.
             return UNDETECTED
         } else {
-            // Advance to the character after the octal number.
-            return if has_digit { (OCTAL, i) } else { UNDETECTED }
+            // Advance to the character after the octal number, and its suffix.
+            if ! has_digit { return UNDETECTED }
+            return (OCTAL, take_suffix(orig, i, &INTEGER_SUFFIXES))
         }
     }
     // We’ve reached the end of the input string.
@@ -201,8 +367,11 @@ fn detect_number_octal(
 #[cfg(test)]
 mod tests {
     use super::detect_number as detect;
+    use super::detect_number_leading_dot as detect_dot;
+    use super::detect_number_tuple_index as detect_index;
     use super::BINARY as B;
     use super::DECIMAL as D;
+    use super::FLOAT as F;
     use super::HEX as H;
     use super::OCTAL as O;
     use super::UNDETECTED as U;
@@ -239,38 +408,48 @@ mod tests {
         assert_eq!(detect(orig, 1),   U);     // _5 can’t start numbers that way
         assert_eq!(detect(orig, 2),  (D,3));  // 5
         assert_eq!(detect(orig, 4),  (D,10)); // 012___
-        assert_eq!(detect(orig, 11), (D,16)); // 3_4_.
-        assert_eq!(detect(orig, 17), (D,29)); // 0_0.0_00__0_
+        assert_eq!(detect(orig, 11), (F,16)); // 3_4_.
+        assert_eq!(detect(orig, 17), (F,29)); // 0_0.0_00__0_
         // Float no exponent.
         let orig = "7.5 0.12 34. 00.0__0_00";
-        assert_eq!(detect(orig, 0),  (D,3));  // 7.5
+        assert_eq!(detect(orig, 0),  (F,3));  // 7.5
         assert_eq!(detect(orig, 1),   U);     // .5 is not a valid number
         assert_eq!(detect(orig, 2),  (D,3));  // 5
         assert_eq!(detect(orig, 3),   U);     // space
-        assert_eq!(detect(orig, 4),  (D,8));  // 0.12
-        assert_eq!(detect(orig, 9),  (D,12)); // 34. is valid
-        assert_eq!(detect(orig, 13), (D,23)); // 00.0__0_00
+        assert_eq!(detect(orig, 4),  (F,8));  // 0.12
+        assert_eq!(detect(orig, 9),  (F,12)); // 34. is valid
+        assert_eq!(detect(orig, 13), (F,23)); // 00.0__0_00
         // Here, each "123." exercises a different conditional branch.
         let orig = "123. 123.";
-        assert_eq!(detect(orig, 0), (D,4));   // 123. part way through input
-        assert_eq!(detect(orig, 5), (D,9));   // 123. reaches end of input
+        assert_eq!(detect(orig, 0), (F,4));   // 123. part way through input
+        assert_eq!(detect(orig, 5), (F,9));   // 123. reaches end of input
+        // Ranges, where the number must not take the first dot of "..".
+        let orig = "1..2 1..=2 1.0..2.0 1e2..3";
+        assert_eq!(detect(orig, 0),  (D,1));  // 1 then ..
+        assert_eq!(detect(orig, 3),  (D,4));  // 2
+        assert_eq!(detect(orig, 5),  (D,6));  // 1 then ..=
+        assert_eq!(detect(orig, 9),  (D,10)); // 2
+        assert_eq!(detect(orig, 11), (F,14)); // 1.0 then ..
+        assert_eq!(detect(orig, 16), (F,19)); // 2.0
+        assert_eq!(detect(orig, 20), (F,23)); // 1e2 then ..
+        assert_eq!(detect("1...2", 0), (D,1)); // 1 then ...
         // Float with exponent.
         let orig = "0e0 9E9 1e+2 4E-3 8E1+2 54.32E+10";
-        assert_eq!(detect(orig, 0),  (D,3));  // 0e0 is 0
-        assert_eq!(detect(orig, 4),  (D,7));  // 9E9 is 9000000000
-        assert_eq!(detect(orig, 8),  (D,12)); // 1e+2 is 100
-        assert_eq!(detect(orig, 13), (D,17)); // 4E-3 is 0.004
-        assert_eq!(detect(orig, 18), (D,21)); // the 8E1 part is accepted
-        assert_eq!(detect(orig, 24), (D,33)); // 54.32E+10 is 543200000000
+        assert_eq!(detect(orig, 0),  (F,3));  // 0e0 is 0
+        assert_eq!(detect(orig, 4),  (F,7));  // 9E9 is 9000000000
+        assert_eq!(detect(orig, 8),  (F,12)); // 1e+2 is 100
+        assert_eq!(detect(orig, 13), (F,17)); // 4E-3 is 0.004
+        assert_eq!(detect(orig, 18), (F,21)); // the 8E1 part is accepted
+        assert_eq!(detect(orig, 24), (F,33)); // 54.32E+10 is 543200000000
         let orig = "4_3.21e+10 43_.21e+10 43.2_1e+10 43.21_e+10 43.21e+_10 43.21e+1_0 43.21e+10_";
-        assert_eq!(detect(orig, 0),  (D,10)); // 4_3.21e+10 is ok .js
-        assert_eq!(detect(orig, 11), (D,21)); // 43_.21e+10 is invalid .js
-        assert_eq!(detect(orig, 22), (D,32)); // 43.2_1e+10 is ok .js
-        assert_eq!(detect(orig, 33), (D,43)); // 43.21_e+10 is invalid .js
-        assert_eq!(detect(orig, 44), (D,54)); // 43.21e+_10 is invalid .js
-        assert_eq!(detect(orig, 55), (D,65)); // 43.21e+1_0 is ok .js
-        assert_eq!(detect(orig, 66), (D,76)); // 43.21e+10_ is invalid .js
-        assert_eq!(detect("43.21e_10", 0), (D,9)); // 43.21e_10 is invalid .js
+        assert_eq!(detect(orig, 0),  (F,10)); // 4_3.21e+10 is ok .js
+        assert_eq!(detect(orig, 11), (F,21)); // 43_.21e+10 is invalid .js
+        assert_eq!(detect(orig, 22), (F,32)); // 43.2_1e+10 is ok .js
+        assert_eq!(detect(orig, 33), (F,43)); // 43.21_e+10 is invalid .js
+        assert_eq!(detect(orig, 44), (F,54)); // 43.21e+_10 is invalid .js
+        assert_eq!(detect(orig, 55), (F,65)); // 43.21e+1_0 is ok .js
+        assert_eq!(detect(orig, 66), (F,76)); // 43.21e+10_ is invalid .js
+        assert_eq!(detect("43.21e_10", 0), (F,9)); // 43.21e_10 is invalid .js
         // Hex.
         let orig = "0x09 0xA_b_ 0xAG 0x__C_";
         assert_eq!(detect(orig, 0),  (H,4));  // 0x09
@@ -279,6 +458,11 @@ mod tests {
         assert_eq!(detect(orig, 5),  (H,11)); // 0xA_b_ mixed case is ok
         assert_eq!(detect(orig, 12), (H,15)); // the 0xA part is accepted
         assert_eq!(detect(orig, 17), (H,23)); // 0x__C_
+        // Hex followed by a field access or tuple index, which ends before "."
+        let orig = "0xAB.len() 0xAB.0 0xAB.";
+        assert_eq!(detect(orig, 0),  (H,4));  // 0xAB then .len()
+        assert_eq!(detect(orig, 11), (H,15)); // 0xAB then .0
+        assert_eq!(detect(orig, 18), (H,22)); // 0xAB then .
         // Octal.
         let orig = "0o07 0o7_3_ 0o7a 0o__5_";
         assert_eq!(detect(orig, 0),  (O,4));  // 0o07
@@ -287,6 +471,21 @@ mod tests {
         assert_eq!(detect(orig, 5),  (O,11)); // 0o7_3_
         assert_eq!(detect(orig, 12), (O,15)); // the 0o7 part is accepted
         assert_eq!(detect(orig, 17), (O,23)); // 0o__5_
+        // Underscores may come before a type suffix, which is part of the
+        // number.
+        let orig = "1_u8 0xFF_u16 1_000_i32 0b1__u8 0o7_i64 1_ 1__";
+        assert_eq!(detect(orig, 0),  (D,4));  // 1_u8
+        assert_eq!(detect(orig, 5),  (H,13)); // 0xFF_u16
+        assert_eq!(detect(orig, 14), (D,23)); // 1_000_i32
+        assert_eq!(detect(orig, 24), (B,31)); // 0b1__u8
+        assert_eq!(detect(orig, 32), (O,39)); // 0o7_i64
+        assert_eq!(detect(orig, 40), (D,42)); // 1_ with no suffix
+        assert_eq!(detect(orig, 43), (D,46)); // 1__ with no suffix
+        // The shortest prefixed numbers with a suffix.
+        let orig = "0x0u8 0b1i8 0o7u8";
+        assert_eq!(detect(orig, 0),  (H,5));  // 0x0u8
+        assert_eq!(detect(orig, 6),  (B,11)); // 0b1i8
+        assert_eq!(detect(orig, 12), (O,17)); // 0o7u8
     }
 
     #[test]
@@ -296,7 +495,7 @@ mod tests {
         assert_eq!(detect(orig, 0),   U);     // 0b12 is not a valid number
         assert_eq!(detect(orig, 2),  (D,4));  // 12 is recognised as decimal
         assert_eq!(detect(orig, 5),   U);     // 0b11.1 is not a valid number
-        assert_eq!(detect(orig, 7),  (D,11)); // 11.1
+        assert_eq!(detect(orig, 7),  (F,11)); // 11.1
         assert_eq!(detect(orig, 12),  U);     // 0b is not a valid number
         assert_eq!(detect(orig, 15), (D,16)); // 0B11 is not valid, but 0 is
         assert_eq!(detect(orig, 20),  U);     // 0b___ is not a valid number
@@ -304,13 +503,13 @@ mod tests {
         // @TODO
         // Incorrect float no exponent.
         let orig = "1.2.3 .12 0..1";
-        assert_eq!(detect(orig, 0),  (D,3));  // 1.2
+        assert_eq!(detect(orig, 0),  (F,3));  // 1.2
         assert_eq!(detect(orig, 1),   U);     // .2 is not a valid number
-        assert_eq!(detect(orig, 2),  (D,5));  // 2.3
+        assert_eq!(detect(orig, 2),  (F,5));  // 2.3
         assert_eq!(detect(orig, 5),   U);     // space
         assert_eq!(detect(orig, 6),   U);     // .12 is not a valid number
         assert_eq!(detect(orig, 7),  (D,9));  // 12
-        assert_eq!(detect(orig, 10), (D,12)); // 0.
+        assert_eq!(detect(orig, 10), (D,11)); // 0 then the range operator
         assert_eq!(detect(orig, 11),  U);     // ..
         assert_eq!(detect(orig, 12),  U);     // .1
         assert_eq!(detect(orig, 13), (D,14)); // 1
@@ -336,7 +535,7 @@ mod tests {
         // Invalid hex.
         let orig = "0xGA 0xab.c 0x 0XAB 0x___";
         assert_eq!(detect(orig, 0),   U); // 0xGA is not a valid number
-        assert_eq!(detect(orig, 5),   U); // 0xab.c is not a valid number
+        assert_eq!(detect(orig, 5),  (H,9)); // the 0xab part of 0xab.c
         assert_eq!(detect(orig, 7),   U); // ab.c is valid, but not a number
         assert_eq!(detect(orig, 12),  U); // 0x is not a valid number
         assert_eq!(detect(orig, 15), (D,16)); // 0XAB is not valid, but 0 is
@@ -345,10 +544,18 @@ mod tests {
         let orig = "0oa7 0o56.7 0o 0O34 0o___";
         assert_eq!(detect(orig, 0),   U); // 0oa7 is not a valid number
         assert_eq!(detect(orig, 5),   U); // 0o56.7 is not a valid number
-        assert_eq!(detect(orig, 7),  (D,11)); // 56.7 is recognised as decimal
+        assert_eq!(detect(orig, 7),  (F,11)); // 56.7 is recognised as decimal
         assert_eq!(detect(orig, 12),  U); // 0o is not a valid number
         assert_eq!(detect(orig, 15), (D,16)); // 0O34 is not valid, but 0 is
         assert_eq!(detect(orig, 20),  U); // 0o___ is not a valid number
+        // A prefix followed directly by a suffix, with no digits.
+        let orig = "0xu8 0bu8 0ou8 0x_u8 0bi64 0ousize";
+        assert_eq!(detect(orig, 0),   U); // 0xu8 has no hex digits
+        assert_eq!(detect(orig, 5),   U); // 0bu8 has no binary digits
+        assert_eq!(detect(orig, 10),  U); // 0ou8 has no octal digits
+        assert_eq!(detect(orig, 15),  U); // 0x_u8 has just an underscore
+        assert_eq!(detect(orig, 21),  U); // 0bi64 has no binary digits
+        assert_eq!(detect(orig, 27),  U); // 0ousize has no octal digits
         // Number too large.
         // These numbers are larger than u128, so Rust won’t parse them.
         // However, detect_number() is just a scanner, and not that smart!
@@ -393,20 +600,22 @@ mod tests {
         assert_eq!(detect("1_1", 0),  (D,3)); // 1_1
         assert_eq!(detect("1__1", 0), (D,4)); // 1__1
         // Float, near the end of `orig`.
-        assert_eq!(detect("1.", 0),   (D,2)); // 1.
-        assert_eq!(detect("1.1", 0),  (D,3)); // 1.1
+        assert_eq!(detect("1.", 0),   (F,2)); // 1.
+        assert_eq!(detect("1..", 0),  (D,1)); // 1 then ..
+        assert_eq!(detect("1e..", 0),  U);    // 1e has no exponent value
+        assert_eq!(detect("1.1", 0),  (F,3)); // 1.1
         assert_eq!(detect("1e", 0),    U);    // 1
         assert_eq!(detect("1E", 0),    U);    // 1
-        assert_eq!(detect("1e1", 0),  (D,3)); // 1e1
-        assert_eq!(detect("1E1", 0),  (D,3)); // 1E1
-        assert_eq!(detect("1.e1", 0), (D,4)); // 1 // @TODO fix this!
-        assert_eq!(detect("1.E1", 0), (D,4)); // 1 // @TODO fix this!
+        assert_eq!(detect("1e1", 0),  (F,3)); // 1e1
+        assert_eq!(detect("1E1", 0),  (F,3)); // 1E1
+        assert_eq!(detect("1.e1", 0), (F,4)); // 1 // @TODO fix this!
+        assert_eq!(detect("1.E1", 0), (F,4)); // 1 // @TODO fix this!
         assert_eq!(detect("1.1e", 0),  U);    // rejected, no exponent value
         assert_eq!(detect("1.1E", 0),  U);    // rejected, no exponent value
-        assert_eq!(detect("1e+1", 0), (D,4)); // 1e+1
-        assert_eq!(detect("1E+1", 0), (D,4)); // 1E+1
-        assert_eq!(detect("1e-1", 0), (D,4)); // 1e-1
-        assert_eq!(detect("1E-1", 0), (D,4)); // 1E-1
+        assert_eq!(detect("1e+1", 0), (F,4)); // 1e+1
+        assert_eq!(detect("1E+1", 0), (F,4)); // 1E+1
+        assert_eq!(detect("1e-1", 0), (F,4)); // 1e-1
+        assert_eq!(detect("1E-1", 0), (F,4)); // 1E-1
         assert_eq!(detect("1e+", 0),   U);    // rejected, trailing sign after +
         assert_eq!(detect("1E+", 0),   U);    // rejected, trailing sign after +
         assert_eq!(detect("1e-", 0),   U);    // rejected, trailing sign after -
@@ -419,8 +628,9 @@ mod tests {
         assert_eq!(detect("0x1g", 0),  (H,3)); // 0x1 @TODO maybe follow "0b12" behaviour?
         assert_eq!(detect("0x_1", 0),  (H,4)); // 0x_1
         assert_eq!(detect("0x1_", 0),  (H,4)); // 0x1_
-        assert_eq!(detect("0x1.", 0),   U);    // hex float is not allowed
-        assert_eq!(detect("0x1.1", 0),  U);    // hex float is not allowed
+        assert_eq!(detect("0x1.", 0),  (H,3)); // 0x1, as hex floats are not allowed
+        assert_eq!(detect("0x1.1", 0), (H,3)); // 0x1 then a tuple index .1
+        assert_eq!(detect("0x.", 0),    U);    // rejected, no hex value
         assert_eq!(detect("0x1e", 0),  (H,4)); // 0x1e not enterpreted as exp
         assert_eq!(detect("0x1E", 0),  (H,4)); // 0x1E not enterpreted as exp
         assert_eq!(detect("0x1e1", 0), (H,5)); // 0x1e1 not enterpreted as exp
@@ -452,7 +662,7 @@ mod tests {
         // Non-ascii.
         assert_eq!(detect("€", 1),     U);    // part way into the three € bytes
         assert_eq!(detect("1€", 0),   (D,1)); // non-ascii after 1
-        assert_eq!(detect("1.€", 0),  (D,2)); // non-ascii after 1.
+        assert_eq!(detect("1.€", 0),  (F,2)); // non-ascii after 1.
         assert_eq!(detect("1_€'", 0), (D,2)); // non-ascii after 1_
         assert_eq!(detect("1e€'", 0),  U);    // non-ascii after 1e
         assert_eq!(detect("0€", 0),   (D,1)); // non-ascii after 0
@@ -463,4 +673,128 @@ mod tests {
         assert_eq!(detect("0o€", 0),   U);    // non-ascii after 0o
         assert_eq!(detect("0o0€", 0), (O,3)); // non-ascii after 0o0
     }
+
+    #[test]
+    fn detect_number_suffixes() {
+        // Float suffixes, after an integer or a float.
+        assert_eq!(detect("1f64", 0),      (F,4));
+        assert_eq!(detect("1.0f32", 0),    (F,6));
+        assert_eq!(detect("1e5f32", 0),    (F,6));
+        assert_eq!(detect("2.5_f64", 0),   (F,7));
+        assert_eq!(detect("1.f32", 0),     (F,2)); // 1. then f32
+        assert_eq!(detect("1.0f64x", 0),   (F,3)); // `f64x` is an Identifier
+        // Integer suffixes, which are only taken after an integer.
+        assert_eq!(detect("1u8", 0),       (D,3));
+        assert_eq!(detect("1_i32", 0),     (D,5));
+        assert_eq!(detect("7usize;", 0),   (D,6));
+        assert_eq!(detect("0x1u8", 0),     (H,5));
+        assert_eq!(detect("0b1_i128", 0),  (B,8));
+        assert_eq!(detect("1.0u8", 0),     (F,3)); // 1.0 then u8
+        assert_eq!(detect("1.0e5usize", 0),(F,5)); // 1.0e5 then usize
+        assert_eq!(detect("1e5i32", 0),    (F,3)); // 1e5 then i32
+        // Not suffixes, so the number ends before them.
+        assert_eq!(detect("1u8x", 0),      (D,1)); // `u8x` is an Identifier
+        assert_eq!(detect("1u8_", 0),      (D,1)); // so is `u8_`
+        assert_eq!(detect("1u7", 0),       (D,1)); // there is no `u7`
+        assert_eq!(detect("1u", 0),        (D,1)); // too short
+        assert_eq!(detect("0b1u8€", 0),    (B,3)); // non-ascii after the suffix
+    }
+
+    #[test]
+    fn split_number_suffix_as_expected() {
+        use super::split_number_suffix as split;
+        assert_eq!(split("1_u8"),       ("1_", "u8"));
+        assert_eq!(split("0xFF_u16"),   ("0xFF_", "u16"));
+        assert_eq!(split("1_000_i32"),  ("1_000_", "i32"));
+        assert_eq!(split("0b1isize"),   ("0b1", "isize"));
+        assert_eq!(split("1_"),         ("1_", ""));
+        assert_eq!(split("0xAB"),       ("0xAB", ""));
+        assert_eq!(split("1.5"),        ("1.5", ""));
+        assert_eq!(split("1.5f32"),     ("1.5", "f32"));
+        assert_eq!(split("1e3_f64"),    ("1e3_", "f64"));
+        assert_eq!(split("0x1f32"),     ("0x1f32", "")); // hex digits
+        assert_eq!(split("u8"),         ("u8", "")); // no digits at all
+        assert_eq!(split(""),           ("", ""));
+    }
+
+    #[test]
+    fn detect_number_integer_or_float() {
+        assert_eq!(detect("42", 0),        (D,2)); // an integer
+        assert_eq!(detect("3.14", 0),      (F,4)); // a dot
+        assert_eq!(detect("1e3", 0),       (F,3)); // an exponent
+        assert_eq!(detect("1f64", 0),      (F,4)); // a float suffix
+        assert_eq!(detect("1_f32", 0),     (F,5)); // underscores, then a suffix
+        assert_eq!(detect("0f32 ", 0),     (F,4)); // zero, then a suffix
+        assert_eq!(detect("1f64x", 0),     (D,1)); // `f64x` is not a suffix
+        assert_eq!(detect("1f64_", 0),     (D,1)); // nor is `f64_`
+        assert_eq!(detect("1f16", 0),      (D,1)); // nor is `f16`
+        assert_eq!(detect("1f6", 0),       (D,1)); // too short
+        assert_eq!(detect("1i64", 0),      (D,4)); // an integer suffix
+        assert_eq!(detect("0x1f64", 0),    (H,6)); // hex digits, not a suffix
+    }
+
+    #[test]
+    fn detect_number_underscore_after_prefix() {
+        // Underscores straight after the prefix, for every base.
+        assert_eq!(detect("0b_1", 0),    (B,4));
+        assert_eq!(detect("0b__1_", 0),  (B,6));
+        assert_eq!(detect("0o_7", 0),    (O,4));
+        assert_eq!(detect("0o__7_", 0),  (O,6));
+        assert_eq!(detect("0x_F", 0),    (H,4));
+        assert_eq!(detect("0x__C_", 0),  (H,6));
+        assert_eq!(detect("0x_0u8", 0),  (H,6)); // with a suffix
+        // At least one digit is required, after any number of underscores.
+        assert_eq!(detect("0b_", 0),      U);
+        assert_eq!(detect("0b__", 0),     U);
+        assert_eq!(detect("0o__", 0),     U);
+        assert_eq!(detect("0x__", 0),     U);
+        assert_eq!(detect("0x__ ", 0),    U);
+        assert_eq!(detect("0x__u8", 0),   U);
+        assert_eq!(detect("0b__2", 0),    U); // 2 is not a binary digit
+        assert_eq!(detect("0o__8", 0),    U); // 8 is not an octal digit
+        // Decimal numbers may contain underscores, but must start with a digit.
+        assert_eq!(detect("0_0", 0),     (D,3));
+        assert_eq!(detect("0__", 0),     (D,3));
+        assert_eq!(detect("_0", 0),       U);  // an Identifier
+    }
+
+    #[test]
+    fn detect_number_tuple_index_as_expected() {
+        let orig = "1 12 1e2 0.0 1_0 1. x";
+        assert_eq!(detect_index(orig, 0),  (D,1));  // 1
+        assert_eq!(detect_index(orig, 2),  (D,4));  // 12
+        assert_eq!(detect_index(orig, 5),  (D,6));  // 1 then e2
+        assert_eq!(detect_index(orig, 9),  (D,10)); // 0 then .0
+        assert_eq!(detect_index(orig, 13), (D,14)); // 1 then _0
+        assert_eq!(detect_index(orig, 17), (D,18)); // 1 then .
+        assert_eq!(detect_index(orig, 20),  U);     // x
+        assert_eq!(detect_index(orig, 1),   U);     // space
+        // Will not panic.
+        assert_eq!(detect_index("", 0),     U);     // empty string
+        assert_eq!(detect_index("1", 1),    U);     // right on the end
+        assert_eq!(detect_index("€1", 1),   U);     // part way into €
+    }
+
+    #[test]
+    fn detect_number_leading_dot_as_expected() {
+        let orig = ".5 .5e3 .5E-3 .1_0 .5.3 .e3 .5e x.0";
+        assert_eq!(detect_dot(orig, 0),  (F,2));  // .5
+        assert_eq!(detect_dot(orig, 3),  (F,7));  // .5e3
+        assert_eq!(detect_dot(orig, 8),  (F,13)); // .5E-3
+        assert_eq!(detect_dot(orig, 14), (F,18)); // .1_0
+        assert_eq!(detect_dot(orig, 19),  U);     // .5.3 has two dots
+        assert_eq!(detect_dot(orig, 24),  U);     // .e3 has no digit
+        assert_eq!(detect_dot(orig, 28),  U);     // .5e has no exponent value
+        assert_eq!(detect_dot(orig, 33), (F,35)); // the context is not checked
+        assert_eq!(detect_dot(orig, 1),   U);     // 5 has no dot
+        assert_eq!(detect_dot(".5u8", 0), (F,2)); // .5 then u8
+        assert_eq!(detect_dot(".5f32", 0),(F,5)); // .5f32
+        assert_eq!(detect_dot("..5", 0),  U);     // a range
+        assert_eq!(detect_dot(".0x1", 0), U);     // not decimal
+        // Will not panic.
+        assert_eq!(detect_dot("", 0),     U);     // empty string
+        assert_eq!(detect_dot(".", 0),    U);     // just a dot
+        assert_eq!(detect_dot(".5", 2),   U);     // right on the end
+        assert_eq!(detect_dot("€.5", 1),  U);     // part way into €
+    }
 }
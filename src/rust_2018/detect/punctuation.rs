@@ -1,6 +1,7 @@
 //! Detects sequences of Punctuation characters, like `;` or `>>=`.
 
 use super::super::lexeme::LexemeKind;
+use super::is_char_start;
 const DETECTED: LexemeKind = LexemeKind::Punctuation;
 const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
 
@@ -22,9 +23,10 @@ pub fn detect_punctuation(
     LexemeKind,
     usize,
 ) {
-    // If the current char is past the last char in `orig`, bail out!
+    // If `chr` is past the last char in `orig`, or part way into a multibyte
+    // char, bail out!
     let len = orig.len();
-    if chr >= len { return UNDETECTED }
+    if ! is_char_start(orig, chr) { return UNDETECTED }
     // If the current char is not present in PUNCTUATION_1, it is not, and does
     // not begin, punctuation. That’s because PUNCTUATION_2 and PUNCTUATION_3
     // all start with a PUNCTUATION_1 character.
@@ -117,9 +119,20 @@ const PUNCTUATION_3: [&str; 4] = [
 #[cfg(test)]
 mod tests {
     use super::detect_punctuation as detect;
+    use super::{PUNCTUATION_1,PUNCTUATION_2,PUNCTUATION_3};
+    use super::super::super::lexeme::PunctOp;
     use super::DETECTED as D;
     use super::UNDETECTED as U;
 
+    #[test]
+    fn every_punctuation_has_a_punct_op() {
+        let all = PUNCTUATION_1.iter().chain(PUNCTUATION_2.iter())
+            .chain(PUNCTUATION_3.iter());
+        for snippet in all {
+            assert!(PunctOp::from_snippet(snippet).is_some(), "{}", snippet);
+        }
+    }
+
     #[test]
     fn detect_punctuation_correct() {
         // Basic.
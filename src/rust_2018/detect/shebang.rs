@@ -0,0 +1,107 @@
+//! Detects a shebang line, like `#!/usr/bin/env rustx`, at the start of input.
+
+use super::super::lexeme::LexemeKind;
+use super::comment::detect_comment;
+use super::{ascii_or_tilde,is_char_start};
+use super::whitespace::detect_whitespace;
+const DETECTED: LexemeKind = LexemeKind::CommentShebang;
+const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
+
+/// Detects a shebang line, like `#!/usr/bin/env rustx`, at the start of input.
+/// 
+/// rustc ignores a first line which starts `#!`, so that a Rust file can be
+/// run as a script. It is only a shebang on the very first line, which may
+/// follow a byte order mark, so the `U+FEFF` of `\u{FEFF}#!/bin/sh` is detected
+/// first by `detect_byte_order_mark()`, and then the shebang.
+/// 
+/// An inner attribute like `#![no_std]` also starts `#!`, so it is never a
+/// shebang. As in rustc, any whitespace and (non-doc) comments between the
+/// `#!` and the `[` are skipped, so `#! /* c */ [no_std]` is an attribute too.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `chr` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `chr` begins a shebang, `detect_shebang()` returns
+/// `LexemeKind::CommentShebang` and the position of the newline which ends it,
+/// or the end of input.  
+/// Otherwise, `detect_shebang()` returns `LexemeKind::Undetected` and `0`.
+pub fn detect_shebang(
+    orig: &str,
+    chr: usize,
+) -> (
+    LexemeKind,
+    usize,
+) {
+    // A shebang must be at the start of input, after any byte order mark.
+    let start = if orig.starts_with('\u{FEFF}') { 3 } else { 0 };
+    if chr != start || ! is_char_start(orig, chr)
+    || ! orig[chr..].starts_with("#!") { return UNDETECTED }
+    // Skip any whitespace and plain comments after the `#!`. If a `[` follows,
+    // this is an inner attribute.
+    let mut pos = chr + 2;
+    loop {
+        let (kind, end) = detect_whitespace(orig, pos);
+        if kind != LexemeKind::Undetected { pos = end; continue }
+        match detect_comment(orig, pos) {
+            (LexemeKind::CommentInline, end)
+            | (LexemeKind::CommentMultiline, end) => pos = end,
+            _ => break,
+        }
+    }
+    if ascii_or_tilde(orig, pos) == "[" { return UNDETECTED }
+    // The shebang runs to the end of its line, but not the newline itself.
+    match orig[chr..].find('\n') {
+        Some(newline) => (DETECTED, chr + newline),
+        None => (DETECTED, orig.len()),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::detect_shebang as detect;
+    use super::DETECTED as S;
+    use super::UNDETECTED as U;
+
+    #[test]
+    fn detect_shebang_correct() {
+        assert_eq!(detect("#!/bin/sh", 0), (S,9));
+        assert_eq!(detect("#!/usr/bin/env rustx\nfn main() {}", 0), (S,20));
+        assert_eq!(detect("#!\r\nx", 0), (S,3)); // a `\r` is kept, like `//`
+        assert_eq!(detect("#!", 0), (S,2)); // nothing after the `#!`
+        assert_eq!(detect("#! x [y]", 0), (S,8)); // `[` does not follow
+        assert_eq!(detect("#!/** d */[x]", 0), (S,13)); // a doc comment
+        // After a byte order mark.
+        assert_eq!(detect("\u{FEFF}#!/bin/sh\ncode", 3), (S,12));
+    }
+
+    #[test]
+    fn detect_shebang_incorrect() {
+        // Inner attributes.
+        assert_eq!(detect("#![no_std]", 0), U);
+        assert_eq!(detect("#! [no_std]", 0), U);
+        assert_eq!(detect("#!\n\t[no_std]", 0), U);
+        assert_eq!(detect("#! // c\n/* d */ [no_std]", 0), U);
+        assert_eq!(detect("\u{FEFF}#![no_std]", 3), U);
+        // Not at the start of input.
+        assert_eq!(detect(" #!/bin/sh", 1), U);
+        assert_eq!(detect("x\n#!/bin/sh", 2), U);
+        assert_eq!(detect("\u{FEFF}#!/bin/sh", 0), U); // the BOM comes first
+        assert_eq!(detect("\u{FEFF} #!/bin/sh", 4), U);
+        // Not a shebang at all.
+        assert_eq!(detect("#[test]", 0), U);
+        assert_eq!(detect("!#", 0), U);
+    }
+
+    #[test]
+    fn detect_shebang_will_not_panic() {
+        assert_eq!(detect("", 0), U); // empty string
+        assert_eq!(detect("#", 0), U); // just a hash
+        assert_eq!(detect("#!", 2), U); // right on the end
+        assert_eq!(detect("#!", 100), U); // way out of range
+        assert_eq!(detect("\u{FEFF}", 3), U); // just a BOM
+        assert_eq!(detect("#! /*", 0), (S,5)); // an unterminated comment
+    }
+}
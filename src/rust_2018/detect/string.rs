@@ -1,14 +1,39 @@
 //! Detects a string literal, like `"Hello \"Rust\""` or `r#"Hello "Rust""#`.
 
 use super::super::lexeme::LexemeKind;
+use super::{ascii_or_tilde,is_char_start};
+const BYTE: LexemeKind = LexemeKind::StringByte;
+const BYTE_RAW: LexemeKind = LexemeKind::StringByteRaw;
 const PLAIN:  LexemeKind = LexemeKind::StringPlain;
 const RAW: LexemeKind = LexemeKind::StringRaw;
+const UNEXPECTED: LexemeKind = LexemeKind::Unexpected;
 const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
 
+/// The maximum number of `#` delimiters allowed around a raw string.
+/// 
+/// rustc rejects a raw string with more hashes, so it is not detected here.
+pub const MAX_RAW_HASHES: usize = 255;
+
 /// Detects a string literal, like `"Hello \"Rust\""` or `r#"Hello "Rust""#`.
 /// 
-/// @TODO `b` prefix, eg `b"Just the bytes"`
-/// @TODO `br` prefix, eg `br#"Just "the" bytes"#`
+/// A raw string with more than `MAX_RAW_HASHES` leading hashes is not detected.
+/// 
+/// The `b` prefix, eg `b"Just the bytes"`, gives a Byte string, and the `br`
+/// prefix, eg `br#"Just "the" bytes"#`, gives a Byte Raw string. Like the
+/// other detectors, this is just a scanner, so non-ascii chars in a Byte string
+/// are not rejected, even though rustc would reject them.
+/// 
+/// Note that the `c` and `cr` prefixes are not part of Rust 2018, so `cr"x"`
+/// is the Identifier `cr` followed by the Plain string `"x"`.
+/// 
+/// A backslash is not an escape in a Raw string, so `r"\"` is complete. A
+/// double quote which is not followed by enough hashes is part of the Raw
+/// string, so `r#"a"b"#` is a single Lexeme.
+/// 
+/// An unterminated string, like `"abc` at the end of input, is not detected
+/// here. `detect_unexpected()` picks it up as a single `Unexpected` Lexeme,
+/// which runs to the end of input. A pipeline without `detect_unexpected()`
+/// can opt in to that by using `detect_string_or_unterminated()` instead.
 /// 
 /// ### Arguments
 /// * `orig` The original Rust code, assumed to conform to the 2018 edition
@@ -25,23 +50,78 @@ pub fn detect_string(
     LexemeKind,
     usize,
 ) {
-    // If the current char is the last in `orig`, it does not begin a string.
+    // If `chr` is past the last char in `orig`, or part way into a multibyte
+    // char, it does not begin a string.
     let len = orig.len();
-    if len < chr + 1 { return UNDETECTED }
+    if ! is_char_start(orig, chr) { return UNDETECTED }
 
     // If the current char is:
-    match get_aot(orig, chr) {
+    match ascii_or_tilde(orig, chr) {
         // A double quote, `chr` could begin a Plain string.
         "\"" => detect_plain_string(orig, chr, len),
-        // A lowercase "r", `chr` could begin a Raw string.
-        "r" => detect_raw_string(orig, chr, len),
+        // A lowercase "r" followed by a double quote or hash, `chr` could
+        // begin a Raw string. Otherwise it begins an Identifier, like `raw`.
+        "r" => match ascii_or_tilde(orig, chr + 1) {
+            "\"" | "#" => detect_raw_string(orig, chr, len),
+            _ => UNDETECTED,
+        },
+        // A lowercase "b" followed by a double quote, or by "r" and then a
+        // double quote or hash, `chr` could begin a Byte or Byte Raw string.
+        // Otherwise it begins an Identifier, like `break` or `brown`.
+        "b" => match (ascii_or_tilde(orig, chr + 1), ascii_or_tilde(orig, chr + 2)) {
+            ("\"", _) => as_kind(BYTE, detect_plain_string(orig, chr + 1, len)),
+            ("r", "\"" | "#") =>
+                as_kind(BYTE_RAW, detect_raw_string(orig, chr + 1, len)),
+            _ => UNDETECTED,
+        },
         // Anything else, `chr` does not begin a string.
         _ => UNDETECTED,
     }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, c: usize) -> &str { orig.get(c..c+1).unwrap_or("~") }
+/// Detects a string literal, or an unterminated plain string.
+/// 
+/// This is the same as `detect_string()`, except that a double quote with no
+/// closing double quote, like the `"abc` of `x = "abc`, is detected as a
+/// single `LexemeKind::Unexpected`, which runs to the end of input. It can
+/// replace `detect_string()` in a custom [`Lexemizer`] pipeline. The default
+/// pipeline gets the same result from `detect_unexpected()`.
+/// 
+/// [`Lexemizer`]: super::super::lexemize::Lexemizer
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `chr` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `chr` begins a valid looking string literal, this returns the same as
+/// `detect_string()`.  
+/// If `chr` is an unterminated plain string’s opening double quote, it returns
+/// `LexemeKind::Unexpected` and the position after the end of input.  
+/// Otherwise, it returns `LexemeKind::Undetected` and `0`.
+pub fn detect_string_or_unterminated(
+    orig: &str,
+    chr: usize,
+) -> (
+    LexemeKind,
+    usize,
+) {
+    match detect_string(orig, chr) {
+        UNDETECTED if ascii_or_tilde(orig, chr) == "\"" => (UNEXPECTED, orig.len()),
+        detected => detected,
+    }
+}
+
+// Changes the kind of a detected string, eg to `StringByte` after a `b` prefix.
+fn as_kind(
+    kind: LexemeKind,
+    detected: (LexemeKind, usize),
+) -> (
+    LexemeKind,
+    usize,
+) {
+    if detected == UNDETECTED { UNDETECTED } else { (kind, detected.1) }
+}
 
 fn detect_plain_string(
     orig: &str,
@@ -53,25 +133,26 @@ fn detect_plain_string(
 ) {
     // Slightly hacky way to to skip forward while looping.
     let mut i = chr + 1;
-    // Step through each char, from `chr` to the end of the original input code.
+    // Step through each backslash and double quote, from `chr` to the end of
+    // the original input code. Everything between them is skipped in one go,
+    // which is much faster than stepping through each char of a long string.
     while i < len {
-        // Get this character, even if it’s non-ascii.
-        let mut j = i + 1;
+        // Find the next backslash or double quote. Both are ascii, so `found`
+        // is always on a char boundary. If there are none, this is not a string.
+        let found = match orig[i..].find(['\\', '"']) {
+            Some(pos) => i + pos,
+            None => return UNDETECTED,
+        };
+        // If this char is a double quote, advance to the end of it.
+        if &orig[found..found+1] == "\"" { return (PLAIN, found + 1) }
+        // Otherwise it’s a backslash. If it ends the input code, this is not a
+        // string.
+        let mut j = found + 1;
+        if j == len { return UNDETECTED }
+        // Ignore the next character, even if it’s non-ascii.
+        // Treat "\€" as a string Lexeme, even though it’s invalid code.
+        j += 1;
         while !orig.is_char_boundary(j) { j += 1 }
-        let c = &orig[i..j];
-        // If this char is a backslash:
-        if c == "\\" {
-            // If the backlash ends the input code, this is not a string.
-            if j == len { return UNDETECTED }
-            // Ignore the next character, even if it’s non-ascii.
-            // Treat "\€" as a string Lexeme, even though it’s invalid code.
-            j += 1;
-            while !orig.is_char_boundary(j) { j += 1 }
-        // If this char is a double quote:
-        } else if c == "\"" {
-            // Advance to the end of the double quote.
-            return (PLAIN, j)
-        }
         // Step forward, ready for the next iteration.
         i = j;
     }
@@ -90,93 +171,54 @@ fn detect_raw_string(
 ) {
     // If there are less than two chars after the "r", it cannot begin a string.
     if len < chr + 3 { return UNDETECTED }
-    // Slightly hacky way to to skip forward while looping.
-    let mut i = chr + 1;
-    // Keep track of the number of leading hashes.
-    let mut hashes = 0;
-    // Keep track of finding the opening and closing double quotes.
-    let mut found_opening_dq = false;
-    let mut found_closing_dq = false;
+    // Count the leading hashes. If that goes beyond the limit, this is not a
+    // Raw string. Trailing hashes are counted down from `hashes`, so never
+    // exceed it either.
+    let rest = &orig[chr+1..];
+    let hashes = rest.len() - rest.trim_start_matches('#').len();
+    if hashes > MAX_RAW_HASHES { return UNDETECTED }
+    // Anything but the opening double quote is not valid for the start of a
+    // Raw string.
+    let mut i = chr + 1 + hashes;
+    if orig.get(i..i+1) != Some("\"") { return UNDETECTED }
+    i += 1;
 
-    // Step through each char, from `chr` to the end of the original input code.
-    // `len-1` saves a nanosecond or two, but also prevents `orig[i..i+1]` from
-    // panicking at the end of the input.
+    // Step through each double quote inside the main part of the string,
+    // skipping everything between them in one go. A backslash is not an escape
+    // in a Raw string, so `r"\"` is a complete string.
     while i < len {
-        // Get this character, even if it’s non-ascii.
-        let mut j = i + 1;
-        while !orig.is_char_boundary(j) { j += 1 }
-        let c = &orig[i..j];
-
-        // If we have not found the opening double quote yet:
-        if ! found_opening_dq {
-            // If this is the opening double quote, note that it’s been found.
-            if c == "\"" {
-                found_opening_dq = true
-            // Otherwise, if this is a leading hash, increment the tally.
-            } else if c == "#" {
-                hashes += 1
-            // Anything else is not valid for the start of a Raw string.
-            } else {
-                return UNDETECTED
-            }
-
-        // Otherwise, if we have already found the closing double quote:
-        } else if found_closing_dq {
-            // If we are not expecting any more hashes:
-            if hashes == 0 {
-                // Valid Raw string, advance to the end of the double quote.
-                return (RAW, j)
-            // Otherwise, if this is a trailing hash, decrement the tally.
-            } else if c == "#" {
-                hashes -= 1;
-                // If we are not expecting any more hashes:
-                if hashes == 0 {
-                    // Valid Raw string, advance to the end of the double quote.
-                    return (RAW, j)
-                }
-            // Anything else is not valid for the end of a Raw string.
-            } else {
-                return UNDETECTED
-            }
-
-        // Otherwise we are inside the main part of the string:
-        } else {
-            // If this char is a backslash:
-            if c == "\\" {
-                // If the backlash ends the input code, this is not a string.
-                if j == len { return UNDETECTED }
-                // Ignore the next character, even if it’s non-ascii.
-                // Treat "\€" as a string Lexeme, even though it’s invalid code.
-                j += 1;
-                while !orig.is_char_boundary(j) { j += 1 }
-            // If this char is a double quote:
-            } else if c == "\"" {
-                // Note that the closing double quote has been found.
-                found_closing_dq = true;
-                // If we are not expecting any more hashes:
-                if hashes == 0 {
-                    // Valid Raw string, advance to the end of the double quote.
-                    return (RAW, j)
-                }
-            }
-        }
-
-        // Step forward, ready for the next iteration.
-        i = j;
+        // Find the next double quote. If there are none, the closing double
+        // quote was not found, so this is not a string.
+        let found = match orig[i..].find('"') {
+            Some(pos) => i + pos,
+            None => return UNDETECTED,
+        };
+        // If this double quote is followed by as many trailing hashes as there
+        // are leading ones, it closes the string. Advance to the end of the
+        // trailing hashes.
+        let end = found + 1 + hashes;
+        let trailing = orig.get(found+1..end).unwrap_or("~");
+        if trailing.bytes().all(|b| b == b'#') { return (RAW, end) }
+        // Otherwise it is part of the string, like the `"` of `r#"a"b"#`.
+        i = found + 1;
     }
 
-    // Reached the end of the `orig` input string. Any leading hashes should
-    // have been balanced by trailing hashes.
-    if found_closing_dq && hashes == 0 { (RAW, i) } else { UNDETECTED }
+    // Reached the end of the `orig` input string, without finding the closing
+    // double quote.
+    UNDETECTED
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::detect_string as detect;
+    use super::detect_string_or_unterminated;
+    use super::BYTE as B;
+    use super::BYTE_RAW as BR;
     use super::PLAIN as P;
+    use super::MAX_RAW_HASHES;
     use super::RAW as R;
     use super::UNDETECTED as U;
+    use super::UNEXPECTED as X;
 
     #[test]
     fn detect_string_correct() {
@@ -190,10 +232,20 @@ mod tests {
         assert_eq!(detect("r#\"ok\"#", 0), (R,7));
         assert_eq!(detect("abcr###\"ok\"###xyz", 3), (R,14));
         assert_eq!(detect("abcr###\"ok\"####xyz", 3), (R,14));
+        let hashes = "#".repeat(MAX_RAW_HASHES);
+        let orig = format!("r{}\"ok\"{}", hashes, hashes);
+        assert_eq!(detect(&orig, 0), (R,515)); // 255 hashes is the limit
+        // A double quote without enough trailing hashes is part of the string.
+        assert_eq!(detect("r#\"a\"b\"#; x", 0), (R,8)); // r#"a"b"#
+        assert_eq!(detect("r##\"a\"#b\"##", 0), (R,11)); // r##"a"#b"##
         // Byte.
-        // @TODO
+        assert_eq!(detect("-b\"ok\"-", 1), (B,6));
+        assert_eq!(detect("b\"\\\"\"", 0), (B,5));  // b"\""
+        assert_eq!(detect("b\"\"", 1), (P,3));      // "" without its prefix
         // Byte raw.
-        // @TODO
+        assert_eq!(detect("-br\"ok\"-", 1), (BR,7));
+        assert_eq!(detect("br#\"#ok#\"#", 0), (BR,10)); // br#"#ok#"#
+        assert_eq!(detect("br\"\"", 1), (R,4));     // r"" without its `b`
 
         // Escapes.
         // Escaped double quote.
@@ -210,8 +262,26 @@ mod tests {
         assert_eq!(detect(orig, 2),   U);     // \0\\\\\"\\\n"z
         assert_eq!(detect(orig, 9),  (P,15)); // "\\\n"z no ‘lookbehind’s!
         assert_eq!(detect(orig, 14),  U);     // "z not a string, has no end
-        // Correct escapes, Raw string.
+        // Backslashes are not escapes in a Raw string.
         assert_eq!(detect("r\"\\0\\n\\t\"", 0), (R,9)); // r"\0\n\t"
+        assert_eq!(detect("r\"\\\"", 0), (R,4)); // r"\"
+        assert_eq!(detect("r#\"\\\"#\"#", 0), (R,6)); // r#"\"# then "#
+    }
+
+    #[test]
+    fn detect_string_long() {
+        // A 1MB string, mostly ascii, with a few escapes and non-ascii chars.
+        let inner = "abc \\\" def € ".repeat(70_000);
+        assert!(inner.len() > 1_000_000);
+        let orig = format!("x\"{}\"y", inner);
+        assert_eq!(detect(&orig, 1), (P,inner.len() + 3));
+        let orig = format!("br##\"{}\"##", inner);
+        assert_eq!(detect(&orig, 0), (BR,inner.len() + 8));
+        // Unterminated, so the whole 1MB is scanned, and then rejected.
+        let orig = format!("\"{}", inner);
+        assert_eq!(detect(&orig, 0), U);
+        let orig = format!("r#\"{}\"", inner.replace('"', ""));
+        assert_eq!(detect(&orig, 0), U);
     }
 
     #[test]
@@ -225,10 +295,53 @@ mod tests {
         assert_eq!(detect("r###\" X in trailing hashes \"##X#", 0), U);
         assert_eq!(detect("r###\" too few trailing hashes \"##", 0), U);
         assert_eq!(detect("-r###\" no trailing hashes \"-", 1), U);
+        // Whitespace between the `r` and the hashes or quote.
+        let orig = "r #\"x\"#";
+        assert_eq!(detect(orig, 0), U); // r #"x"# is not a Raw string
+        assert_eq!(detect(orig, 2), U); // #"x"# is not a string either
+        assert_eq!(detect(orig, 3), (P,6)); // but "x" is a Plain string
+        assert_eq!(detect("r# \"x\"#", 0), U); // space after the hash
+        assert_eq!(detect("r\t\"x\"", 0), U); // tab after the r
+        // Too many hashes.
+        let hashes = "#".repeat(MAX_RAW_HASHES + 1);
+        let orig = format!("r{}\"ok\"{}", hashes, hashes);
+        assert_eq!(detect(&orig, 0), U); // 256 hashes
         // Incorrect byte.
-        // @TODO
+        assert_eq!(detect("b\"ok", 0), U);     // no closing double quote
+        assert_eq!(detect("b \"ok\"", 0), U);  // space after the prefix
+        assert_eq!(detect("bb\"ok\"", 0), U);  // two prefixes
         // Incorrect byte raw.
-        // @TODO
+        assert_eq!(detect("br#\"ok\"", 0), U); // too few trailing hashes
+        assert_eq!(detect("rb\"ok\"", 0), U);  // prefix in the wrong order
+        // `c` and `cr` are not prefixes in Rust 2018.
+        assert_eq!(detect("c\"ok\"", 0), U);
+        assert_eq!(detect("cr\"ok\"", 0), U);
+        // Identifiers which start with a prefix, even with a string later on.
+        assert_eq!(detect("break \"ok\"", 0), U);
+        assert_eq!(detect("brown#\"ok\"#", 0), U);
+        assert_eq!(detect("bytes\"ok\"", 0), U);
+        assert_eq!(detect("raw \"ok\"", 0), U);
+        assert_eq!(detect("ref#\"ok\"#", 0), U);
+        assert_eq!(detect("crate\"ok\"", 0), U);
+    }
+
+    #[test]
+    fn detect_string_or_unterminated_as_expected() {
+        let detect = detect_string_or_unterminated;
+        // A terminated string is detected as usual.
+        assert_eq!(detect("\"abc\"", 0), (P,5));     // "abc"
+        assert_eq!(detect("x = \"abc\";", 4), (P,9)); // "abc" then ;
+        assert_eq!(detect("b\"abc\"", 0), (B,6));    // b"abc"
+        // An unterminated plain string is Unexpected, to the end of input.
+        assert_eq!(detect("\"abc", 0), (X,4));        // "abc
+        assert_eq!(detect("x = \"abc\n;", 4), (X,10)); // "abc then newline ;
+        assert_eq!(detect("\"abc\\\"", 0), (X,6));  // "abc\" escaped quote
+        assert_eq!(detect("\"", 0), (X,1));           // "
+        // Anything else is still not detected.
+        assert_eq!(detect("abc", 0), U);
+        assert_eq!(detect("r\"abc", 0), U);
+        assert_eq!(detect("\"abc", 1), U);
+        assert_eq!(detect("\"abc", 100), U);
     }
 
     #[test]
@@ -242,6 +355,10 @@ mod tests {
         assert_eq!(detect("\"\\z", 0), U);              // "\z
         assert_eq!(detect("\"\\z\\", 0), U);            // "\z\
         assert_eq!(detect("\"\\z\\\"", 0), U);          // "\z\"
+        assert_eq!(detect("b", 0), U);                  // b
+        assert_eq!(detect("b\"", 0), U);                // b"
+        assert_eq!(detect("br", 0), U);                 // br
+        assert_eq!(detect("br\"", 0), U);               // br"
         assert_eq!(detect("r", 0), U);                  // r
         assert_eq!(detect("r\"", 0), U);                // r"
         assert_eq!(detect("r\"a", 0), U);               // r"a
@@ -249,8 +366,8 @@ mod tests {
         assert_eq!(detect("r\"\\n", 0), U);             // r"\n
         assert_eq!(detect("r\"\\z", 0), U);             // r"\z
         assert_eq!(detect("r\"\\z\\", 0), U);           // r"\z\
-        assert_eq!(detect("r\"\\z\\\"", 0), U);         // r"\z\"
-        assert_eq!(detect("r\"\\z\\\"\"", 0), (R,7));   // r"\z\""
+        assert_eq!(detect("r\"\\z\\\"", 0), (R,6));     // r"\z\" has no escapes
+        assert_eq!(detect("r\"\\z\\\"\"", 0), (R,6));   // r"\z\" then another "
         assert_eq!(detect("r#", 0), U);                 // r#
         assert_eq!(detect("r#\"", 0), U);               // r#"
         assert_eq!(detect("r#\"a", 0), U);              // r#"a
@@ -259,7 +376,7 @@ mod tests {
         assert_eq!(detect("r#\"\\z", 0), U);            // r#"\z
         assert_eq!(detect("r#\"\\z\\", 0), U);          // r#"\z\
         assert_eq!(detect("r#\"\\z\\\"", 0), U);        // r#"\z\"
-        assert_eq!(detect("r#\"\\z\\\"#", 0), U);       // r#"\z\"#
+        assert_eq!(detect("r#\"\\z\\\"#", 0), (R,8));   // r#"\z\"#
         assert_eq!(detect("r#\"\\z\\\"\"#", 0), (R,9)); // r#"\z\""#
         assert_eq!(detect("r##\"\\z\\\"\"#", 0), U);    // r##"\z\""# missing #
         // Invalid `chr`.
@@ -286,8 +403,8 @@ mod tests {
         assert_eq!(detect("r\"\\€", 0), U); // non-ascii after r"\
         assert_eq!(detect("r\"\\z€", 0), U); // non-ascii after r"\z
         assert_eq!(detect("r\"\\z\\€", 0), U); // non-ascii after r"\z\
-        assert_eq!(detect("r\"\\z\\\"€", 0), U); // non-ascii after r"\z\"
-        assert_eq!(detect("r\"\\z\\\"\"€", 0), (R,7)); // non-ascii after r"\z\""
+        assert_eq!(detect("r\"\\z\\\"€", 0), (R,6)); // non-ascii after r"\z\"
+        assert_eq!(detect("r\"\\z\\\"\"€", 0), (R,6)); // r"\z\" then "€
         assert_eq!(detect("r\"€\"", 0), (R,6)); // non-ascii in r""
         assert_eq!(detect("r\"a€\"", 0), (R,7)); // non-ascii in r"a"
         assert_eq!(detect("r\"\\€\"", 0), (R,7)); // non-ascii in r"\"
@@ -0,0 +1,194 @@
+//! Detects a malformed token, like an unterminated string or a bad number.
+
+use super::super::lexeme::LexemeKind;
+use super::character::detect_character;
+use super::comment::detect_comment;
+use super::{ascii_or_tilde,is_char_start};
+use super::number::detect_number;
+use super::string::detect_string;
+const DETECTED: LexemeKind = LexemeKind::Unexpected;
+const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
+
+/// Detects a malformed token, like an unterminated string or a bad number.
+/// 
+/// The other `detect_*()` functions return `LexemeKind::Undetected` for a
+/// token which starts correctly, but is malformed. Without this function, the
+/// malformed token would be lumped in with any ‘Unidentifiable’ characters,
+/// or split into several misleading Lexemes. For example `0b12` would become
+/// an Unidentifiable `0` followed by a Freeword `b12`.
+/// 
+/// `detect_unexpected()` recognises:
+/// - An unterminated char, like `'\` or `'1`, where the `'` is followed by
+///   something which cannot begin a lifetime or label. Just the `'` is
+///   Unexpected, so `'ab` is left alone, in case it’s the lifetime `'ab`
+/// - An unterminated plain string, like `"abc`, which runs to the end of input
+/// - An unterminated multiline comment, like `/*abc`, which also runs to the
+///   end of input. So does one nested more than `MAX_COMMENT_DEPTH` levels
+///   deep
+/// - A malformed number, like `0b12` or `1e+`, which runs to the end of the
+///   alphanumeric characters
+/// 
+/// To avoid flagging valid code, the relevant `detect_*()` function is called
+/// first. So `detect_unexpected()` is cheap if placed after the other
+/// detectors, but also correct if they have been left out.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `chr` The character position in `orig` to look at
+/// 
+/// ### Returns
+/// If `chr` begins a malformed token, `detect_unexpected()` returns
+/// `LexemeKind::Unexpected` and the position after the malformed token ends.
+/// Otherwise, `detect_unexpected()` returns `LexemeKind::Undetected` and `0`.
+pub fn detect_unexpected(
+    orig: &str,
+    chr: usize,
+) -> (
+    LexemeKind,
+    usize,
+) {
+    // If `chr` is past the last char in `orig`, or part way into a multibyte
+    // char, bail out!
+    let len = orig.len();
+    if ! is_char_start(orig, chr) { return UNDETECTED }
+    let c = ascii_or_tilde(orig, chr);
+
+    // If the current char is a single quote which does not begin a valid char,
+    // and cannot begin a lifetime or label either, just the quote is malformed.
+    if c == "'" {
+        return if detect_character(orig, chr).0 == LexemeKind::Undetected
+        && ! starts_lifetime(orig, chr + 1) { (DETECTED, chr + 1) } else { UNDETECTED }
+    }
+
+    // If the current char is a double quote which does not begin a valid
+    // string, the string must be unterminated. It runs to the end of input.
+    if c == "\"" {
+        return if detect_string(orig, chr).0 == LexemeKind::Undetected
+            { (DETECTED, len) } else { UNDETECTED }
+    }
+
+    // If the current and next chars begin a multiline comment which is not
+    // valid, the comment must be unterminated or too deeply nested. It runs to
+    // the end of input.
+    if c == "/" && ascii_or_tilde(orig, chr + 1) == "*" {
+        return if detect_comment(orig, chr).0 == LexemeKind::Undetected
+            { (DETECTED, len) } else { UNDETECTED }
+    }
+
+    // If the current char is a digit which does not begin a valid number:
+    if ("0"..="9").contains(&c)
+    && detect_number(orig, chr).0 == LexemeKind::Undetected {
+        return (DETECTED, find_end_of_number(orig, chr, len))
+    }
+
+    // Anything else is not a malformed token.
+    UNDETECTED
+}
+
+// Returns true if the char at `pos` could begin the name of a lifetime or
+// label, like the `a` of `'a`, which is a letter or underscore.
+fn starts_lifetime(orig: &str, pos: usize) -> bool {
+    match orig.get(pos..).and_then(|rest| rest.chars().next()) {
+        Some(c) => c == '_' || c.is_alphabetic(),
+        None => false,
+    }
+}
+
+// Returns the position after a malformed number, like "0b12" or "1e+".
+fn find_end_of_number(
+    orig: &str,
+    chr: usize,
+    len: usize,
+) -> usize {
+    // Hex digits include "e" and "E", so "+" and "-" are never part of them.
+    let is_hex = ascii_or_tilde(orig, chr + 1) == "x";
+    let mut i = chr + 1;
+    while i < len {
+        let c = ascii_or_tilde(orig, i);
+        let prev = ascii_or_tilde(orig, i - 1);
+        // Letters, digits and underscores are part of the malformed number.
+        if c.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && c != "~"
+        // So is a dot, if a digit or underscore follows it, like "0b1.1".
+        || c == "." && is_digit_or_underscore(ascii_or_tilde(orig, i + 1))
+        // So is a sign, if it follows an exponent marker, like "1e+".
+        || ! is_hex && (c == "+" || c == "-") && (prev == "e" || prev == "E")
+            { i += 1 } else { break }
+    }
+    i
+}
+
+// Returns true if `c` is "0" to "9" or "_".
+fn is_digit_or_underscore(c: &str) -> bool { c == "_" || ("0"..="9").contains(&c) }
+
+
+#[cfg(test)]
+mod tests {
+    use super::detect_unexpected as detect;
+    use super::DETECTED as X;
+    use super::UNDETECTED as U;
+
+    #[test]
+    fn detect_unexpected_correct() {
+        // Unterminated string.
+        assert_eq!(detect("\"abc", 0),      (X,4)); // "abc
+        assert_eq!(detect("a \"b\\\"c", 2), (X,7)); // "b\"c
+        assert_eq!(detect("\"abc\\", 0),    (X,5)); // "abc\ ends in backslash
+        // Unterminated char, where just the "'" is malformed.
+        assert_eq!(detect("'\\", 0),      (X,1)); // '\ at the end of input
+        assert_eq!(detect("'\\n x", 0),   (X,1)); // '\n with no closing quote
+        assert_eq!(detect("'1 ", 0),      (X,1)); // '1 cannot be a lifetime
+        assert_eq!(detect("x = ' ", 4),   (X,5)); // ' followed by a space
+        assert_eq!(detect("'", 0),        (X,1)); // ' at the end of input
+        // Unterminated multiline comment.
+        assert_eq!(detect("/*abc", 0),       (X,5));  // /*abc
+        assert_eq!(detect("a /* b /* c */", 2), (X,14)); // nested, unclosed
+        let orig = "/*".repeat(300) + &"*/".repeat(300);
+        assert_eq!(detect(&orig, 0), (X,1200)); // nested too deeply
+        // Malformed number.
+        assert_eq!(detect("0b12", 0),     (X,4));  // 0b12
+        assert_eq!(detect("0b12 + 1", 0), (X,4));  // 0b12 then ` + 1`
+        assert_eq!(detect("0b11.1;", 0),  (X,6));  // 0b11.1
+        assert_eq!(detect("0b", 0),       (X,2));  // 0b has no digits
+        assert_eq!(detect("0o8", 0),      (X,3));  // 0o8
+        assert_eq!(detect("0x", 0),       (X,2));  // 0x has no digits
+        assert_eq!(detect("1e+)", 0),     (X,3));  // 1e+
+        assert_eq!(detect("1._2", 0),     (X,4));  // 1._2
+        assert_eq!(detect("2E_", 0),      (X,3));  // 2E_
+    }
+
+    #[test]
+    fn detect_unexpected_incorrect() {
+        // Valid tokens are not malformed.
+        assert_eq!(detect("\"abc\"", 0), U); // "abc"
+        assert_eq!(detect("/*a*/", 0),   U); // /*a*/
+        assert_eq!(detect("/ 2", 0),     U); // division
+        assert_eq!(detect("0b11", 0),    U); // 0b11
+        assert_eq!(detect("1e+2", 0),    U); // 1e+2
+        assert_eq!(detect("0x1e+2", 0),  U); // 0x1e is valid, then +2
+        assert_eq!(detect("'a'", 0),     U); // 'a' is a valid char
+        assert_eq!(detect("'\\n'", 0),   U); // '\n' is a valid char
+        assert_eq!(detect("'ab c", 0),   U); // 'ab could be a lifetime
+        assert_eq!(detect("&'a T", 1),   U); // 'a is a lifetime
+        assert_eq!(detect("'_ ", 0),     U); // '_ is the anonymous lifetime
+        assert_eq!(detect("'é: loop", 0), U); // 'é could be a label
+        // Not the start of a token.
+        assert_eq!(detect("abc", 0), U); // Identifier
+        assert_eq!(detect("~", 0),   U); // Unidentifiable
+    }
+
+    #[test]
+    fn detect_unexpected_will_not_panic() {
+        // Near the end of `orig`.
+        assert_eq!(detect("", 0),   U);    // empty string
+        assert_eq!(detect("\"", 0), (X,1)); // "
+        assert_eq!(detect("/", 0),  U);    // /
+        assert_eq!(detect("/*", 0), (X,2)); // /*
+        // Invalid `chr`.
+        assert_eq!(detect("abc", 3),   U); // 3 is after "c", so incorrect
+        assert_eq!(detect("abc", 100), U); // 100 is way out of range
+        // Non-ascii.
+        assert_eq!(detect("€", 1),      U);     // part way into the € bytes
+        assert_eq!(detect("0b2€", 0),  (X,3));  // 0b2 then €
+        assert_eq!(detect("\"€", 0),   (X,4));  // "€
+    }
+}
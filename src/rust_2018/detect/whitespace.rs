@@ -1,6 +1,7 @@
 //! Detects a sequence of Whitespace characters.
 
 use super::super::lexeme::LexemeKind;
+use super::is_char_start;
 const DETECTED: LexemeKind = LexemeKind::WhitespaceTrimmable;
 const UNDETECTED: (LexemeKind, usize) = (LexemeKind::Undetected, 0);
 
@@ -27,39 +28,36 @@ pub fn detect_whitespace(
 ) {
     // If the current char is past the last char in `orig`, or `chr` is not on
     // a character boundary, bail out! The char boundary test avoids a potential
-    // panic if `&orig[i..j]` is reached, below.
+    // panic if `orig[i..]` is reached, below.
     let len = orig.len();
-    if chr >= len || !orig.is_char_boundary(chr) { return UNDETECTED }
+    if ! is_char_start(orig, chr) { return UNDETECTED }
     // Step through each byte-position, from `chr` to the end of the input code.
+    let bytes = orig.as_bytes();
     let mut i = chr;
-    while i < len {
-        // Get the current character if it’s ascii, or get "~" if it’s not.
-        let c = get_aot(orig, i);
-        // Jump to the next char if this is ascii whitespace.
-        if c == " "        // U+0020  UTF-8 20        "Space"
-        || c == "\n"       // U+000A  UTF-8 0A        "New Line" or "Line Feed"
-        || c == "\t"       // U+0009  UTF-8 09        "Horizontal Tabulation"
-        || c == "\r"       // U+000D  UTF-8 0D        "Carriage Return"
-        || c == "\u{000B}" // U+000B  UTF-8 0B        "Vertical Tabulation"
-        || c == "\u{000C}" // U+000C  UTF-8 0C        "Form Feed"
-        { i += 1; continue }
-        // End the loop if this is ascii non-whitespace.
-        if c != "~" { break }
-        // End the loop if there is no next byte.
-        if i >= len - 1 { break }
-        // Get the next character.
-        let mut j = i + 1;
-        while !orig.is_char_boundary(j) { j += 1 }
-        let c = &orig[i..j];
-        // End the loop if we encountered a literal tilde.
-        if c == "~" { break }
+    loop {
+        // Fast path, for ascii whitespace, which is most whitespace in practice.
+        // Skip each ascii whitespace byte in a tight loop.
+        while i < len && matches!(bytes[i],
+            b' '    // U+0020  UTF-8 20        "Space"
+          | b'\n'   // U+000A  UTF-8 0A        "New Line" or "Line Feed"
+          | b'\t'   // U+0009  UTF-8 09        "Horizontal Tabulation"
+          | b'\r'   // U+000D  UTF-8 0D        "Carriage Return"
+          | 0x0B    // U+000B  UTF-8 0B        "Vertical Tabulation"
+          | 0x0C    // U+000C  UTF-8 0C        "Form Feed"
+        ) { i += 1 }
+        // End the loop at the end of the input code, or at ascii non-whitespace.
+        // Only a byte of 0x80 or more can begin a non-ascii char.
+        if i >= len || bytes[i] < 0x80 { break }
+        // Slow path. Get the non-ascii char. `i` is always on a char boundary
+        // here, because only whole chars have been skipped.
+        let c = match orig[i..].chars().next() { Some(c) => c, None => break };
         // Jump to the next char if this is non-ascii Pattern_White_Space.
-        if c == "\u{0085}" // U+0085  UTF-8 C2 85     "Next Line"
-        || c == "\u{200E}" // U+200E  UTF-8 E2 80 8E  "Left-To-Right Mark"
-        || c == "\u{200F}" // U+200F  UTF-8 E2 80 8F  "Right-To-Left Mark"
-        || c == "\u{2028}" // U+2028  UTF-8 E2 80 A8  "Line Separator"
-        || c == "\u{2029}" // U+2029  UTF-8 E2 80 A9  "Paragraph Separator"
-        { i = j; continue }
+        if c == '\u{0085}' // U+0085  UTF-8 C2 85     "Next Line"
+        || c == '\u{200E}' // U+200E  UTF-8 E2 80 8E  "Left-To-Right Mark"
+        || c == '\u{200F}' // U+200F  UTF-8 E2 80 8F  "Right-To-Left Mark"
+        || c == '\u{2028}' // U+2028  UTF-8 E2 80 A8  "Line Separator"
+        || c == '\u{2029}' // U+2029  UTF-8 E2 80 A9  "Paragraph Separator"
+        { i += c.len_utf8(); continue }
         // End the loop if we encountered anything else.
         break;
     }
@@ -67,9 +65,6 @@ pub fn detect_whitespace(
     if i == chr { UNDETECTED } else { (DETECTED, i) }
 }
 
-// Returns the ascii character at a position, or tilde if invalid or non-ascii.
-fn get_aot(orig: &str, c: usize) -> &str { orig.get(c..c+1).unwrap_or("~") }
-
 
 #[cfg(test)]
 mod tests {
@@ -117,6 +112,33 @@ mod tests {
         assert_eq!(detect(orig, 5), (D,6)); // <NL> advance to <EOI>
     }
 
+    // The simplest possible whitespace detector, to check the fast path
+    // against. Returns the position after the whitespace which starts at `chr`.
+    fn slow_path(orig: &str, chr: usize) -> usize {
+        let is_ws = |c: char|
+            "\t\n\u{B}\u{C}\r \u{85}\u{200E}\u{200F}\u{2028}\u{2029}".contains(c);
+        orig[chr..].find(|c| ! is_ws(c)).map_or(orig.len(), |end| chr + end)
+    }
+
+    #[test]
+    fn detect_whitespace_fast_path_matches_slow_path() {
+        // A 10KB run of spaces, then a non-whitespace char.
+        let orig = " ".repeat(10_000) + "x";
+        assert_eq!(detect(&orig, 0), (D,10_000));
+        assert_eq!(detect(&orig, 0).1, slow_path(&orig, 0));
+        // A 10KB run which ends at the end of input.
+        let orig = "\t \r\n".repeat(2_500);
+        assert_eq!(detect(&orig, 0), (D,10_000));
+        // Ascii and non-ascii whitespace, mixed with other chars, from every
+        // char boundary.
+        let orig = "  \u{2028}\n\u{85}x \u{A0} \u{200E}\u{200F}~€\t\u{2029}\r\n";
+        for (chr, _) in orig.char_indices() {
+            let end = slow_path(orig, chr);
+            let expected = if end == chr { U } else { (D,end) };
+            assert_eq!(detect(orig, chr), expected, "at {}", chr);
+        }
+    }
+
     #[test]
     fn detect_whitespace_will_not_panic() {
         // Near the end of `orig` input code.
@@ -0,0 +1,215 @@
+//! Describes each malformed token in a `LexemizeResult`, for an IDE to show.
+
+use alloc::vec;
+use alloc::string::{String,ToString};
+use alloc::vec::Vec;
+
+use super::lexeme::{Lexeme,LexemeKind,UnexpectedReason};
+const FREEWORD: LexemeKind = LexemeKind::IdentifierFreeword;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+
+/// A problem with the original Rust code, like an unterminated string.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct Diagnostic {
+    /// The position that the problem starts, relative to the start of `orig`.
+    /// Zero indexed.
+    pub chr: usize,
+    /// The length of the problem, in bytes.
+    pub len: usize,
+    /// A short, human readable description of the problem.
+    pub message: String,
+}
+
+/// Describes each `Unexpected`, `Unidentifiable` and `ReservedPrefix` Lexeme.
+/// 
+/// The `detect_*()` functions just return a `LexemeKind`, so the message is
+/// worked out from the Lexeme afterwards. For example, an `Unexpected` Lexeme
+/// which starts with `"` must be an unterminated string. If a Lexeme was
+/// flagged by a checker, like `flag_inexact_floats()`, its reason is used.
+/// 
+/// A raw string with unbalanced hashes, like `r#"abc"`, is not detected as a
+/// string, so it becomes the Identifier `r`, the Punctuation `#` and the Plain
+/// string `"abc"`. That sequence is also described, as a single Diagnostic.
+/// 
+/// ### Arguments
+/// * `lexemes` The Lexemes found by `lexemize()`
+/// * `reasons` The reason that each flagged Lexeme was retagged as Unexpected
+/// 
+/// ### Returns
+/// `diagnose()` returns a vector of [`Diagnostic`] objects, ordered by `chr`.
+pub fn diagnose(
+    lexemes: &[Lexeme],
+    reasons: &[(usize, UnexpectedReason)],
+) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut i = 0;
+    while i < lexemes.len() {
+        let lexeme = &lexemes[i];
+        // A malformed raw string spans several Lexemes, so check for it first.
+        if let Some(end) = find_malformed_raw_string(lexemes, i) {
+            let last = &lexemes[end];
+            diagnostics.push(Diagnostic {
+                chr: lexeme.chr,
+                len: last.chr + last.snippet.len() - lexeme.chr,
+                message: "unterminated raw string, or unbalanced `#` delimiters"
+                    .to_string(),
+            });
+            i = end + 1;
+            continue
+        }
+        let message = match lexeme.kind {
+            LexemeKind::Unexpected => describe_unexpected(lexeme, reasons),
+            LexemeKind::Unidentifiable if lexeme.snippet.chars().count() > 1 =>
+                "unidentifiable characters",
+            LexemeKind::Unidentifiable => "unidentifiable character",
+            LexemeKind::ReservedPrefix => "reserved prefix",
+            _ => { i += 1; continue }
+        };
+        diagnostics.push(Diagnostic {
+            chr: lexeme.chr,
+            len: lexeme.snippet.len(),
+            message: message.to_string(),
+        });
+        i += 1;
+    }
+    diagnostics
+}
+
+// Returns a message for an Unexpected Lexeme, based on why it was flagged, or
+// else on the character it starts with.
+fn describe_unexpected(
+    lexeme: &Lexeme,
+    reasons: &[(usize, UnexpectedReason)],
+) -> &'static str {
+    match reasons.iter().find(|(chr, _)| *chr == lexeme.chr) {
+        Some((_, UnexpectedReason::InexactFloat)) =>
+            return "float literal cannot be represented exactly as `f64`",
+        Some((_, UnexpectedReason::KeywordLifetime)) =>
+            return "lifetime is named after a keyword",
+        Some((_, UnexpectedReason::OversizedNumber)) =>
+            return "integer literal is too large for its type",
+        None => {}
+    }
+    let snippet = lexeme.snippet;
+    if snippet.starts_with("'\\x") {
+        "out of range hex escape, must be `\\x00` to `\\x7F`"
+    } else if snippet.starts_with('\'') {
+        "unterminated character literal"
+    } else if snippet.starts_with('"') {
+        "unterminated string literal"
+    } else if snippet.starts_with("/*") {
+        "unterminated block comment, or nested too deeply"
+    } else if snippet == "\r" {
+        "lone carriage return, not followed by a newline"
+    } else if snippet == "\0" {
+        "NUL character outside a string or comment"
+    } else if snippet.starts_with("r#") {
+        "`crate`, `self`, `super` and `Self` cannot be raw identifiers"
+    } else if snippet.starts_with(|c: char| c.is_ascii_digit()) {
+        "malformed number literal"
+    } else {
+        "unexpected token"
+    }
+}
+
+// If the Lexeme at `i` is the `r` or `br` of a raw string which was not
+// detected, followed directly by zero or more `#` and then a `"`, returns the
+// index of the Lexeme which starts with the `"`.
+pub(crate) fn find_malformed_raw_string(lexemes: &[Lexeme], i: usize) -> Option<usize> {
+    let first = &lexemes[i];
+    if first.kind != FREEWORD || first.snippet != "r" && first.snippet != "br" {
+        return None
+    }
+    for (j, lexeme) in lexemes.iter().enumerate().skip(i + 1) {
+        let prev = &lexemes[j-1];
+        if lexeme.chr != prev.chr + prev.snippet.len() { return None } // a gap
+        if lexeme.snippet.starts_with('"') { return Some(j) }
+        if lexeme.kind != PUNCTUATION || lexeme.snippet != "#" { return None }
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{diagnose,Diagnostic};
+    use super::super::lexemize::{lexemize,lexemize_with_options,LexemizeOptions};
+    use super::super::edition::Edition;
+
+    // Returns the diagnostics for some code, as (chr, len, message) tuples.
+    fn diagnostics(orig: &'static str) -> Vec<(usize, usize, String)> {
+        lexemize(orig).diagnostics.into_iter()
+            .map(|Diagnostic { chr, len, message }| (chr, len, message))
+            .collect()
+    }
+
+    fn d(chr: usize, len: usize, message: &str) -> (usize, usize, String) {
+        (chr, len, message.into())
+    }
+
+    #[test]
+    fn diagnose_unterminated() {
+        assert_eq!(diagnostics("\"abc"),
+            vec![d(0, 4, "unterminated string literal")]);
+        assert_eq!(diagnostics("x = \"abc\n"),
+            vec![d(4, 5, "unterminated string literal")]);
+        assert_eq!(diagnostics("'\\"), vec![
+            d(0, 1, "unterminated character literal"),
+            d(1, 1, "unidentifiable character"),
+        ]);
+        assert_eq!(diagnostics("a /* b"),
+            vec![d(2, 4, "unterminated block comment, or nested too deeply")]);
+    }
+
+    #[test]
+    fn diagnose_malformed() {
+        assert_eq!(diagnostics("0b12;"), vec![d(0, 4, "malformed number literal")]);
+        assert_eq!(diagnostics("'\\xFF'"),
+            vec![d(0, 6, "out of range hex escape, must be `\\x00` to `\\x7F`")]);
+        assert_eq!(diagnostics("€€ x"), vec![d(0, 6, "unidentifiable characters")]);
+        // Raw strings with unbalanced hashes, or no closing quote.
+        assert_eq!(diagnostics("r#\"abc\""),
+            vec![d(0, 7, "unterminated raw string, or unbalanced `#` delimiters")]);
+        assert_eq!(diagnostics("x = br##\"abc"),
+            vec![d(4, 8, "unterminated raw string, or unbalanced `#` delimiters")]);
+        assert_eq!(diagnostics("r\"abc"),
+            vec![d(0, 5, "unterminated raw string, or unbalanced `#` delimiters")]);
+        // Keywords which cannot be raw identifiers.
+        assert_eq!(diagnostics("r#fn r#self"), vec![d(5, 6,
+            "`crate`, `self`, `super` and `Self` cannot be raw identifiers")]);
+        // A reserved prefix, from Rust 2024.
+        let options = LexemizeOptions {
+            edition: Edition::Rust2024,
+            ..LexemizeOptions::default()
+        };
+        assert_eq!(lexemize_with_options("foo#bar", options).diagnostics,
+            vec![Diagnostic { chr: 0, len: 3, message: "reserved prefix".into() }]);
+    }
+
+    #[test]
+    fn diagnose_checked() {
+        let options = LexemizeOptions { checked: true, ..LexemizeOptions::default() };
+        let result = lexemize_with_options("'fn 0.1", options);
+        assert_eq!(result.diagnostics, vec![
+            Diagnostic { chr: 0, len: 3,
+                message: "lifetime is named after a keyword".into() },
+            Diagnostic { chr: 4, len: 3,
+                message: "float literal cannot be represented exactly as `f64`".into() },
+        ]);
+        // An opt-in pass updates the diagnostics too.
+        let mut result = lexemize("256u8");
+        assert_eq!(result.diagnostics, vec![]);
+        result.flag_oversized_numbers();
+        assert_eq!(result.diagnostics, vec![Diagnostic { chr: 0, len: 5,
+            message: "integer literal is too large for its type".into() }]);
+    }
+
+    #[test]
+    fn diagnose_valid_code() {
+        assert_eq!(diagnostics(""), vec![]);
+        assert_eq!(diagnostics("let r = r#\"abc\"#; r / \"x\""), vec![]);
+        assert_eq!(diagnostics("r #\"x\"#"), vec![]); // `r` then a space
+        assert_eq!(diagnostics("#[derive(Debug)] r"), vec![]);
+        assert_eq!(diagnose(&[], &[]), vec![]);
+    }
+}
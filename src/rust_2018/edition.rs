@@ -0,0 +1,26 @@
+//! The Rust edition which the original code is written for.
+
+/// The Rust edition which the original code is written for.
+/// 
+/// A few words changed status between editions. For example `async` is an
+/// ordinary identifier in Rust 2015, but a Keyword in Rust 2018. A
+/// [`Lexemizer`] runs `detect_identifier_for()` with its edition wherever its
+/// pipeline has `detect_identifier()`. A custom identifier detector does not
+/// see the edition, so should call `detect_identifier_for()` itself.
+/// 
+/// [`Lexemizer`]: super::lexemize::Lexemizer
+/// 
+/// Editions are ordered by year, so `edition >= Edition::Rust2021` is true for
+/// Rust 2021 and every later edition.
+#[derive(Clone,Copy,Debug,Default,Eq,Ord,PartialEq,PartialOrd)]
+pub enum Edition {
+    /// The 2015 edition, where `async`, `await`, `dyn` and `try` are Freewords.
+    Rust2015,
+    /// The 2018 edition, which is the default.
+    #[default]
+    Rust2018,
+    /// The 2021 edition.
+    Rust2021,
+    /// The 2024 edition, which reserves prefixes like the `foo` of `foo#bar`.
+    Rust2024,
+}
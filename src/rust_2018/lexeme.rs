@@ -1,96 +1,1005 @@
 //! An enum and a struct used by `lexemize()`.
 
-use std::fmt;
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::Range;
+use unicode_width::UnicodeWidthChar;
 
-///
+use super::detect::identifier::keyword_category;
+
+// How many columns `Lexeme::display_width()` counts for a tab.
+const TAB_WIDTH: usize = 4;
+
+/// The category of a Lexeme.
+/// 
+/// Each `LexemeKind` is a single bit, and related kinds share the same byte:
 /// ```txt
-/// 0000000000000000000000000000XXXX   0 -  3  Character
-/// 000000000000000000000000XXXX0000   4 -  7  Comment
-/// 00000000000000000000XXXX00000000   8 - 11  Identifier
-/// 0000000000000000XXXX000000000000  12 - 15  Number
-/// 000000000000XXXX0000000000000000  16 - 19  Punctuation
-/// 00000000XXXX00000000000000000000  20 - 23  String
-/// 0000XXXX000000000000000000000000  24 - 27  Undetected, etc
-/// XXXX0000000000000000000000000000  28 - 31  Whitespace
+/// 00000000000000000000000000000000000000000000000000000000XXXXXXXX   0 -  7  Character
+/// 000000000000000000000000000000000000000000000000XXXXXXXX00000000   8 - 15  Comment
+/// 0000000000000000000000000000000000000000XXXXXXXX0000000000000000  16 - 23  Identifier
+/// 00000000000000000000000000000000XXXXXXXX000000000000000000000000  24 - 31  Number
+/// 000000000000000000000000XXXXXXXX00000000000000000000000000000000  32 - 39  Punctuation
+/// 0000000000000000XXXXXXXX0000000000000000000000000000000000000000  40 - 47  String
+/// 00000000XXXXXXXX000000000000000000000000000000000000000000000000  48 - 55  Undetected, etc
+/// XXXXXXXX00000000000000000000000000000000000000000000000000000000  56 - 63  Whitespace
 /// ```
 /// 
-#[derive(Clone,Copy,Debug,PartialEq)]
+/// `LexemeKinds` are ordered by their bit, so kinds in the same group sort
+/// together. They can also be hashed, so either a `BTreeMap` or a `HashMap`
+/// can be keyed by `LexemeKind`.
+#[derive(Clone,Copy,Debug,Eq,Hash,Ord,PartialEq,PartialOrd)]
+#[repr(u64)]
 pub enum LexemeKind {
     /// Not used yet.
-    CharacterByte = 1,
+    CharacterByte = 1 << 0,
+    /// A 7-bit `char` literal, like `'\x4A'`.
+    CharacterHex = 1 << 1,
+    /// A plain `char` literal, like `'A'` or `'\n'`.
+    CharacterPlain = 1 << 2,
+    /// A unicode `char` literal, like `'\u{03aB}'`.
+    CharacterUnicode = 1 << 3,
+
+    /// An inline doc comment, like `/// foo` or `//! foo`.
+    CommentDocInline = 1 << 8,
+    /// A multiline doc comment, like `/** foo */` or `/*! foo */`.
+    CommentDocMultiline = 1 << 9,
+    /// An inline comment, like `// foo`.
+    CommentInline = 1 << 10,
+    /// A multiline comment, like `/* foo */`.
+    CommentMultiline = 1 << 11,
+    /// A shebang line at the start of the input, like `#!/usr/bin/env rustx`,
+    /// which may follow a `ByteOrderMark`.
+    CommentShebang = 1 << 12,
+
+    /// Any identifier which is not a Keyword or StdType, like `foo`.
+    IdentifierFreeword = 1 << 16,
+    /// A keyword, like `let` or `while`.
+    IdentifierKeyword = 1 << 17,
+    /// Not used yet.
+    IdentifierOther = 1 << 18,
+    /// A primitive type, like `u8` or `str`.
+    IdentifierStdType = 1 << 19,
+    /// The name of an invoked macro, like the `println` of `println!()`.
+    IdentifierMacro = 1 << 20,
+    /// The `for` of a higher-ranked trait bound, like `for<'a> Fn(&'a u8)`.
+    IdentifierHrtbFor = 1 << 21,
+    /// A lifetime, like `'a` or `'static`.
+    IdentifierLifetime = 1 << 22,
+    /// A loop or block label and its colon, like the `'outer:` of
+    /// `'outer: loop {}`.
+    Label = 1 << 23,
+
+    /// A binary number literal, like `0b1001`.
+    NumberBinary = 1 << 24,
+    /// A hexadecimal number literal, like `0xA9`.
+    NumberHex = 1 << 25,
+    /// An octal number literal, like `0o17`.
+    NumberOctal = 1 << 26,
+    /// A decimal integer literal, like `42`.
+    NumberDecimal = 1 << 27,
+    /// A decimal float literal, like `12.34`, `1e3` or `1f64`.
+    NumberDecimalFloat = 1 << 28,
+
+    /// A sequence of Punctuation characters, like `;` or `>>=`.
+    Punctuation = 1 << 32,
+    /// A `*`, `+` or `?` repetition operator in a `macro_rules!`, like the `*`
+    /// of `$($x),*`.
+    MacroRepeatOp = 1 << 33,
+    /// The `#` or `#!` which opens an attribute, like `#[derive(Debug)]`.
+    AttributeStart = 1 << 34,
+
+    /// Not used yet.
+    StringByte = 1 << 40,
+    /// Not used yet.
+    StringByteRaw = 1 << 41,
+    /// A plain string literal, like `"Hello \"Rust\""`.
+    StringPlain = 1 << 42,
+    /// A raw string literal, like `r#"Hello "Rust""#`.
+    StringRaw = 1 << 43,
+
+    /// Returned by a `detect_*()` function which did not detect its Lexeme.
+    Undetected = 1 << 48,
+    /// A malformed token, like the unterminated string `"abc` or `0b12`.
+    Unexpected = 1 << 49,
+    /// A run of characters which no `detect_*()` function recognised.
+    Unidentifiable = 1 << 50,
+    /// An identifier which is immediately followed by `#`, `"` or `'`, like
+    /// the `foo` of `foo#bar`. This is only produced for `Edition::Rust2024`
+    /// and later.
+    ReservedPrefix = 1 << 51,
+
+    /// A sequence of Whitespace characters.
+    WhitespaceTrimmable = 1 << 56,
+    /// All the Whitespace and Comments between two other Lexemes, merged into
+    /// one. Only produced if `LexemizeOptions::compact_trivia` is set.
+    Trivia = 1 << 57,
+    /// A UTF-8 byte order mark, `U+FEFF`, at the very start of the input.
+    ByteOrderMark = 1 << 58,
+    /// The end-of-input sentinel, which is always the last Lexeme. Its
+    /// snippet is empty, but it is displayed as `<EOI>`.
+    EndOfInput = 1 << 59,
+}
+
+impl LexemeKind {
+    /// Returns `true` if this is one of the `Character*` kinds.
+    pub fn is_character(&self) -> bool { self.in_group(0) }
+    /// Returns `true` if this is one of the `Comment*` kinds.
+    pub fn is_comment(&self) -> bool { self.in_group(1) }
+    /// Returns `true` if this is one of the `Identifier*` kinds.
+    pub fn is_identifier(&self) -> bool { self.in_group(2) }
+    /// Returns `true` if this is one of the `Number*` kinds.
+    pub fn is_number(&self) -> bool { self.in_group(3) }
+    /// Returns `true` if this is `Punctuation`, `MacroRepeatOp` or
+    /// `AttributeStart`.
+    pub fn is_punctuation(&self) -> bool { self.in_group(4) }
+    /// Returns `true` if this is one of the `String*` kinds.
+    pub fn is_string(&self) -> bool { self.in_group(5) }
+    /// Returns `true` if this is `Undetected`, `Unexpected`, `Unidentifiable`
+    /// or `ReservedPrefix`.
+    pub fn is_other(&self) -> bool { self.in_group(6) }
+    /// Returns `true` if this is `WhitespaceTrimmable`, `Trivia`,
+    /// `ByteOrderMark` or `EndOfInput`.
+    pub fn is_whitespace(&self) -> bool { self.in_group(7) }
+
+    /// Returns the coarse group of this kind, like `LexemeGroup::Number` for
+    /// `NumberHex`.
     /// 
-    CharacterHex = 2,
+    /// The group is found from the byte which holds this kind’s bit, so it
+    /// agrees with the `is_*()` functions, and with any kind added later.
+    pub fn group(&self) -> LexemeGroup {
+        match (*self as u64).trailing_zeros() / 8 {
+            0 => LexemeGroup::Character,
+            1 => LexemeGroup::Comment,
+            2 => LexemeGroup::Identifier,
+            3 => LexemeGroup::Number,
+            4 => LexemeGroup::Punctuation,
+            5 => LexemeGroup::String,
+            6 => LexemeGroup::Other,
+            _ => LexemeGroup::Whitespace,
+        }
+    }
+
+    // Returns `true` if this kind’s bit is in byte number `group`.
+    fn in_group(&self, group: u64) -> bool {
+        *self as u64 & (0xFF << (group * 8)) != 0
+    }
+}
+
+impl From<LexemeKind> for u64 {
+    /// Converts a kind to its discriminant, like `1 << 57` for `Trivia`. A
+    /// `u32` cannot hold every discriminant, so the conversion is to `u64`.
+    fn from(kind: LexemeKind) -> Self {
+        kind as u64
+    }
+}
+
+impl TryFrom<u64> for LexemeKind {
+    /// The value which is not the discriminant of any kind.
+    type Error = u64;
+
+    /// Converts a discriminant, from `u64::from(kind)`, back to the kind.
+    /// Fails for a value which no kind uses, like `3`, which has two bits set,
+    /// or `1 << 4`, which is in the Character byte but is not a Character kind.
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        if ! value.is_power_of_two() { return Err(value) }
+        let bit = value.trailing_zeros();
+        use LexemeKind::*;
+        Ok(match bit {
+             0 => CharacterByte,
+             1 => CharacterHex,
+             2 => CharacterPlain,
+             3 => CharacterUnicode,
+             8 => CommentDocInline,
+             9 => CommentDocMultiline,
+            10 => CommentInline,
+            11 => CommentMultiline,
+            12 => CommentShebang,
+            16 => IdentifierFreeword,
+            17 => IdentifierKeyword,
+            18 => IdentifierOther,
+            19 => IdentifierStdType,
+            20 => IdentifierMacro,
+            21 => IdentifierHrtbFor,
+            22 => IdentifierLifetime,
+            23 => Label,
+            24 => NumberBinary,
+            25 => NumberHex,
+            26 => NumberOctal,
+            27 => NumberDecimal,
+            28 => NumberDecimalFloat,
+            32 => Punctuation,
+            33 => MacroRepeatOp,
+            34 => AttributeStart,
+            40 => StringByte,
+            41 => StringByteRaw,
+            42 => StringPlain,
+            43 => StringRaw,
+            48 => Undetected,
+            49 => Unexpected,
+            50 => Unidentifiable,
+            51 => ReservedPrefix,
+            56 => WhitespaceTrimmable,
+            57 => Trivia,
+            58 => ByteOrderMark,
+            59 => EndOfInput,
+            _ => return Err(value),
+        })
+    }
+}
+
+/// The coarse category of a `LexemeKind`, from `LexemeKind::group()`.
+/// 
+/// Each group is one byte of the `LexemeKind` layout, in the same order, for
+/// code which does not care whether a Number is `NumberHex` or `NumberDecimal`.
+#[derive(Clone,Copy,Debug,Eq,Ord,PartialEq,PartialOrd)]
+pub enum LexemeGroup {
+    /// `CharacterByte`, `CharacterHex`, `CharacterPlain` or `CharacterUnicode`.
+    Character,
+    /// Any of the `Comment*` kinds.
+    Comment,
+    /// Any of the `Identifier*` kinds, or `Label`.
+    Identifier,
+    /// Any of the `Number*` kinds.
+    Number,
+    /// `Punctuation`, `MacroRepeatOp` or `AttributeStart`.
+    Punctuation,
+    /// Any of the `String*` kinds.
+    String,
+    /// `Undetected`, `Unexpected`, `Unidentifiable` or `ReservedPrefix`.
+    Other,
+    /// `WhitespaceTrimmable`, `Trivia`, `ByteOrderMark` or `EndOfInput`.
+    Whitespace,
+}
+
+/// The reason that a Lexeme was flagged as `LexemeKind::Unexpected`.
+/// 
+/// Only recorded if `LexemizeOptions::checked` is set, or by an opt-in pass
+/// like `LexemizeResult::flag_oversized_numbers()`.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum UnexpectedReason {
+    /// A decimal float literal which cannot be represented exactly as an
+    /// `f64`, like `0.1`.
+    InexactFloat,
+    /// A lifetime which is named after a keyword, like `'fn`.
+    KeywordLifetime,
+    /// An integer literal which is too large for its type, like `256u8`.
+    OversizedNumber,
+}
+
+/// An operator or delimiter, which a Punctuation Lexeme’s snippet can be
+/// classified as, using `Lexeme::punctuation_op()`.
+/// 
+/// The names follow the Rust Reference’s ‘Punctuation’ table, except that `->`
+/// is `ThinArrow`, to pair with `FatArrow`.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum PunctOp {
+    /// `'` Labels and Lifetimes.
+    SingleQuote,
+    /// `_` Wildcard patterns and Inferred types.
+    Underscore,
+    /// `-` Subtraction and Negation.
+    Minus,
+    /// `,` Various separators.
+    Comma,
+    /// `;` Terminator.
+    Semi,
+    /// `:` Various separators.
+    Colon,
+    /// `!` Bitwise and Logical NOT, and Macro calls.
+    Not,
+    /// `?` Question mark operator.
+    Question,
+    /// `.` Field access and Tuple index.
+    Dot,
+    /// `(` Open parenthesis.
+    OpenParen,
+    /// `)` Close parenthesis.
+    CloseParen,
+    /// `[` Open square bracket.
+    OpenBracket,
+    /// `]` Close square bracket.
+    CloseBracket,
+    /// `{` Open curly brace.
+    OpenBrace,
+    /// `}` Close curly brace.
+    CloseBrace,
+    /// `@` Subpattern binding.
+    At,
+    /// `*` Multiplication, Dereference and Raw pointers.
+    Star,
+    /// `/` Division.
+    Slash,
+    /// `&` Bitwise and Logical AND, and Borrow.
+    And,
+    /// `#` Attributes.
+    Pound,
+    /// `%` Remainder.
+    Percent,
+    /// `^` Bitwise and Logical XOR.
+    Caret,
+    /// `+` Addition and Trait bounds.
+    Plus,
+    /// `<` Less than, Generics and Paths.
+    Lt,
+    /// `=` Assignment.
+    Eq,
+    /// `>` Greater than, Generics and Paths.
+    Gt,
+    /// `|` Bitwise and Logical OR, and Closures.
+    Or,
+    /// `$` Macros.
+    Dollar,
+    /// `-=` Subtraction assignment.
+    MinusEq,
+    /// `->` Function return type, `RArrow` in the Reference.
+    ThinArrow,
+    /// `::` Path separator.
+    PathSep,
+    /// `!=` Not equal.
+    Ne,
+    /// `..` Range, struct update like `..Default::default()`, and rest
+    /// patterns.
+    DotDot,
+    /// `*=` Multiplication assignment.
+    StarEq,
+    /// `/=` Division assignment.
+    SlashEq,
+    /// `&&` Lazy AND.
+    AndAnd,
+    /// `&=` Bitwise AND assignment.
+    AndEq,
+    /// `%=` Remainder assignment.
+    PercentEq,
+    /// `^=` Bitwise XOR assignment.
+    CaretEq,
+    /// `+=` Addition assignment.
+    PlusEq,
+    /// `<<` Shift left.
+    Shl,
+    /// `<=` Less than or equal to.
+    Le,
+    /// `==` Equal.
+    EqEq,
+    /// `=>` Match arms and Macros.
+    FatArrow,
+    /// `>=` Greater than or equal to.
+    Ge,
+    /// `>>` Shift right.
+    Shr,
+    /// `|=` Bitwise OR assignment.
+    OrEq,
+    /// `||` Lazy OR and Closures.
+    OrOr,
+    /// `...` Variadic functions, and obsolete range patterns.
+    DotDotDot,
+    /// `..=` Inclusive range, and range patterns.
+    DotDotEq,
+    /// `<<=` Shift left assignment.
+    ShlEq,
+    /// `>>=` Shift right assignment.
+    ShrEq,
+}
+
+impl PunctOp {
+    /// Classifies a snippet like `=>` as a `PunctOp`, like `PunctOp::FatArrow`.
     /// 
-    CharacterPlain = 4,
+    /// ### Returns
+    /// `from_snippet()` returns `None` if `snippet` is not exactly one of the
+    /// operators or delimiters detected by `detect_punctuation()`.
+    pub fn from_snippet(snippet: &str) -> Option<PunctOp> {
+        Some(match snippet {
+            "'" => PunctOp::SingleQuote,
+            "_" => PunctOp::Underscore,
+            "-" => PunctOp::Minus,
+            "," => PunctOp::Comma,
+            ";" => PunctOp::Semi,
+            ":" => PunctOp::Colon,
+            "!" => PunctOp::Not,
+            "?" => PunctOp::Question,
+            "." => PunctOp::Dot,
+            "(" => PunctOp::OpenParen,
+            ")" => PunctOp::CloseParen,
+            "[" => PunctOp::OpenBracket,
+            "]" => PunctOp::CloseBracket,
+            "{" => PunctOp::OpenBrace,
+            "}" => PunctOp::CloseBrace,
+            "@" => PunctOp::At,
+            "*" => PunctOp::Star,
+            "/" => PunctOp::Slash,
+            "&" => PunctOp::And,
+            "#" => PunctOp::Pound,
+            "%" => PunctOp::Percent,
+            "^" => PunctOp::Caret,
+            "+" => PunctOp::Plus,
+            "<" => PunctOp::Lt,
+            "=" => PunctOp::Eq,
+            ">" => PunctOp::Gt,
+            "|" => PunctOp::Or,
+            "$" => PunctOp::Dollar,
+            "-=" => PunctOp::MinusEq,
+            "->" => PunctOp::ThinArrow,
+            "::" => PunctOp::PathSep,
+            "!=" => PunctOp::Ne,
+            ".." => PunctOp::DotDot,
+            "*=" => PunctOp::StarEq,
+            "/=" => PunctOp::SlashEq,
+            "&&" => PunctOp::AndAnd,
+            "&=" => PunctOp::AndEq,
+            "%=" => PunctOp::PercentEq,
+            "^=" => PunctOp::CaretEq,
+            "+=" => PunctOp::PlusEq,
+            "<<" => PunctOp::Shl,
+            "<=" => PunctOp::Le,
+            "==" => PunctOp::EqEq,
+            "=>" => PunctOp::FatArrow,
+            ">=" => PunctOp::Ge,
+            ">>" => PunctOp::Shr,
+            "|=" => PunctOp::OrEq,
+            "||" => PunctOp::OrOr,
+            "..." => PunctOp::DotDotDot,
+            "..=" => PunctOp::DotDotEq,
+            "<<=" => PunctOp::ShlEq,
+            ">>=" => PunctOp::ShrEq,
+            _ => return None,
+        })
+    }
+
+    /// Returns `true` for the structural delimiters `(`, `)`, `[`, `]`, `{`
+    /// and `}`.
+    pub fn is_delimiter(&self) -> bool {
+        use PunctOp::*;
+        matches!(self, OpenParen | CloseParen | OpenBracket | CloseBracket
+            | OpenBrace | CloseBrace)
+    }
+
+    /// Returns `true` for an arithmetic, bitwise, comparison, logical or
+    /// assignment operator, like `+`, `<<=` or `&&`.
     /// 
-    CharacterUnicode = 8,
+    /// Punctuation which is not really an expression operator, like `;`, `,`,
+    /// `::`, `=>` or `..`, is neither an operator nor a delimiter.
+    pub fn is_operator(&self) -> bool {
+        use PunctOp::*;
+        matches!(self, Minus | Not | Star | Slash | And | Percent | Caret
+            | Plus | Lt | Eq | Gt | Or | MinusEq | Ne | StarEq | SlashEq
+            | AndAnd | AndEq | PercentEq | CaretEq | PlusEq | Shl | Le | EqEq
+            | Ge | Shr | OrEq | OrOr | ShlEq | ShrEq)
+    }
+}
 
-    /// Not used yet.
-    CommentDocInline = 16,
-    /// Not used yet.
-    CommentDocMultiline = 32,
+/// What a run of Whitespace is made of, for tab-vs-space linters.
+/// 
+/// Returned by `Lexeme::whitespace_stats()`.
+#[derive(Clone,Copy,Debug,Default,Eq,PartialEq)]
+pub struct WhitespaceStats {
+    /// The number of spaces, `U+0020`.
+    pub spaces: usize,
+    /// The number of horizontal tabs, `\t`.
+    pub tabs: usize,
+    /// The number of line feeds, `\n`. A Windows style `\r\n` counts as one
+    /// newline.
+    pub newlines: usize,
+    /// The number of any other Whitespace characters, like a lone `\r` or
+    /// `U+2028`.
+    pub other: usize,
+}
+
+/// Whether a doc comment documents the item it is inside, or the next item.
+/// 
+/// Returned by `Lexeme::doc_placement()`.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum DocPlacement {
+    /// An inner doc comment, like `//! text` or `/*! text */`, which documents
+    /// the enclosing item, usually a crate or module.
+    Inner,
+    /// An outer doc comment, like `/// text` or `/** text */`, which documents
+    /// the item which follows it.
+    Outer,
+}
+
+/// How strictly a Keyword is reserved, from the Rust Reference.
+/// 
+/// Returned by `Lexeme::keyword_category()`.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum KeywordCategory {
+    /// A Keyword which is reserved for future use, like `become` or `typeof`.
+    /// These are not used yet, but still can not be used as identifiers.
+    Reserved,
+    /// A Keyword which is in use, like `fn` or `while`. These can never be
+    /// used as identifiers.
+    Strict,
+    /// A Keyword which only has a special meaning in certain contexts, like
+    /// `union`. Elsewhere, it is an ordinary identifier.
+    Weak,
+}
+
+/// A single Lexeme, which is a slice of the original code and its category.
+/// 
+/// Lexemes are ordered by `chr`, so a vector of Lexemes from `lexemize()` is
+/// already sorted, and can be searched with `binary_search_by()`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Lexeme<'a> {
+    /// Category of the Lexeme.
+    pub kind: LexemeKind,
+    /// The position that the Lexeme starts, relative to the start of `orig`.
+    /// Zero indexed.
+    pub chr: usize,
+    /// The slice of `orig` which the Lexeme covers.
+    pub snippet: &'a str,
+}
+
+impl<'a> Lexeme<'a> {
+    /// Returns `true` if the Lexeme starts at the byte offset `offset`.
+    pub fn starts_at(&self, offset: usize) -> bool {
+        self.chr == offset
+    }
+
+    /// Returns `true` if the Lexeme is Whitespace, Trivia or any kind of
+    /// Comment, which a parser usually skips, or attaches to the next token.
+    pub fn is_trivia(&self) -> bool {
+        self.kind.is_whitespace() || self.kind.is_comment()
+    }
+
+    /// Returns the length of the snippet in bytes. The `<EOI>` sentinel’s
+    /// snippet is empty, so its length is `0`.
+    pub fn len(&self) -> usize {
+        self.snippet.len()
+    }
+
+    /// Returns `true` if the Lexeme has no length, which is only the case for
+    /// the `<EOI>` sentinel.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the byte offset just after the Lexeme ends, which is where the
+    /// next Lexeme starts.
+    pub fn end(&self) -> usize {
+        self.chr + self.len()
+    }
+
+    /// Returns the byte range of the Lexeme in the original code, which can be
+    /// used to slice it, like `&orig[lexeme.range()]`.
+    pub fn range(&self) -> Range<usize> {
+        self.chr..self.end()
+    }
+
+    /// Returns how many columns the snippet takes up in a monospace table,
+    /// like the one made by `LexemizeResult::format_with()`.
     /// 
-    CommentInline = 64,
+    /// Wide chars, like most CJK chars and emoji, take up two columns, and
+    /// combining marks take up none. As in the table’s default layout, each
+    /// `\n` is shown as `<NL>`, and the `<EOI>` sentinel as `<EOI>`. A tab
+    /// counts as four columns, and any other control char, like `\r`, as zero.
+    pub fn display_width(&self) -> usize {
+        if self.is_eoi() { return "<EOI>".len() }
+        self.snippet.chars().map(|c| match c {
+            '\n' => "<NL>".len(),
+            '\t' => TAB_WIDTH,
+            c => c.width().unwrap_or(0),
+        }).sum()
+    }
+
+    /// Returns `true` if the byte offset `offset` is inside the Lexeme’s span.
     /// 
-    CommentMultiline = 128,
+    /// The span includes `chr`, but not the position after the snippet ends.
+    /// The `<EOI>` sentinel has an empty span, so it never contains an offset.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.range().contains(&offset)
+    }
 
+    /// Splits the Lexeme into two, at a byte offset relative to its `chr`.
     /// 
-    IdentifierFreeword = 256,
+    /// Both halves inherit the original `kind`, so the caller should re-tag
+    /// them as needed. For example, a refinement could split `>>` into `>` and
+    /// `>`, at the end of nested generics.
     /// 
-    IdentifierKeyword = 512,
-    /// Not used yet.
-    IdentifierOther = 1024,
+    /// ### Arguments
+    /// * `byte_offset` The position to split at, relative to the snippet start
     /// 
-    IdentifierStdType = 2048,
+    /// ### Returns
+    /// `split_at()` returns the two halves, or `None` if either half would be
+    /// empty, `byte_offset` is not on a char boundary, or this is the `<EOI>`
+    /// sentinel.
+    pub fn split_at(self, byte_offset: usize) -> Option<(Lexeme<'a>, Lexeme<'a>)> {
+        if self.is_eoi()
+        || byte_offset == 0
+        || byte_offset >= self.snippet.len()
+        || ! self.snippet.is_char_boundary(byte_offset)
+            { return None }
+        let (first, second) = self.snippet.split_at(byte_offset);
+        Some((
+            Lexeme { kind: self.kind, chr: self.chr, snippet: first },
+            Lexeme { kind: self.kind, chr: self.chr + byte_offset, snippet: second },
+        ))
+    }
 
+    /// Returns the text of a doc comment, without its delimiters.
     /// 
-    NumberBinary = 4096,
+    /// For `/// foo` or `//! foo`, that is ` foo`, without any line ending.
+    /// For `/** foo */` or `/*! foo */`, that is ` foo `. If a multiline doc
+    /// comment continues over several lines, any `* ` at the start of the
+    /// second and later lines is also removed, after optional indentation.
+    /// Line endings, including Windows style `\r\n`, are kept as they are.
     /// 
-    NumberHex = 8192,
+    /// ### Returns
+    /// `doc_text()` returns `None` if this is not a `CommentDoc*` Lexeme. The
+    /// text is borrowed from `snippet` unless asterisks had to be removed.
+    pub fn doc_text(&self) -> Option<Cow<'a, str>> {
+        let snippet = self.snippet;
+        match self.kind {
+            LexemeKind::CommentDocInline => {
+                // Remove the leading "///" or "//!", and any line ending.
+                let text = snippet.get(3..)?;
+                let text = text.strip_suffix('\n').unwrap_or(text);
+                Some(Cow::Borrowed(text.strip_suffix('\r').unwrap_or(text)))
+            }
+            LexemeKind::CommentDocMultiline => {
+                // Remove the leading "/**" or "/*!", and the trailing "*/".
+                let text = snippet.get(3..snippet.len().checked_sub(2)?)?;
+                if ! text.contains('\n') { return Some(Cow::Borrowed(text)) }
+                // Remove "* " or a lone "*" after the indent of later lines.
+                let mut lines = text.split_inclusive('\n');
+                let mut stripped: String = lines.next()?.into();
+                for line in lines {
+                    let indented = line.trim_start_matches([' ', '\t']);
+                    stripped.push_str(
+                        if let Some(rest) = indented.strip_prefix("* ") { rest }
+                        else if indented == "*" || indented.starts_with("*\n")
+                        || indented.starts_with("*\r\n") { &indented[1..] }
+                        else { line }
+                    );
+                }
+                Some(Cow::Owned(stripped))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns each line of a multiline doc comment, like `["a", "b"]` for
+    /// `/** a\n * b\n */`, for documentation tools.
     /// 
-    NumberOctal = 16384,
+    /// The `/**` or `/*!` and the `*/` are removed. On the second and later
+    /// lines, any `* ` or lone `*` is removed, after optional indentation, as
+    /// in `doc_text()`. So asterisks may be aligned or not. A line with no
+    /// leading `*` is kept as it is, including its indentation. One space after
+    /// the `/**` is removed, and so is any whitespace before the `*/`. If the
+    /// `/**` or the `*/` is on a line of its own, that line is left out.
     /// 
-    NumberDecimal = 32768,
+    /// ### Returns
+    /// `block_doc_lines()` returns the lines, without line endings, or an
+    /// empty vector if this is not a `CommentDocMultiline` Lexeme.
+    pub fn block_doc_lines(&self) -> Vec<&'a str> {
+        if self.kind != LexemeKind::CommentDocMultiline { return vec![] }
+        let snippet = self.snippet;
+        let text = match snippet.get(3..snippet.len().saturating_sub(2)) {
+            Some(text) => text,
+            None => return vec![],
+        };
+        let mut lines: Vec<&'a str> = text.split('\n').enumerate().map(|(i, line)| {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if i == 0 { return line.strip_prefix(' ').unwrap_or(line) }
+            let indented = line.trim_start_matches([' ', '\t']);
+            if let Some(rest) = indented.strip_prefix("* ") { rest }
+            else if indented == "*" { "" }
+            else { line }
+        }).collect();
+        // Remove the space before the `*/`, and the lines the delimiters may
+        // have to themselves.
+        if let Some(last) = lines.last_mut() { *last = last.trim_end() }
+        if lines.last() == Some(&"") { lines.pop(); }
+        if lines.first() == Some(&"") { lines.remove(0); }
+        lines
+    }
 
+    /// Returns the value of a string literal, with its escapes decoded.
     /// 
-    Punctuation = 65536,
+    /// For a plain string like `"a\nb"`, the quotes are removed, and escapes
+    /// like `\n`, `\t`, `\\`, `\"`, `\x41` and `\u{1F600}` are decoded. A
+    /// backslash at the end of a line skips the newline and any indentation
+    /// after it. An invalid escape, like `\q`, is kept as it is.
+    /// 
+    /// For a raw string like `r#"a\nb"#`, the `r`, hashes and quotes are
+    /// removed, and the content is returned verbatim.
+    /// 
+    /// ### Returns
+    /// `string_value()` returns `None` if this is not a `String*` Lexeme.
+    pub fn string_value(&self) -> Option<String> {
+        let snippet = self.snippet;
+        match self.kind {
+            LexemeKind::StringPlain | LexemeKind::StringByte => {
+                // Remove the `b` prefix, if any, and the quotes.
+                let snippet = snippet.strip_prefix('b').unwrap_or(snippet);
+                Some(unescape(snippet.get(1..snippet.len().checked_sub(1)?)?))
+            }
+            LexemeKind::StringRaw | LexemeKind::StringByteRaw => {
+                // Remove the `r` or `br` prefix, the hashes and the quotes.
+                let snippet = snippet.strip_prefix('b').unwrap_or(snippet);
+                let snippet = snippet.strip_prefix('r')?;
+                let hashes = snippet.len() - snippet.trim_start_matches('#').len();
+                let end = snippet.len().checked_sub(hashes + 1)?;
+                Some(snippet.get(hashes+1..end)?.into())
+            }
+            _ => None,
+        }
+    }
 
-    /// Not used yet.
-    StringByte = 1048576,
-    /// Not used yet.
-    StringByteRaw = 2097152,
+    /// Returns the content of a string or character literal, between its
+    /// delimiters, like the `a\nb` of `"a\nb"`.
     /// 
-    StringPlain = 4194304,
+    /// Unlike `string_value()`, escapes are not decoded, so the content is
+    /// exactly as it appears in the original Rust code. Any `b` or `r` prefix
+    /// is removed, along with the hashes of a raw string like `r#"x"#`, and the
+    /// quotes. For a character literal like `'A'` or `b'\x41'`, the prefix and
+    /// the single quotes are removed.
     /// 
-    StringRaw = 8388608,
+    /// ### Returns
+    /// `snippet_without_delimiters()` returns `None` if this is not a
+    /// `Character*` or `String*` Lexeme, or if it is too short to have both
+    /// delimiters.
+    pub fn snippet_without_delimiters(&self) -> Option<&'a str> {
+        let snippet = self.snippet;
+        // Remove the `b` prefix, if any, then find the length of the opening
+        // and closing delimiters. A raw string’s hashes are on both sides.
+        let snippet = snippet.strip_prefix('b').unwrap_or(snippet);
+        let (open, close) = match self.kind {
+            LexemeKind::StringRaw | LexemeKind::StringByteRaw => {
+                let after_r = snippet.strip_prefix('r')?;
+                let hashes = after_r.len() - after_r.trim_start_matches('#').len();
+                (hashes + 2, hashes + 1)
+            }
+            _ if self.kind.is_character() || self.kind.is_string() => (1, 1),
+            _ => return None,
+        };
+        snippet.get(open..snippet.len().checked_sub(close)?)
+    }
 
-    ///
-    Undetected = 16777216,
+    /// Finds the `{...}` placeholders in a string literal, like the `{}` and
+    /// `{name}` of `"{} {name} {{literal}}"`.
+    /// 
+    /// This assumes the string is passed to a format macro, like `format!()`,
+    /// so `{{` and `}}` are escaped braces, not placeholders. In a plain string,
+    /// escapes like `\u{7B}` are skipped. A raw string has no escapes, so its
+    /// braces are read just like a plain string’s. A `{` with no closing `}`,
+    /// and anything after it, is ignored.
     /// 
-    Unexpected = 33554432,
+    /// ### Returns
+    /// `format_placeholders()` returns the byte range of each placeholder in
+    /// the original Rust code, including the braces, in the same way as
+    /// `range()`. It returns an empty vector if this is not a `StringPlain` or
+    /// `StringRaw` Lexeme, because byte strings cannot be formatted.
+    pub fn format_placeholders(&self) -> Vec<Range<usize>> {
+        let snippet = self.snippet;
+        // Find the start and end of the content, and whether it has escapes.
+        let (mut i, end, escapes) = match self.kind {
+            LexemeKind::StringPlain => (1, snippet.len().saturating_sub(1), true),
+            LexemeKind::StringRaw => {
+                // The `r` and hashes, which are balanced by a `"` and hashes.
+                let prefix = snippet.len() - snippet.trim_start_matches(['r', '#']).len();
+                (prefix + 1, snippet.len().saturating_sub(prefix), false)
+            }
+            _ => return vec![],
+        };
+        let bytes = snippet.as_bytes();
+        let mut placeholders = vec![];
+        while i < end {
+            match bytes[i] {
+                // Skip an escape, including the braces of a `\u{...}`.
+                b'\\' if escapes => {
+                    i += 2;
+                    if bytes[i-1] == b'u' {
+                        i = match snippet.get(i..end).and_then(|rest| rest.find('}')) {
+                            Some(j) => i + j + 1,
+                            None => end,
+                        };
+                    }
+                }
+                // `{{` and `}}` are escaped braces.
+                b'{' | b'}' if i + 1 < end && bytes[i+1] == bytes[i] => i += 2,
+                b'{' => match snippet[i..end].find('}') {
+                    Some(j) => {
+                        placeholders.push(self.chr + i..self.chr + i + j + 1);
+                        i += j + 1;
+                    }
+                    None => break,
+                },
+                _ => i += 1,
+            }
+        }
+        placeholders
+    }
+
+    /// Counts the spaces, tabs, newlines and other characters in Whitespace.
     /// 
-    Unidentifiable = 67108864,
+    /// ### Returns
+    /// `whitespace_stats()` returns `None` if this is not a
+    /// `WhitespaceTrimmable` Lexeme.
+    pub fn whitespace_stats(&self) -> Option<WhitespaceStats> {
+        if self.kind != LexemeKind::WhitespaceTrimmable { return None }
+        let mut stats = WhitespaceStats::default();
+        let mut chars = self.snippet.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                ' ' => stats.spaces += 1,
+                '\t' => stats.tabs += 1,
+                '\n' => stats.newlines += 1,
+                '\r' if chars.peek() == Some(&'\n') => {}
+                _ => stats.other += 1,
+            }
+        }
+        Some(stats)
+    }
 
+    /// Returns whether a doc comment is Inner, like `//!`, or Outer, like `///`.
     /// 
-    WhitespaceTrimmable = 268435456,
-}
+    /// ### Returns
+    /// `doc_placement()` returns `None` if this is not a `CommentDoc*` Lexeme.
+    pub fn doc_placement(&self) -> Option<DocPlacement> {
+        if self.kind != LexemeKind::CommentDocInline
+        && self.kind != LexemeKind::CommentDocMultiline { return None }
+        // The third char is "!" for Inner, or "/" or "*" for Outer.
+        match self.snippet.get(2..3)? {
+            "!" => Some(DocPlacement::Inner),
+            _ => Some(DocPlacement::Outer),
+        }
+    }
 
-///
-#[derive(Copy, Clone)]
-pub struct Lexeme {
-    /// Category of the Lexeme.
-    pub kind: LexemeKind,
-    /// The position that the Lexeme starts, relative to the start of `orig`.
-    /// Zero indexed.
-    pub chr: usize,
+    /// Classifies a Punctuation Lexeme as an operator or delimiter, so that a
+    /// parser can match on `PunctOp::FatArrow` rather than the snippet `=>`.
     /// 
-    pub snippet: &'static str,
+    /// ### Returns
+    /// `punctuation_op()` returns `None` if this is not a Punctuation Lexeme.
+    pub fn punctuation_op(&self) -> Option<PunctOp> {
+        if self.kind != LexemeKind::Punctuation { return None }
+        PunctOp::from_snippet(self.snippet)
+    }
+
+    /// Returns `true` if this is an `IdentifierKeyword` Lexeme, of any
+    /// `KeywordCategory`.
+    pub fn is_keyword_kind(&self) -> bool {
+        self.kind == LexemeKind::IdentifierKeyword
+    }
+
+    /// Returns whether a Keyword is Strict, like `fn`, Reserved, like
+    /// `typeof`, or Weak, like `union`.
+    /// 
+    /// ### Returns
+    /// `keyword_category()` returns `None` if this is not an
+    /// `IdentifierKeyword` Lexeme.
+    pub fn keyword_category(&self) -> Option<KeywordCategory> {
+        if ! self.is_keyword_kind() { return None }
+        keyword_category(self.snippet)
+    }
+
+    /// Returns `true` if the hex digits mix upper and lower case, like
+    /// `0xAbCd` or `'\xaB'`, for style linters.
+    /// 
+    /// The `0x` or `\x` prefix, underscores, and anything after the digits are
+    /// ignored, so `0xab_u8` does not mix case.
+    /// 
+    /// ### Returns
+    /// `hex_has_mixed_case()` returns `false` if this is not a `NumberHex` or
+    /// `CharacterHex` Lexeme.
+    pub fn hex_has_mixed_case(&self) -> bool {
+        // Find the digits, which start after the first `x`.
+        let digits = match self.kind {
+            LexemeKind::CharacterHex | LexemeKind::NumberHex =>
+                match self.snippet.find('x') {
+                    Some(x) => &self.snippet[x+1..],
+                    None => return false,
+                },
+            _ => return false,
+        };
+        let digits = digits.bytes()
+            .take_while(|b| b.is_ascii_hexdigit() || *b == b'_');
+        let (mut upper, mut lower) = (false, false);
+        for b in digits {
+            upper |= b.is_ascii_uppercase();
+            lower |= b.is_ascii_lowercase();
+        }
+        upper && lower
+    }
+
+    /// Returns `true` if this is a Punctuation Lexeme which is a delimiter,
+    /// like `(` or `}`. See `PunctOp::is_delimiter()`.
+    pub fn is_delimiter(&self) -> bool {
+        self.punctuation_op().is_some_and(|op| op.is_delimiter())
+    }
+
+    /// Returns `true` if this is a Punctuation Lexeme which is an operator,
+    /// like `+` or `&&`. See `PunctOp::is_operator()`.
+    pub fn is_operator(&self) -> bool {
+        self.punctuation_op().is_some_and(|op| op.is_operator())
+    }
+
+    // Returns `true` if this is the end-of-input sentinel.
+    fn is_eoi(&self) -> bool {
+        self.kind == LexemeKind::EndOfInput
+    }
 }
 
-impl fmt::Display for Lexeme {
+// Decodes the escapes in the content of a plain string, like `a\nb`.
+fn unescape(content: &str) -> String {
+    let mut value = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(pos) = rest.find('\\') {
+        value.push_str(&rest[..pos]);
+        let escape = &rest[pos..];
+        // Push the decoded char, if any. If the escape is invalid, just push
+        // the backslash, and carry on from the char after it.
+        let used = match decode_escape(escape) {
+            Some((decoded, used)) => { value.extend(decoded); used }
+            None => { value.push('\\'); 1 }
+        };
+        rest = &escape[used..];
+    }
+    value.push_str(rest);
+    value
+}
+
+// Decodes the escape at the start of `escape`, like `\n` or `\u{1F600}`.
+// Returns the char (or `None` for a line continuation) and the number of bytes
+// used, or `None` if the escape is invalid.
+fn decode_escape(escape: &str) -> Option<(Option<char>, usize)> {
+    let c = escape[1..].chars().next()?;
+    let decoded = match c {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        '\\' | '"' | '\'' => c,
+        // A 7-bit char code, like `\x41`, or for a byte string up to `\xFF`.
+        'x' => {
+            let hex = escape.get(2..4)?;
+            if ! hex.chars().all(|c| c.is_ascii_hexdigit()) { return None }
+            return Some((Some(u8::from_str_radix(hex, 16).ok()? as char), 4))
+        }
+        // A unicode char code, like `\u{1F600}`, which may contain underscores.
+        'u' => {
+            let close = escape.find('}')?;
+            if escape.get(2..3)? != "{" { return None }
+            let hex: String = escape[3..close].chars().filter(|c| *c != '_').collect();
+            if hex.is_empty() || ! hex.chars().all(|c| c.is_ascii_hexdigit()) { return None }
+            let value = u32::from_str_radix(&hex, 16).ok()?;
+            return Some((Some(char::from_u32(value)?), close + 1))
+        }
+        // A line continuation, which skips the newline and any indentation.
+        '\n' | '\r' => {
+            let after = escape[1..].trim_start();
+            return Some((None, escape.len() - after.len()))
+        }
+        _ => return None,
+    };
+    Some((Some(decoded), 2))
+}
+
+impl Ord for Lexeme<'_> {
+    // Lexemes are ordered by `chr`. Two Lexemes from the same `lexemize()` call
+    // never share a `chr`, but to stay consistent with `Eq`, ties are broken by
+    // `snippet` and then `kind`.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.chr.cmp(&other.chr)
+            .then_with(|| self.snippet.cmp(other.snippet))
+            .then_with(|| (self.kind as u64).cmp(&(other.kind as u64)))
+    }
+}
+
+impl PartialOrd for Lexeme<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Lexeme<'_> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         let kind = format!("{:?}", self.kind);
-        let snippet = self.snippet.replace("\n", "<NL>");
+        let snippet = if self.is_eoi() { "<EOI>".into() }
+            else { self.snippet.replace("\n", "<NL>") };
         write!(fmt, "{: <20} {: >4}  {}", kind, self.chr, snippet)
         //                     |||
         //                     ||+-- target width is four characters
@@ -99,10 +1008,35 @@ impl fmt::Display for Lexeme {
     }
 }
 
+/// A Lexeme which owns its snippet, so it does not borrow from the original
+/// code, for example to keep it after the code read by `lexemize_reader()` is
+/// dropped.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct OwnedLexeme {
+    /// Category of the Lexeme.
+    pub kind: LexemeKind,
+    /// The position that the Lexeme starts, relative to the start of `orig`.
+    /// Zero indexed.
+    pub chr: usize,
+    /// A copy of the slice of `orig` which the Lexeme covered.
+    pub snippet: String,
+}
+
+impl From<Lexeme<'_>> for OwnedLexeme {
+    fn from(lexeme: Lexeme<'_>) -> Self {
+        OwnedLexeme {
+            kind: lexeme.kind,
+            chr: lexeme.chr,
+            snippet: lexeme.snippet.into(),
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::test_helpers::lx;
     
     #[test]
     fn lexeme_kind_debug_as_expected() {
@@ -130,6 +1064,14 @@ mod tests {
                                               "IdentifierOther");
         assert_eq!(format!("{:?}", LexemeKind::IdentifierStdType),
                                               "IdentifierStdType");
+        assert_eq!(format!("{:?}", LexemeKind::IdentifierMacro),
+                                              "IdentifierMacro");
+        assert_eq!(format!("{:?}", LexemeKind::IdentifierHrtbFor),
+                                              "IdentifierHrtbFor");
+        assert_eq!(format!("{:?}", LexemeKind::IdentifierLifetime),
+                                              "IdentifierLifetime");
+        assert_eq!(format!("{:?}", LexemeKind::Label),
+                                              "Label");
         assert_eq!(format!("{:?}", LexemeKind::NumberBinary),
                                               "NumberBinary");
         assert_eq!(format!("{:?}", LexemeKind::NumberHex),
@@ -140,6 +1082,10 @@ mod tests {
                                               "NumberDecimal");
         assert_eq!(format!("{:?}", LexemeKind::Punctuation),
                                               "Punctuation");
+        assert_eq!(format!("{:?}", LexemeKind::MacroRepeatOp),
+                                              "MacroRepeatOp");
+        assert_eq!(format!("{:?}", LexemeKind::AttributeStart),
+                                              "AttributeStart");
         assert_eq!(format!("{:?}", LexemeKind::StringByte),
                                               "StringByte");
         assert_eq!(format!("{:?}", LexemeKind::StringByteRaw),
@@ -154,8 +1100,118 @@ mod tests {
                                               "Unexpected");
         assert_eq!(format!("{:?}", LexemeKind::Unidentifiable),
                                               "Unidentifiable");
+        assert_eq!(format!("{:?}", LexemeKind::ReservedPrefix),
+                                              "ReservedPrefix");
         assert_eq!(format!("{:?}", LexemeKind::WhitespaceTrimmable),
                                               "WhitespaceTrimmable");
+        assert_eq!(format!("{:?}", LexemeKind::Trivia),
+                                              "Trivia");
+        assert_eq!(format!("{:?}", LexemeKind::ByteOrderMark),
+                                              "ByteOrderMark");
+        assert_eq!(format!("{:?}", LexemeKind::EndOfInput),
+                                              "EndOfInput");
+    }
+
+    #[test]
+    fn lexeme_kind_predicates() {
+        assert!(LexemeKind::CharacterHex.is_character());
+        assert!(LexemeKind::CommentDocInline.is_comment());
+        assert!(LexemeKind::IdentifierLifetime.is_identifier());
+        assert!(LexemeKind::NumberOctal.is_number());
+        assert!(LexemeKind::AttributeStart.is_punctuation());
+        assert!(LexemeKind::StringRaw.is_string());
+        assert!(LexemeKind::Unexpected.is_other());
+        assert!(LexemeKind::Trivia.is_whitespace());
+        // Each kind is in exactly one group.
+        let kind = LexemeKind::CommentInline;
+        assert!(! kind.is_character() && ! kind.is_identifier()
+             && ! kind.is_number() && ! kind.is_punctuation()
+             && ! kind.is_string() && ! kind.is_other()
+             && ! kind.is_whitespace());
+    }
+
+    #[test]
+    fn lexeme_kind_group() {
+        use LexemeGroup as G;
+        use LexemeKind::*;
+        let expected = [
+            (CharacterByte, G::Character), (CharacterHex, G::Character),
+            (CharacterPlain, G::Character), (CharacterUnicode, G::Character),
+            (CommentDocInline, G::Comment), (CommentDocMultiline, G::Comment),
+            (CommentInline, G::Comment), (CommentMultiline, G::Comment),
+            (CommentShebang, G::Comment),
+            (IdentifierFreeword, G::Identifier), (IdentifierKeyword, G::Identifier),
+            (IdentifierOther, G::Identifier), (IdentifierStdType, G::Identifier),
+            (IdentifierMacro, G::Identifier), (IdentifierHrtbFor, G::Identifier),
+            (IdentifierLifetime, G::Identifier), (Label, G::Identifier),
+            (NumberBinary, G::Number), (NumberHex, G::Number),
+            (NumberOctal, G::Number), (NumberDecimal, G::Number),
+            (NumberDecimalFloat, G::Number),
+            (Punctuation, G::Punctuation), (MacroRepeatOp, G::Punctuation),
+            (AttributeStart, G::Punctuation),
+            (StringByte, G::String), (StringByteRaw, G::String),
+            (StringPlain, G::String), (StringRaw, G::String),
+            (Undetected, G::Other), (Unexpected, G::Other),
+            (Unidentifiable, G::Other), (ReservedPrefix, G::Other),
+            (WhitespaceTrimmable, G::Whitespace), (Trivia, G::Whitespace),
+            (ByteOrderMark, G::Whitespace), (EndOfInput, G::Whitespace),
+        ];
+        assert_eq!(expected.len(), 37); // every kind
+        for (kind, group) in expected.iter() {
+            assert_eq!(kind.group(), *group, "{:?}", kind);
+            // The group agrees with the `is_*()` functions.
+            assert_eq!(kind.is_number(), *group == G::Number);
+            assert_eq!(kind.is_whitespace(), *group == G::Whitespace);
+        }
+        assert!(G::Character < G::Whitespace);
+    }
+
+    #[test]
+    fn lexeme_kind_u64_round_trip() {
+        // Every kind round-trips through its discriminant.
+        let mut found = 0;
+        for bit in 0..64 {
+            if let Ok(kind) = LexemeKind::try_from(1u64 << bit) {
+                assert_eq!(LexemeKind::try_from(kind as u64), Ok(kind));
+                assert_eq!(u64::from(kind), kind as u64);
+                found += 1;
+            }
+        }
+        assert_eq!(found, 37);
+        assert_eq!(u64::from(LexemeKind::CharacterByte), 1);
+        assert_eq!(u64::from(LexemeKind::Trivia), 1 << 57);
+        // Values which are not the discriminant of any kind.
+        assert_eq!(LexemeKind::try_from(3), Err(3));
+        assert_eq!(LexemeKind::try_from(0), Err(0));
+        assert_eq!(LexemeKind::try_from(1 << 4), Err(1 << 4));
+        assert_eq!(LexemeKind::try_from(1 << 63), Err(1 << 63));
+        assert_eq!(LexemeKind::try_from(u64::MAX), Err(u64::MAX));
+    }
+
+    #[test]
+    fn lexeme_kind_ord() {
+        assert!(LexemeKind::CharacterByte < LexemeKind::CharacterHex);
+        assert!(LexemeKind::CharacterUnicode < LexemeKind::CommentDocInline);
+        assert!(LexemeKind::Unidentifiable < LexemeKind::WhitespaceTrimmable);
+    }
+
+    #[test]
+    fn lexeme_kind_in_sets() {
+        use std::collections::{BTreeSet,HashSet};
+        // Every kind, inserted in reverse order.
+        let kinds: Vec<LexemeKind> = (0..64).rev()
+            .filter_map(|bit| LexemeKind::try_from(1u64 << bit).ok()).collect();
+        let sorted: BTreeSet<LexemeKind> = kinds.iter().copied().collect();
+        let hashed: HashSet<LexemeKind> = kinds.iter().copied().collect();
+        assert_eq!(sorted.len(), 37);
+        assert_eq!(hashed.len(), 37);
+        // A BTreeSet is sorted by bit, so the groups stay together.
+        let sorted: Vec<LexemeKind> = sorted.into_iter().collect();
+        assert_eq!(sorted.first(), Some(&LexemeKind::CharacterByte));
+        assert_eq!(sorted.last(), Some(&LexemeKind::EndOfInput));
+        assert!(sorted.windows(2).all(|pair| (pair[0] as u64) < pair[1] as u64));
+        assert!(sorted.windows(2).all(|pair| pair[0].group() <= pair[1].group()));
+        assert!(hashed.contains(&LexemeKind::Label));
     }
 
     #[test]
@@ -163,8 +1219,450 @@ mod tests {
         let lexeme = Lexeme {
             kind: LexemeKind::CharacterUnicode,
             chr: 123,
-            snippet: "yup".into(),
+            snippet: "yup",
         };
         assert_eq!(lexeme.to_string(), "CharacterUnicode      123  yup");
+        // The `<EOI>` sentinel’s snippet is empty, but it is shown as <EOI>.
+        let eoi = lx(LexemeKind::EndOfInput, 9, "");
+        assert_eq!(eoi.to_string(), "EndOfInput              9  <EOI>");
+    }
+
+    #[test]
+    fn owned_lexeme_from_lexeme() {
+        let owned = OwnedLexeme::from(lx(LexemeKind::StringPlain, 7, "\"yup\""));
+        assert_eq!(owned, OwnedLexeme {
+            kind: LexemeKind::StringPlain,
+            chr: 7,
+            snippet: String::from("\"yup\""),
+        });
+    }
+
+    #[test]
+    fn lexeme_starts_at_and_contains() {
+        let lexeme = lx(LexemeKind::StringPlain, 4, "\"€\"");
+        assert!(! lexeme.starts_at(3));
+        assert!(  lexeme.starts_at(4));
+        assert!(! lexeme.starts_at(5));
+        assert!(! lexeme.contains(3)); // just before
+        assert!(  lexeme.contains(4)); // the opening double quote
+        assert!(  lexeme.contains(6)); // part way into the three € bytes
+        assert!(  lexeme.contains(8)); // the closing double quote
+        assert!(! lexeme.contains(9)); // just after
+        // The `<EOI>` sentinel has an empty span.
+        let eoi = lx(LexemeKind::EndOfInput, 9, "");
+        assert!(  eoi.starts_at(9));
+        assert!(! eoi.contains(9));
+        assert!(! eoi.contains(10));
+    }
+
+    #[test]
+    fn lexeme_is_trivia() {
+        assert!(lx(LexemeKind::WhitespaceTrimmable, 0, " ").is_trivia());
+        assert!(lx(LexemeKind::Trivia, 0, " // c\n").is_trivia());
+        assert!(lx(LexemeKind::CommentInline, 0, "// c").is_trivia());
+        assert!(lx(LexemeKind::CommentDocMultiline, 0, "/** d */").is_trivia());
+        assert!(! lx(LexemeKind::IdentifierFreeword, 0, "x").is_trivia());
+        assert!(! lx(LexemeKind::StringPlain, 0, "\"// c\"").is_trivia());
+        assert!(! lx(LexemeKind::Unidentifiable, 0, "€").is_trivia());
+    }
+
+    #[test]
+    fn lexeme_whitespace_stats() {
+        let stats = |snippet| lx(LexemeKind::WhitespaceTrimmable, 0, snippet)
+            .whitespace_stats();
+        let ws = |spaces, tabs, newlines, other|
+            Some(WhitespaceStats { spaces, tabs, newlines, other });
+        assert_eq!(stats("\t \n\t"), ws(1, 2, 1, 0));
+        assert_eq!(stats("    "), ws(4, 0, 0, 0));
+        assert_eq!(stats("\r\n\r\n"), ws(0, 0, 2, 0)); // `\r\n` is one newline
+        assert_eq!(stats("\r \u{2028}\u{0B}"), ws(1, 0, 0, 3)); // lone `\r` is other
+        assert_eq!(stats(""), ws(0, 0, 0, 0));
+        // Not Whitespace.
+        assert_eq!(lx(LexemeKind::Trivia, 0, " // c\n").whitespace_stats(), None);
+        assert_eq!(lx(LexemeKind::StringPlain, 0, "\" \"").whitespace_stats(), None);
+        assert_eq!(lx(LexemeKind::EndOfInput, 0, "").whitespace_stats(), None);
+    }
+
+    #[test]
+    fn lexeme_doc_text() {
+        use LexemeKind::{CommentDocInline as DI,CommentDocMultiline as DM};
+        // Inline.
+        assert_eq!(lx(DI, 0, "/// hello").doc_text().as_deref(), Some(" hello"));
+        assert_eq!(lx(DI, 0, "//! inner").doc_text().as_deref(), Some(" inner"));
+        assert_eq!(lx(DI, 0, "///\r").doc_text().as_deref(),     Some(""));
+        assert_eq!(lx(DI, 0, "/// x\n").doc_text().as_deref(),   Some(" x"));
+        // Multiline, on one line.
+        assert_eq!(lx(DM, 0, "/** a */").doc_text().as_deref(),  Some(" a "));
+        assert_eq!(lx(DM, 0, "/*!*/").doc_text().as_deref(),     Some(""));
+        // Multiline, over several lines.
+        assert_eq!(lx(DM, 0, "/** a\n * b */").doc_text().as_deref(),
+            Some(" a\nb "));
+        assert_eq!(lx(DM, 0, "/** a\r\n\t* b\r\n *\r\n * c\r\n */").doc_text().as_deref(),
+            Some(" a\r\nb\r\n\r\nc\r\n "));
+        assert_eq!(lx(DM, 0, "/**\n  no star\n  **bold**\n*/").doc_text().as_deref(),
+            Some("\n  no star\n  **bold**\n"));
+        // Not a doc comment.
+        assert_eq!(lx(LexemeKind::CommentInline, 0, "// no").doc_text(), None);
+        assert_eq!(lx(LexemeKind::CommentMultiline, 0, "/* no */").doc_text(), None);
+        assert_eq!(lx(LexemeKind::StringPlain, 0, "\"/// no\"").doc_text(), None);
+        // Too short to be a doc comment, so will not panic.
+        assert_eq!(lx(DM, 0, "/**").doc_text(), None);
+        assert_eq!(lx(DI, 0, "//").doc_text(), None);
+    }
+
+    #[test]
+    fn lexeme_block_doc_lines() {
+        use LexemeKind::CommentDocMultiline as DM;
+        let lines = |snippet| lx(DM, 0, snippet).block_doc_lines();
+        // Aligned asterisks.
+        assert_eq!(lines("/** line1\n * line2\n */"), vec!["line1", "line2"]);
+        assert_eq!(lines("/**\n * line1\n *\n * line2\n */"), vec!["line1", "", "line2"]);
+        assert_eq!(lines("/*!\r\n * inner\r\n */"), vec!["inner"]);
+        // Unaligned asterisks.
+        assert_eq!(lines("/** a\n* b\n\t\t* c\n      */"), vec!["a", "b", "c"]);
+        assert_eq!(lines("/** a\n   * b */"), vec!["a", "b"]);
+        // Lines with no leading `*` keep their indentation.
+        assert_eq!(lines("/**\n  no star\n  **bold**\n*/"), vec!["  no star", "  **bold**"]);
+        assert_eq!(lines("/** a\n\n    code\n*/"), vec!["a", "", "    code"]);
+        // On one line.
+        assert_eq!(lines("/** a */"), vec!["a"]);
+        assert_eq!(lines("/**a*/"), vec!["a"]);
+        assert_eq!(lines("/*!*/"), Vec::<&str>::new());
+        // Not a multiline doc comment, or too short, so will not panic.
+        assert_eq!(lx(LexemeKind::CommentDocInline, 0, "/// a").block_doc_lines(),
+            Vec::<&str>::new());
+        assert_eq!(lx(LexemeKind::CommentMultiline, 0, "/* a */").block_doc_lines(),
+            Vec::<&str>::new());
+        assert_eq!(lines("/**"), Vec::<&str>::new());
+        assert_eq!(lines(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn lexeme_doc_placement() {
+        use LexemeKind::{CommentDocInline as DI,CommentDocMultiline as DM};
+        use super::DocPlacement::{Inner,Outer};
+        assert_eq!(lx(DI, 0, "//! inner").doc_placement(),  Some(Inner));
+        assert_eq!(lx(DI, 0, "/// outer").doc_placement(),  Some(Outer));
+        assert_eq!(lx(DM, 0, "/*! inner */").doc_placement(), Some(Inner));
+        assert_eq!(lx(DM, 0, "/** outer */").doc_placement(), Some(Outer));
+        assert_eq!(lx(DI, 0, "//!").doc_placement(),        Some(Inner));
+        // Not a doc comment.
+        assert_eq!(lx(LexemeKind::CommentInline, 0, "// no").doc_placement(), None);
+        assert_eq!(lx(LexemeKind::CommentInline, 0, "//// no").doc_placement(), None);
+        assert_eq!(lx(LexemeKind::CommentMultiline, 0, "/* no */").doc_placement(), None);
+        assert_eq!(lx(LexemeKind::CommentMultiline, 0, "/*! no").doc_placement(), None);
+        // Too short to be a doc comment, so will not panic.
+        assert_eq!(lx(DI, 0, "//").doc_placement(), None);
+    }
+
+    #[test]
+    fn lexeme_keyword_category() {
+        use super::KeywordCategory::{Reserved,Strict,Weak};
+        let category = |snippet| lx(LexemeKind::IdentifierKeyword, 0, snippet)
+            .keyword_category();
+        assert_eq!(category("fn"),     Some(Strict));
+        assert_eq!(category("dyn"),    Some(Strict));
+        assert_eq!(category("Self"),   Some(Strict));
+        assert_eq!(category("typeof"), Some(Reserved));
+        assert_eq!(category("become"), Some(Reserved));
+        assert_eq!(category("priv"),   Some(Reserved));
+        assert_eq!(category("union"),  Some(Weak));
+        assert_eq!(category("macro_rules"), Some(Weak));
+        assert!(lx(LexemeKind::IdentifierKeyword, 0, "union").is_keyword_kind());
+        // Not a Keyword.
+        assert_eq!(category("foo"), None);
+        assert!(! lx(LexemeKind::IdentifierFreeword, 0, "fn").is_keyword_kind());
+        assert_eq!(lx(LexemeKind::IdentifierFreeword, 0, "fn").keyword_category(),
+            None);
+        assert_eq!(lx(LexemeKind::IdentifierStdType, 0, "u8").keyword_category(),
+            None);
+    }
+
+    #[test]
+    fn lexeme_hex_has_mixed_case() {
+        use LexemeKind::{CharacterHex as C,NumberHex as H};
+        assert!(lx(H, 0, "0xAbCd").hex_has_mixed_case());
+        assert!(lx(H, 0, "0xA_b").hex_has_mixed_case());
+        assert!(lx(C, 0, "'\\xaB'").hex_has_mixed_case());
+        assert!(! lx(H, 0, "0xABCD").hex_has_mixed_case());
+        assert!(! lx(H, 0, "0xabcd").hex_has_mixed_case());
+        assert!(! lx(H, 0, "0x1234").hex_has_mixed_case());
+        assert!(! lx(C, 0, "'\\x4A'").hex_has_mixed_case());
+        // The `0x` prefix and a suffix are not digits.
+        assert!(! lx(H, 0, "0xABi32").hex_has_mixed_case());
+        assert!(! lx(H, 0, "0xab_U8").hex_has_mixed_case());
+        // Not hex, or too short, so will not panic.
+        assert!(! lx(LexemeKind::NumberDecimal, 0, "1e5E").hex_has_mixed_case());
+        assert!(! lx(LexemeKind::IdentifierFreeword, 0, "aB").hex_has_mixed_case());
+        assert!(! lx(H, 0, "0x").hex_has_mixed_case());
+        assert!(! lx(H, 0, "").hex_has_mixed_case());
+    }
+
+    #[test]
+    fn lexeme_display_width() {
+        let width = |kind, snippet| lx(kind, 0, snippet).display_width();
+        use LexemeKind::{CommentInline as C,StringPlain as P};
+        use LexemeKind::WhitespaceTrimmable as W;
+        assert_eq!(width(P, "\"abc\""), 5);
+        assert_eq!(width(W, "\t"), 4);
+        assert_eq!(width(W, " \t "), 6);
+        // Wide CJK chars take two columns, but `€` is one, despite being 3 bytes.
+        assert_eq!(width(P, "\"世界\""), 6);
+        assert_eq!(width(P, "\"€\""), 3);
+        // A combining acute accent takes no columns.
+        assert_eq!(width(P, "\"e\u{301}\""), 3);
+        // Newlines are shown as `<NL>`, and other control chars take nothing.
+        assert_eq!(width(W, "\n"), 4);
+        assert_eq!(width(C, "// x\r\n"), 8);
+        assert_eq!(width(P, "\"\u{0}\u{7F}\""), 2);
+        // The `<EOI>` sentinel, and an empty snippet.
+        assert_eq!(width(LexemeKind::EndOfInput, ""), 5);
+        assert_eq!(width(W, ""), 0);
+    }
+
+    #[test]
+    fn lexeme_len_end_and_range() {
+        let lexeme = lx(LexemeKind::StringPlain, 4, "\"€\"");
+        assert_eq!(lexeme.len(), 5);
+        assert_eq!(lexeme.end(), 9);
+        assert_eq!(lexeme.range(), 4..9);
+        assert!(! lexeme.is_empty());
+        // The <EOI> sentinel is empty, and ends where it starts.
+        let eoi = lx(LexemeKind::EndOfInput, 9, "");
+        assert_eq!(eoi.len(), 0);
+        assert_eq!(eoi.end(), 9);
+        assert_eq!(eoi.range(), 9..9);
+        assert!(eoi.is_empty());
+    }
+
+    #[test]
+    fn lexeme_punctuation_op() {
+        use super::PunctOp as O;
+        let op = |snippet| lx(LexemeKind::Punctuation, 0, snippet).punctuation_op();
+        assert_eq!(op("'"), Some(O::SingleQuote));
+        assert_eq!(op("_"), Some(O::Underscore));
+        assert_eq!(op("-"), Some(O::Minus));
+        assert_eq!(op(","), Some(O::Comma));
+        assert_eq!(op(";"), Some(O::Semi));
+        assert_eq!(op(":"), Some(O::Colon));
+        assert_eq!(op("!"), Some(O::Not));
+        assert_eq!(op("?"), Some(O::Question));
+        assert_eq!(op("."), Some(O::Dot));
+        assert_eq!(op("("), Some(O::OpenParen));
+        assert_eq!(op(")"), Some(O::CloseParen));
+        assert_eq!(op("["), Some(O::OpenBracket));
+        assert_eq!(op("]"), Some(O::CloseBracket));
+        assert_eq!(op("{"), Some(O::OpenBrace));
+        assert_eq!(op("}"), Some(O::CloseBrace));
+        assert_eq!(op("@"), Some(O::At));
+        assert_eq!(op("*"), Some(O::Star));
+        assert_eq!(op("/"), Some(O::Slash));
+        assert_eq!(op("&"), Some(O::And));
+        assert_eq!(op("#"), Some(O::Pound));
+        assert_eq!(op("%"), Some(O::Percent));
+        assert_eq!(op("^"), Some(O::Caret));
+        assert_eq!(op("+"), Some(O::Plus));
+        assert_eq!(op("<"), Some(O::Lt));
+        assert_eq!(op("="), Some(O::Eq));
+        assert_eq!(op(">"), Some(O::Gt));
+        assert_eq!(op("|"), Some(O::Or));
+        assert_eq!(op("$"), Some(O::Dollar));
+        assert_eq!(op("-="), Some(O::MinusEq));
+        assert_eq!(op("->"), Some(O::ThinArrow));
+        assert_eq!(op("::"), Some(O::PathSep));
+        assert_eq!(op("!="), Some(O::Ne));
+        assert_eq!(op(".."), Some(O::DotDot));
+        assert_eq!(op("*="), Some(O::StarEq));
+        assert_eq!(op("/="), Some(O::SlashEq));
+        assert_eq!(op("&&"), Some(O::AndAnd));
+        assert_eq!(op("&="), Some(O::AndEq));
+        assert_eq!(op("%="), Some(O::PercentEq));
+        assert_eq!(op("^="), Some(O::CaretEq));
+        assert_eq!(op("+="), Some(O::PlusEq));
+        assert_eq!(op("<<"), Some(O::Shl));
+        assert_eq!(op("<="), Some(O::Le));
+        assert_eq!(op("=="), Some(O::EqEq));
+        assert_eq!(op("=>"), Some(O::FatArrow));
+        assert_eq!(op(">="), Some(O::Ge));
+        assert_eq!(op(">>"), Some(O::Shr));
+        assert_eq!(op("|="), Some(O::OrEq));
+        assert_eq!(op("||"), Some(O::OrOr));
+        assert_eq!(op("..."), Some(O::DotDotDot));
+        assert_eq!(op("..="), Some(O::DotDotEq));
+        assert_eq!(op("<<="), Some(O::ShlEq));
+        assert_eq!(op(">>="), Some(O::ShrEq));
+        // Not a single operator or delimiter.
+        assert_eq!(op("==="), None);
+        assert_eq!(op(""), None);
+        assert_eq!(op("~"), None);
+        // Not a Punctuation Lexeme.
+        assert_eq!(lx(LexemeKind::StringPlain, 0, "\"=>\"").punctuation_op(), None);
+        assert_eq!(lx(LexemeKind::Unidentifiable, 0, "=>").punctuation_op(), None);
+    }
+
+    #[test]
+    fn lexeme_is_delimiter_and_is_operator() {
+        let class = |snippet| {
+            let lexeme = lx(LexemeKind::Punctuation, 0, snippet);
+            match (lexeme.is_delimiter(), lexeme.is_operator()) {
+                (true, false) => 'd',
+                (false, true) => 'o',
+                (false, false) => '-',
+                (true, true) => unreachable!(),
+            }
+        };
+        // Every single-char Punctuation, `d` for delimiter, `o` for operator.
+        let singles = ["'", "_", "-", ",", ";", ":", "!", "?", ".", "(", ")",
+            "[", "]", "{", "}", "@", "*", "/", "&", "#", "%", "^", "+", "<",
+            "=", ">", "|", "$"];
+        let classes: String = singles.iter().map(|snippet| class(snippet)).collect();
+        assert_eq!(classes, "--o---o--dddddd-ooo-ooooooo-");
+        // Some multi-char Punctuation.
+        assert_eq!(class("<<="), 'o');
+        assert_eq!(class("&&"), 'o');
+        assert_eq!(class("!="), 'o');
+        assert_eq!(class("::"), '-');
+        assert_eq!(class("=>"), '-');
+        assert_eq!(class(".."), '-');
+        // Not a Punctuation Lexeme.
+        assert!(! lx(LexemeKind::StringPlain, 0, "(").is_delimiter());
+        assert!(! lx(LexemeKind::Unidentifiable, 0, "+").is_operator());
+    }
+
+    #[test]
+    fn lexeme_snippet_without_delimiters() {
+        let inner = |kind, snippet| lx(kind, 0, snippet).snippet_without_delimiters();
+        use LexemeKind::*;
+        // Strings, with escapes left as they are.
+        assert_eq!(inner(StringPlain, "\"a\\nb\""),      Some("a\\nb"));
+        assert_eq!(inner(StringPlain, "\"\""),            Some(""));
+        assert_eq!(inner(StringByte, "b\"x\\\"\""),      Some("x\\\""));
+        assert_eq!(inner(StringRaw, "r#\"x\"#"),         Some("x"));
+        assert_eq!(inner(StringRaw, "r\"a\\nb\""),       Some("a\\nb"));
+        assert_eq!(inner(StringRaw, "r##\"a\"#b\"##"),   Some("a\"#b"));
+        assert_eq!(inner(StringByteRaw, "br#\"€\"#"),    Some("€"));
+        // Characters.
+        assert_eq!(inner(CharacterPlain, "'A'"),        Some("A"));
+        assert_eq!(inner(CharacterPlain, "'\\''"),      Some("\\'"));
+        assert_eq!(inner(CharacterByte, "b'\\n'"),      Some("\\n"));
+        assert_eq!(inner(CharacterHex, "'\\x41'"),      Some("\\x41"));
+        assert_eq!(inner(CharacterUnicode, "'\\u{1F600}'"), Some("\\u{1F600}"));
+        // Not quoted.
+        assert_eq!(inner(IdentifierFreeword, "abc"), None);
+        assert_eq!(inner(Unexpected, "\"abc"), None);
+        assert_eq!(inner(EndOfInput, ""), None);
+        // Too short to have both delimiters, so will not panic.
+        assert_eq!(inner(StringPlain, "\""), None);
+        assert_eq!(inner(StringRaw, "r"),    None);
+        assert_eq!(inner(StringRaw, "r#\""),  None);
+        assert_eq!(inner(CharacterByte, "b"), None);
+    }
+
+    #[test]
+    fn lexeme_string_value() {
+        let value = |kind, snippet| lx(kind, 0, snippet).string_value();
+        use LexemeKind::{StringPlain as P,StringRaw as R};
+        // Plain, with each kind of escape.
+        assert_eq!(value(P, "\"abc\""),           Some("abc".into()));
+        assert_eq!(value(P, "\"\""),              Some("".into()));
+        assert_eq!(value(P, "\"a\\nb\""),         Some("a\nb".into()));
+        assert_eq!(value(P, "\"\\t\\r\\0\""),     Some("\t\r\0".into()));
+        assert_eq!(value(P, "\"\\\\ \\\" \\'\""), Some("\\ \" '".into()));
+        assert_eq!(value(P, "\"\\x41\\x7e\""),    Some("A~".into()));
+        assert_eq!(value(P, "\"\\u{1F600}\""),    Some("😀".into()));
+        assert_eq!(value(P, "\"\\u{1_F6_00}€\""), Some("😀€".into()));
+        assert_eq!(value(P, "\"a\\\n    b\""),     Some("ab".into()));
+        assert_eq!(value(P, "\"a\\\r\n\tb\""),   Some("ab".into()));
+        // Invalid escapes are kept as they are.
+        assert_eq!(value(P, "\"\\q\""),           Some("\\q".into()));
+        assert_eq!(value(P, "\"\\xZZ\""),         Some("\\xZZ".into()));
+        assert_eq!(value(P, "\"\\u{D800}\""),     Some("\\u{D800}".into()));
+        assert_eq!(value(P, "\"\\u{}\""),         Some("\\u{}".into()));
+        // Raw, where escapes are not decoded.
+        assert_eq!(value(R, "r\"a\\nb\""),        Some("a\\nb".into()));
+        assert_eq!(value(R, "r##\"a\"#b\"##"),     Some("a\"#b".into()));
+        assert_eq!(value(R, "r\"\""),             Some("".into()));
+        // Not a string.
+        assert_eq!(value(LexemeKind::CharacterPlain, "'a'"), None);
+        assert_eq!(value(LexemeKind::IdentifierFreeword, "abc"), None);
+        // Too short to be a string, so will not panic.
+        assert_eq!(value(P, "\""), None);
+        assert_eq!(value(R, "r"),  None);
+        assert_eq!(value(P, "\"\\\""), Some("\\".into())); // lone backslash
+    }
+
+    #[test]
+    fn lexeme_format_placeholders() {
+        use LexemeKind::{StringPlain as P,StringRaw as R};
+        // Returns the text of each placeholder.
+        let found = |kind, chr, snippet: &'static str| lx(kind, chr, snippet)
+            .format_placeholders().into_iter()
+            .map(|range| &snippet[range.start - chr..range.end - chr])
+            .collect::<Vec<_>>();
+        assert_eq!(found(P, 0, "\"{} {name} {{literal}}\""), vec!["{}", "{name}"]);
+        assert_eq!(lx(P, 10, "\"{} {name} {{literal}}\"").format_placeholders(),
+            vec![11..13, 14..20]);
+        assert_eq!(found(P, 0, "\"{0:>8.3} {x:?} {:#x}\""),
+            vec!["{0:>8.3}", "{x:?}", "{:#x}"]);
+        assert_eq!(found(P, 0, "\"{{{}}}\""), vec!["{}"]); // escaped, then {}
+        assert_eq!(found(P, 0, "\"}} {{\""), Vec::<&str>::new());
+        // Escapes in a plain string are skipped.
+        assert_eq!(found(P, 0, "\"\\u{7B}x\\u{7D} {a}\""), vec!["{a}"]);
+        assert_eq!(found(P, 0, "\"\\\\{a}\""), vec!["{a}"]); // an escaped backslash
+        // A raw string has no escapes.
+        assert_eq!(found(R, 0, "r\"\\{a}\""), vec!["{a}"]);
+        assert_eq!(found(R, 0, "r##\"{a}\"#{{b}}\"##"), vec!["{a}"]);
+        // Unclosed braces, and not a formattable string.
+        assert_eq!(found(P, 0, "\"{a} {b\""), vec!["{a}"]);
+        assert_eq!(found(LexemeKind::StringByte, 0, "b\"{a}\""), Vec::<&str>::new());
+        assert_eq!(found(LexemeKind::IdentifierFreeword, 0, "a"), Vec::<&str>::new());
+        // Too short to be a string, so will not panic.
+        assert_eq!(found(P, 0, "\""), Vec::<&str>::new());
+        assert_eq!(found(P, 0, "\"\\\""), Vec::<&str>::new());
+        assert_eq!(found(P, 0, "\"\\u{\""), Vec::<&str>::new());
+        assert_eq!(found(R, 0, "r"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn lexeme_split_at() {
+        // Split `>>` into `>` and `>`.
+        let shr = lx(LexemeKind::Punctuation, 10, ">>");
+        assert_eq!(shr.split_at(1), Some((
+            lx(LexemeKind::Punctuation, 10, ">"),
+            lx(LexemeKind::Punctuation, 11, ">"),
+        )));
+        // Split a string with non-ascii, on a char boundary.
+        let string = lx(LexemeKind::StringPlain, 0, "\"€\"");
+        assert_eq!(string.split_at(4), Some((
+            lx(LexemeKind::StringPlain, 0, "\"€"),
+            lx(LexemeKind::StringPlain, 4, "\""),
+        )));
+        // Reject a split which is out of range, or not on a char boundary.
+        assert_eq!(shr.split_at(0),   None); // first half would be empty
+        assert_eq!(shr.split_at(2),   None); // second half would be empty
+        assert_eq!(shr.split_at(3),   None); // out of range
+        assert_eq!(shr.split_at(100), None); // way out of range
+        assert_eq!(string.split_at(2), None); // part way into the € bytes
+        // Reject a split of the `<EOI>` sentinel.
+        let eoi = lx(LexemeKind::EndOfInput, 9, "");
+        assert_eq!(eoi.split_at(2), None);
+    }
+
+    #[test]
+    fn lexeme_ord_and_binary_search() {
+        let a = lx(LexemeKind::IdentifierFreeword, 0, "a");
+        let b = lx(LexemeKind::WhitespaceTrimmable, 1, "  ");
+        let c = lx(LexemeKind::IdentifierFreeword, 3, "c");
+        assert!(a < b && b < c);
+        let mut lexemes = vec![c, a, b];
+        lexemes.sort();
+        assert_eq!(lexemes, vec![a, b, c]);
+        // Find the Lexeme which contains byte offset 2.
+        let found = lexemes.binary_search_by(|lexeme|
+            if lexeme.contains(2) { Ordering::Equal } else { lexeme.chr.cmp(&2) });
+        assert_eq!(found, Ok(1));
+        // Find the Lexeme which starts at byte offset 3.
+        assert_eq!(lexemes.binary_search_by_key(&3, |lexeme| lexeme.chr), Ok(2));
+        assert_eq!(lexemes.binary_search_by_key(&2, |lexeme| lexeme.chr), Err(2));
     }
 }
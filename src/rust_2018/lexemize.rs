@@ -1,55 +1,923 @@
 //! Transforms Rust 2018 code to a vector of Lexemes.
 
-use std::fmt::{Display,Formatter,Error};
+use alloc::{format,vec};
+use alloc::collections::BTreeMap;
+use alloc::string::{String,ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display,Error,Formatter};
+use core::ops::Range;
 
-use super::lexeme::{Lexeme,LexemeKind};
+use super::diagnostic::{diagnose,find_malformed_raw_string,Diagnostic};
+use super::edition::Edition;
+use super::lexeme::{Lexeme,LexemeKind,OwnedLexeme,UnexpectedReason};
+use super::check::inexact_float::flag_inexact_floats;
+use super::check::keyword_lifetime::flag_keyword_lifetimes;
+use super::check::oversized_number::flag_oversized_numbers;
+use super::detect::byte_order_mark::detect_byte_order_mark;
 use super::detect::character::detect_character;
 use super::detect::comment::detect_comment;
-use super::detect::identifier::detect_identifier;
-use super::detect::number::detect_number;
+use super::detect::identifier::{detect_identifier,detect_identifier_for,is_reserved_prefix};
+use super::detect::number::{detect_number,detect_number_leading_dot,split_number_suffix};
 use super::detect::punctuation::detect_punctuation;
+use super::detect::shebang::detect_shebang;
 use super::detect::string::detect_string;
+use super::detect::unexpected::detect_unexpected;
 use super::detect::whitespace::detect_whitespace;
+use super::refine::attribute::tag_attribute;
+use super::refine::hrtb::tag_hrtb;
+use super::refine::label::tag_label;
+use super::refine::lifetime::tag_lifetime;
+use super::refine::macro_invocation::tag_macro_invocation;
+use super::refine::macro_repetition::tag_macro_repetition;
+use super::refine::macro_rules::tag_macro_rules;
+use super::refine::trivia::compact_trivia;
+use super::refine::tuple_index::tag_tuple_index;
+use super::refine::union::tag_union;
 
-///
-pub struct LexemizeResult {
-    ///
-    pub lexemes: Vec<Lexeme>,
+// How many Lexemes `relex()` restarts detection before the edit, and how many
+// new Lexemes after it must line up with old ones before detection stops.
+const RELEX_MARGIN: usize = 8;
+const RELEX_CONTEXT: usize = 4;
+// Detectors look at most a few bytes past a Lexeme, eg `'\u{10FFFF}'`, so
+// `relex()` also restarts at least this many bytes before the edit.
+const RELEX_MARGIN_BYTES: usize = 16;
+// `lexemize_parallel()` does not split code into chunks smaller than this.
+#[cfg(feature = "rayon")]
+const PARALLEL_MIN_CHUNK: usize = 64 * 1024;
+
+/// The result of calling `lexemize()`.
+pub struct LexemizeResult<'a> {
+    /// The Lexemes found in `orig`, ending with the `<EOI>` sentinel.
+    pub lexemes: Vec<Lexeme<'a>>,
+    /// The `chr` of each Lexeme flagged in checked mode, and why. Always empty
+    /// unless `LexemizeOptions::checked` is set, or an opt-in pass like
+    /// `flag_oversized_numbers()` has been run.
+    pub reasons: Vec<(usize, UnexpectedReason)>,
+    /// A description of each malformed token, like an unterminated string, so
+    /// that an IDE does not have to work out why a Lexeme is `Unexpected`.
+    pub diagnostics: Vec<Diagnostic>,
+    /// `true` if the last Lexeme before the `<EOI>` sentinel ends with `\n`,
+    /// so `orig` ends with a newline, which formatters often insist on.
+    /// `false` for empty input.
+    pub ends_with_newline: bool,
 }
 
-impl Display for LexemizeResult {
-    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+impl<'a> LexemizeResult<'a> {
+    /// Reconstructs the original Rust code, by joining every Lexeme’s snippet.
+    /// 
+    /// The `<EOI>` sentinel’s snippet is empty, so it adds nothing.
+    pub fn to_source(&self) -> String {
+        self.lexemes.iter().map(|lexeme| lexeme.snippet).collect()
+    }
+
+    /// Joins the snippets of the Lexemes which match a predicate.
+    /// 
+    /// For example, `result.joined(|l| ! l.kind.is_comment())` returns the
+    /// code with its comments stripped, and `|l| l.kind.is_comment()` returns
+    /// just the comments. The `<EOI>` sentinel’s snippet is empty, so it adds
+    /// nothing.
+    /// 
+    /// ### Arguments
+    /// * `pred` Returns `true` for each Lexeme whose snippet should be kept
+    /// 
+    /// ### Returns
+    /// `joined()` returns the kept snippets, in order, with nothing between them.
+    pub fn joined(&self, pred: impl Fn(&Lexeme) -> bool) -> String {
+        self.lexemes.iter()
+            .filter(|lexeme| pred(lexeme))
+            .map(|lexeme| lexeme.snippet)
+            .collect()
+    }
+
+    /// Rebuilds the source, replacing the snippets of some Lexemes, for tools
+    /// which rewrite code in a single pass.
+    /// 
+    /// For example, `|l| (l.kind == LexemeKind::NumberHex)
+    /// .then(|| l.snippet.to_lowercase())` lowercases every hex number. The
+    /// `<EOI>` sentinel is skipped.
+    /// 
+    /// ### Arguments
+    /// * `f` Returns the new text for a Lexeme, or `None` to keep its snippet
+    /// 
+    /// ### Returns
+    /// `rewrite()` returns the rewritten source, in order.
+    pub fn rewrite(&self, f: impl Fn(&Lexeme) -> Option<String>) -> String {
+        let mut out = String::new();
+        for lexeme in self.iter_no_eoi() {
+            match f(lexeme) {
+                Some(text) => out.push_str(&text),
+                None => out.push_str(lexeme.snippet),
+            }
+        }
+        out
+    }
+
+    /// Merges each run of Whitespace and Comments into a single `Trivia`
+    /// Lexeme, after lexemizing, for parsers which only care about trivia as a
+    /// unit.
+    /// 
+    /// This gives the same Lexemes as setting `LexemizeOptions::compact_trivia`
+    /// before lexemizing. Significant Lexemes are unchanged, and so are the
+    /// `reasons` and `diagnostics`.
+    /// 
+    /// ### Arguments
+    /// * `orig` The original Rust code, which each merged `snippet` is sliced
+    ///   from
+    /// 
+    /// ### Returns
+    /// `collapse_trivia()` returns a new [`LexemizeResult`] object.
+    pub fn collapse_trivia(&self, orig: &'a str) -> LexemizeResult<'a> {
+        LexemizeResult {
+            lexemes: compact_trivia(orig, self.lexemes.clone()),
+            reasons: self.reasons.clone(),
+            diagnostics: self.diagnostics.clone(),
+            ends_with_newline: self.ends_with_newline,
+        }
+    }
+
+    /// Copies every Lexeme, including the `<EOI>` sentinel, into an
+    /// [`OwnedLexeme`], which does not borrow from the original code.
+    pub fn to_owned_lexemes(&self) -> Vec<OwnedLexeme> {
+        self.lexemes.iter().map(|lexeme| OwnedLexeme::from(*lexeme)).collect()
+    }
+
+    /// Finds the Lexeme under a byte offset, for example to show a hover hint.
+    /// 
+    /// An offset part way into a multibyte character, like the second byte of
+    /// `€`, still finds the Lexeme which contains that character.
+    /// 
+    /// ### Arguments
+    /// * `chr` The byte offset in the original Rust code to look at
+    /// 
+    /// ### Returns
+    /// `lexeme_at()` returns the Lexeme which starts at or before `chr`, and
+    /// ends after it. The `<EOI>` sentinel is only returned if `chr` is exactly
+    /// the end of input. Any offset after that returns `None`.
+    pub fn lexeme_at(&self, chr: usize) -> Option<&Lexeme<'a>> {
+        // Lexemes are sorted by `chr`, so a binary search finds the first
+        // Lexeme which starts after `chr`. The one before it contains `chr`.
+        if chr > self.lexemes.last()?.chr { return None }
+        let after = self.lexemes.partition_point(|lexeme| lexeme.chr <= chr);
+        self.lexemes.get(after.checked_sub(1)?)
+    }
+
+    /// Iterates over every Lexeme, including the `<EOI>` sentinel.
+    /// 
+    /// `for lexeme in &result` does the same thing.
+    pub fn iter(&self) -> core::slice::Iter<'_, Lexeme<'a>> {
+        self.lexemes.iter()
+    }
+
+    /// Iterates over every Lexeme, apart from the `<EOI>` sentinel.
+    pub fn iter_no_eoi(&self) -> core::slice::Iter<'_, Lexeme<'a>> {
+        match self.lexemes.last() {
+            Some(last) if last.kind == LexemeKind::EndOfInput =>
+                self.lexemes[..self.lexemes.len()-1].iter(),
+            _ => self.lexemes.iter(),
+        }
+    }
+
+    /// Finds every Lexeme on a line, for line based IDE features.
+    /// 
+    /// A Lexeme which spans several lines, like a multiline comment, is found
+    /// for each line it touches. A line break belongs to the line it ends, so
+    /// the `// c\n` of `// c\nx` is only on line 1. As in `format_with()`,
+    /// `\n`, `\r\n` and a bare `\r` each count as a single line break. The
+    /// `<EOI>` sentinel is never found.
+    /// 
+    /// ### Arguments
+    /// * `line` The 1-indexed line number
+    /// 
+    /// ### Returns
+    /// `tokens_on_line()` returns the Lexemes which touch `line`, in order.
+    pub fn tokens_on_line(&self, line: usize) -> Vec<&Lexeme<'a>> {
+        self.lexemes.iter().zip(self.line_cols()).filter(|(lexeme, (first, _))| {
+            if lexeme.kind == LexemeKind::EndOfInput || *first > line {
+                return false
+            }
+            // The line break at the very end, if any, does not start a line.
+            let snippet = lexeme.snippet;
+            let inner = snippet.strip_suffix('\n').unwrap_or(snippet);
+            let inner = inner.strip_suffix('\r').unwrap_or(inner);
+            *first + count_line_breaks(inner) >= line
+        }).map(|(lexeme, _)| lexeme).collect()
+    }
+
+    /// Iterates over the Lexemes which a parser usually cares about.
+    /// 
+    /// Whitespace, Trivia and Comments (including doc comments) are skipped,
+    /// and so is the `<EOI>` sentinel.
+    pub fn significant(&self) -> impl Iterator<Item=&Lexeme<'a>> {
+        self.iter_no_eoi().filter(|lexeme| ! lexeme.is_trivia())
+    }
+
+    /// Finds every doc comment, like `//! a`, `/// b` or `/** c */`, so that a
+    /// documentation tool does not need to walk every Lexeme.
+    /// 
+    /// Both inner and outer doc comments are found, which
+    /// `Lexeme::doc_placement()` can tell apart, and `Lexeme::doc_text()`
+    /// extracts the text of each one. If the Lexemes were
+    /// merged by `LexemizeOptions::compact_trivia`, the doc comments are part
+    /// of `Trivia`, so none are found.
+    /// 
+    /// ### Returns
+    /// `doc_comments()` returns each `CommentDoc*` Lexeme, in order.
+    pub fn doc_comments(&self) -> Vec<&Lexeme<'a>> {
+        self.lexemes.iter().filter(|lexeme|
+            lexeme.kind == LexemeKind::CommentDocInline
+            || lexeme.kind == LexemeKind::CommentDocMultiline
+        ).collect()
+    }
+
+    /// Pairs each significant Lexeme with the run of trivia just before it.
+    /// 
+    /// Trivia is Whitespace, Trivia and Comments, as in `Lexeme::is_trivia()`.
+    /// Many parsers attach it to the next significant token, for example to
+    /// keep comments when reformatting code.
+    /// 
+    /// ### Returns
+    /// `with_attached_trivia()` returns a vector of (Lexeme, trivia) pairs. The
+    /// last pair is always the `<EOI>` sentinel, with any trailing trivia.
+    pub fn with_attached_trivia(&self) -> Vec<(Lexeme<'a>, Vec<Lexeme<'a>>)> {
+        let mut pairs = vec![];
+        let mut trivia = vec![];
+        let end = self.lexemes.len().saturating_sub(1);
+        for lexeme in &self.lexemes[..end] {
+            if lexeme.is_trivia() {
+                trivia.push(*lexeme);
+            } else {
+                pairs.push((*lexeme, core::mem::take(&mut trivia)));
+            }
+        }
+        if let Some(eoi) = self.lexemes.last() { pairs.push((*eoi, trivia)) }
+        pairs
+    }
+
+    /// Finds the byte span of each complete attribute, like `#[derive(Debug)]`
+    /// or `#![no_std]`, for tooling which treats attributes as a unit.
+    /// 
+    /// Each `AttributeStart` is matched to its closing `]` by counting the `[`
+    /// and `]` Punctuation after it, so nested brackets like the `[c]` of
+    /// `#[a(b[c])]` are included. Brackets in strings and comments are not
+    /// Punctuation, so they are ignored. This is only bracket matching, not an
+    /// attribute parser.
+    /// 
+    /// ### Returns
+    /// `attributes()` returns the byte range of each attribute, from the `#` to
+    /// the closing `]`, in order. An attribute with no closing `]` is left out.
+    pub fn attributes(&self) -> Vec<Range<usize>> {
+        let mut spans = vec![];
+        let mut i = 0;
+        while i < self.lexemes.len() {
+            let start = &self.lexemes[i];
+            i += 1;
+            if start.kind != LexemeKind::AttributeStart { continue }
+            // Find the `]` which balances the `[` just after the `#` or `#!`.
+            let mut depth = 0;
+            for (j, lexeme) in self.lexemes.iter().enumerate().skip(i) {
+                if lexeme.kind != LexemeKind::Punctuation { continue }
+                match lexeme.snippet {
+                    "[" => depth += 1,
+                    "]" => depth -= 1,
+                    _ => continue,
+                }
+                if depth == 0 {
+                    spans.push(start.chr..lexeme.end());
+                    i = j + 1;
+                    break;
+                }
+            }
+        }
+        spans
+    }
+
+    /// Finds delimiters which are not balanced, like the `]` of `(]` or both
+    /// `{` of `{{`, for tooling which wants to warn early.
+    /// 
+    /// Each `(`, `[` or `{` Punctuation is pushed onto a stack, and each `)`,
+    /// `]` or `}` pops its partner off the top. A closer which does not match
+    /// the top of the stack is reported, and left unmatched. Any openers left
+    /// on the stack at the end of input are reported too. Delimiters in
+    /// strings, chars and comments are not Punctuation, so they are ignored.
+    /// 
+    /// ### Returns
+    /// `unbalanced_delimiters()` returns the `chr` and char of each unbalanced
+    /// delimiter, in order.
+    pub fn unbalanced_delimiters(&self) -> Vec<(usize, char)> {
+        let mut unbalanced = vec![];
+        let mut openers: Vec<(usize, char)> = vec![];
+        for lexeme in &self.lexemes {
+            if ! lexeme.is_delimiter() { continue }
+            let c = lexeme.snippet.chars().next().unwrap_or('~');
+            let partner = match c {
+                ')' => '(',
+                ']' => '[',
+                '}' => '{',
+                _ => { openers.push((lexeme.chr, c)); continue }
+            };
+            match openers.last() {
+                Some((_, top)) if *top == partner => { openers.pop(); }
+                _ => unbalanced.push((lexeme.chr, c)),
+            }
+        }
+        unbalanced.append(&mut openers);
+        unbalanced.sort_unstable();
+        unbalanced
+    }
+
+    /// Counts how many Lexemes of each kind were found.
+    /// 
+    /// The `<EOI>` sentinel is not counted. Kinds which were not found are left
+    /// out, rather than counted as zero.
+    pub fn counts(&self) -> BTreeMap<LexemeKind, usize> {
+        let end = self.lexemes.len().saturating_sub(1);
+        let mut counts = BTreeMap::new();
+        for lexeme in &self.lexemes[..end] {
+            *counts.entry(lexeme.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Counts how many Lexemes are in a group, like
+    /// `result.count_group(LexemeKind::is_comment)`.
+    /// 
+    /// The `<EOI>` sentinel is not counted.
+    pub fn count_group(&self, in_group: fn(&LexemeKind) -> bool) -> usize {
+        let end = self.lexemes.len().saturating_sub(1);
+        self.lexemes[..end].iter().filter(|lexeme| in_group(&lexeme.kind)).count()
+    }
+
+    /// Finds number literals which were probably meant to have a lowercase
+    /// prefix, like `0B11`, which is likely a typo for `0b11`.
+    /// 
+    /// Only `0b`, `0o` and `0x` are prefixes in Rust, so `0B11` is lexemized as
+    /// the Number `0` followed by the Identifier `B11`. That is correct, but
+    /// surprising, so this opt-in lint looks for a `0` which is immediately
+    /// followed by `B`, `O` or `X`, and then a valid digit for that base.
+    /// 
+    /// ### Returns
+    /// `suspicious_number_prefixes()` returns the `chr` of each suspicious `0`.
+    pub fn suspicious_number_prefixes(&self) -> Vec<usize> {
+        self.lexemes.windows(2).filter(|pair| {
+            let (zero, next) = (&pair[0], &pair[1]);
+            if zero.kind != LexemeKind::NumberDecimal || zero.snippet != "0"
+            || ! next.kind.is_identifier() || next.chr != zero.chr + 1 {
+                return false
+            }
+            let mut chars = next.snippet.chars();
+            let radix = match chars.next() {
+                Some('B') => 2,
+                Some('O') => 8,
+                Some('X') => 16,
+                _ => return false,
+            };
+            chars.next().is_some_and(|c| c.is_digit(radix))
+        }).map(|pair| pair[0].chr).collect()
+    }
+
+    /// Finds number literals which were probably meant to have an exponent,
+    /// like `1+1`, which may be a typo for `1e+1`.
+    /// 
+    /// `detect_number()` never takes a `+` or `-` unless it follows an `e` or
+    /// `E`, so `1+1` is lexemized as `1`, `+` and `1`. That is correct, and is
+    /// usually just addition, so this opt-in lint is noisy. It looks for a
+    /// decimal Number with no exponent, immediately followed by `+` or `-` and
+    /// then another Number, with no whitespace. It also looks for a number with
+    /// an exponent but no exponent digits, like `10e` or `9E+`, which is
+    /// `Unexpected`.
+    /// 
+    /// ### Returns
+    /// `suspicious_numbers()` returns the `chr` of each suspicious number, in
+    /// order.
+    pub fn suspicious_numbers(&self) -> Vec<usize> {
+        let lexemes = &self.lexemes;
+        (0..lexemes.len()).filter(|i| {
+            let number = &lexemes[*i];
+            let snippet = number.snippet;
+            // A number with a bare exponent, like `10e` or `2.5e-`.
+            if number.kind == LexemeKind::Unexpected {
+                return snippet.starts_with(|c: char| c.is_ascii_digit())
+                    && ! matches!(snippet.get(..2), Some("0b" | "0o" | "0x"))
+                    && snippet.trim_end_matches(['+', '-']).ends_with(['e', 'E'])
+            }
+            // A number followed directly by a sign and another number. A
+            // number with a type suffix, like `1u8`, was not meant to have an
+            // exponent.
+            if ! matches!(number.kind,
+                LexemeKind::NumberDecimal | LexemeKind::NumberDecimalFloat)
+            || snippet.contains(['e', 'E'])
+            || ! split_number_suffix(snippet).1.is_empty() { return false }
+            match (lexemes.get(i + 1), lexemes.get(i + 2)) {
+                (Some(sign), Some(next)) =>
+                    sign.kind == LexemeKind::Punctuation
+                    && (sign.snippet == "+" || sign.snippet == "-")
+                    && sign.chr == number.end()
+                    && next.kind.is_number()
+                    && next.chr == sign.end(),
+                _ => false,
+            }
+        }).map(|i| lexemes[i].chr).collect()
+    }
+
+    /// Finds each empty group, like the unit type `()`, or `[]` or `{}`.
+    /// 
+    /// Trivia is ignored, so `( )` and `{ /* none */ }` are empty too. Only an
+    /// opener whose very next significant Lexeme is its partner closer counts,
+    /// so `({})` has one empty group, the `{}`.
+    /// 
+    /// ### Returns
+    /// `empty_groups()` returns the `chr` of each empty group’s opener, in
+    /// order.
+    pub fn empty_groups(&self) -> Vec<usize> {
+        use super::lexeme::PunctOp::*;
+        let mut groups = vec![];
+        let mut previous: Option<&Lexeme> = None;
+        for lexeme in self.significant() {
+            if let Some(opener) = previous {
+                let pair = (opener.punctuation_op(), lexeme.punctuation_op());
+                if matches!(pair, (Some(OpenParen), Some(CloseParen))
+                | (Some(OpenBracket), Some(CloseBracket))
+                | (Some(OpenBrace), Some(CloseBrace))) {
+                    groups.push(opener.chr);
+                }
+            }
+            previous = Some(lexeme);
+        }
+        groups
+    }
+
+    /// Finds each trailing separator, like the `,` of `[1, 2,]`, so that a
+    /// formatter can add or remove trailing commas.
+    /// 
+    /// A `,` or `;` is trailing if the next significant Lexeme is the `)`, `]`
+    /// or `}` which closes the group the separator is in, or if nothing
+    /// significant follows it at all. Trivia is ignored, so the `,` of
+    /// `(a, /* b */)` is trailing. A closer which does not match its opener,
+    /// like the `]` of `(a,]`, does not count.
+    /// 
+    /// ### Returns
+    /// `separator_positions()` returns the `chr` of each trailing separator, in
+    /// order.
+    pub fn separator_positions(&self) -> Vec<usize> {
+        let is_separator = |lexeme: &Lexeme| lexeme.kind == LexemeKind::Punctuation
+            && (lexeme.snippet == "," || lexeme.snippet == ";");
+        let mut positions = vec![];
+        let mut openers: Vec<char> = vec![];
+        let mut previous: Option<&Lexeme> = None;
+        for lexeme in self.significant() {
+            // Track the open groups, and whether this closer matches one.
+            let mut closes_group = false;
+            if lexeme.is_delimiter() {
+                let c = lexeme.snippet.chars().next().unwrap_or('~');
+                let partner = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    '}' => '{',
+                    _ => { openers.push(c); '~' }
+                };
+                if openers.last() == Some(&partner) {
+                    openers.pop();
+                    closes_group = true;
+                }
+            }
+            if let Some(separator) = previous.filter(|p| is_separator(p)) {
+                if closes_group { positions.push(separator.chr) }
+            }
+            previous = Some(lexeme);
+        }
+        // A separator at the very end of the input is also trailing.
+        if let Some(separator) = previous.filter(|p| is_separator(p)) {
+            positions.push(separator.chr);
+        }
+        positions
+    }
+
+    /// Finds string literals which directly follow another string literal,
+    /// like `"a" "b"`, for concatenation linting.
+    /// 
+    /// Unlike C, Rust does not join adjacent string literals, so code ported
+    /// from C which splits a long string this way will not compile. Only
+    /// trivia may come between the two strings, so `"a" + "b"` and
+    /// `"a", "b"` are fine. A run of three strings gives two pairs.
+    /// 
+    /// ### Returns
+    /// `adjacent_string_literals()` returns the `chr` of the first and second
+    /// string of each adjacent pair, in order.
+    pub fn adjacent_string_literals(&self) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        let mut previous: Option<&Lexeme> = None;
+        for lexeme in self.significant() {
+            if let Some(previous) = previous {
+                if previous.kind.is_string() && lexeme.kind.is_string() {
+                    pairs.push((previous.chr, lexeme.chr));
+                }
+            }
+            previous = Some(lexeme);
+        }
+        pairs
+    }
+
+    /// Flags integer literals which are too large for their type, like `256u8`
+    /// or an unsuffixed literal which overflows `u128`.
+    /// 
+    /// This is an opt-in pass, for linters which want to warn. Each oversized
+    /// literal is retagged as `Unexpected`, and recorded in `reasons` with
+    /// `UnexpectedReason::OversizedNumber`. See `flag_oversized_numbers()` in
+    /// the `check::oversized_number` module for the details. `diagnostics` is
+    /// updated to match.
+    pub fn flag_oversized_numbers(&mut self) {
+        self.reasons.append(&mut flag_oversized_numbers(&mut self.lexemes));
+        self.reasons.sort_by_key(|(chr, _)| *chr);
+        self.diagnostics = diagnose(&self.lexemes, &self.reasons);
+    }
+
+    /// Lexemizes edited code again, reusing the Lexemes which the edit did not
+    /// affect, for example after each keystroke in an IDE.
+    /// 
+    /// Detection restarts a few Lexemes before the edit, just after some
+    /// Whitespace, and stops soon after it, as soon as the new Lexemes line up
+    /// with the old ones again. The unchanged Lexemes before and after are
+    /// spliced in, with the later ones moved by the change in length. `REFINERS`
+    /// and `diagnose()` then run over every Lexeme, because they are cheap
+    /// compared with detection.
+    /// 
+    /// A raw string with unbalanced hashes, like `r#"abc"`, may be completed by
+    /// an edit anywhere after it, so detection restarts before the first one.
+    /// An edit inside an unterminated string or block comment re-lexemizes to
+    /// the end of input, because the unterminated Lexeme runs to the end.
+    /// 
+    /// `self` must be the result of `lexemize()`, with the default options,
+    /// for the code before the edit. If the edit does not fit that code, or
+    /// `reasons` is not empty, `relex()` just calls `lexemize()` instead.
+    /// 
+    /// ### Arguments
+    /// * `orig` The original Rust code, after the edit
+    /// * `edit` The byte range which was replaced, in the code before the edit
+    /// * `new_len` The length in bytes of the text which replaced `edit`
+    /// 
+    /// ### Returns
+    /// `relex()` returns a [`LexemizeResult`] object, the same as
+    /// `lexemize(orig)` would.
+    pub fn relex<'b>(
+        &self,
+        orig: &'b str,
+        edit: Range<usize>,
+        new_len: usize,
+    ) -> LexemizeResult<'b> {
+        // The `<EOI>` sentinel’s `chr` is the length of the code before the edit.
+        let old_len = match self.lexemes.last() {
+            Some(eoi) => eoi.chr,
+            None => return lexemize(orig),
+        };
+        let new_end = edit.start + new_len;
+        if edit.start > edit.end || edit.end > old_len
+        || orig.len() + edit.len() != old_len + new_len
+        || ! orig.is_char_boundary(edit.start) || ! orig.is_char_boundary(new_end)
+        || ! self.reasons.is_empty() {
+            return lexemize(orig)
+        }
+
+        // Find a Lexeme well before the edit, which follows some Whitespace.
+        // No refiner merges or splits Lexemes across Whitespace, so detection
+        // which restarts there gives the same Lexemes as before.
+        let old = &self.lexemes;
+        let containing = old.partition_point(|lexeme| lexeme.chr <= edit.start);
+        let mut start = containing.saturating_sub(1 + RELEX_MARGIN);
+        if let Some(i) = (0..start).find(|i| find_malformed_raw_string(old, *i).is_some()) {
+            start = i;
+        }
+        while start > 0 && (old[start].chr + RELEX_MARGIN_BYTES > edit.start
+            || old[start-1].kind != LexemeKind::WhitespaceTrimmable) {
+            start -= 1;
+        }
+
+        // Keep the Lexemes before `start`, whose snippets are unchanged.
+        let mut lexemes: Vec<Lexeme> = old[..start].iter().map(|lexeme| Lexeme {
+            snippet: &orig[lexeme.chr..lexeme.chr + lexeme.snippet.len()],
+            ..*lexeme
+        }).collect();
+
+        // Detect Lexemes until the last few, after the edit, have the same
+        // positions and lengths as old Lexemes, moved by the change in length.
+        // From then on, detection would give the same Lexemes as before.
+        let mut resume = None;
+        let restart = old[start].chr;
+        let lexemizer = Lexemizer::default();
+        lexemizer.detect_lexemes(orig, restart, &mut lexemes, |chr, found| {
+            if found.len() < RELEX_CONTEXT { return false }
+            let tail = &found[found.len() - RELEX_CONTEXT..];
+            if tail[0].chr < new_end { return false }
+            let old_chr = chr + edit.len() - new_len;
+            let j = match old.binary_search_by_key(&old_chr, |lexeme| lexeme.chr) {
+                Ok(j) if j >= RELEX_CONTEXT => j,
+                _ => return false,
+            };
+            let lines_up = tail.iter().zip(&old[j - RELEX_CONTEXT..j]).all(|(new, old)|
+                new.chr + edit.len() == old.chr + new_len
+                && new.snippet.len() == old.snippet.len());
+            if lines_up { resume = Some(j) }
+            lines_up
+        });
+
+        // Splice in the unchanged Lexemes after the edit.
+        if let Some(j) = resume {
+            for lexeme in &old[j..old.len()-1] {
+                let chr = lexeme.chr + new_len - edit.len();
+                lexemes.push(Lexeme {
+                    snippet: &orig[chr..chr + lexeme.snippet.len()],
+                    chr,
+                    ..*lexeme
+                });
+            }
+        }
+
+        // `tag_macro_repetition()` can tag a `*` far from its `macro_rules`,
+        // so untag every repetition operator, then add `<EOI>` and run all the
+        // refiners.
+        for lexeme in lexemes.iter_mut() {
+            if lexeme.kind == LexemeKind::MacroRepeatOp {
+                lexeme.kind = LexemeKind::Punctuation;
+            }
+        }
+        let lexemes = refine(orig, lexemes, orig.len());
+        let diagnostics = diagnose(&lexemes, &[]);
+        let ends_with_newline = ends_with_newline(&lexemes);
+        LexemizeResult {
+            lexemes,
+            reasons: vec![],
+            diagnostics,
+            ends_with_newline,
+        }
+    }
+
+    /// Formats the Lexemes as a table, one Lexeme per line.
+    /// 
+    /// `result.to_string()` is the same as
+    /// `result.format_with(DisplayOptions::default())`.
+    /// 
+    /// ### Arguments
+    /// * `opts` A [`DisplayOptions`] object, which modifies the layout
+    /// 
+    /// ### Returns
+    /// `format_with()` returns a multiline `String`, with a header line.
+    pub fn format_with(&self, opts: DisplayOptions) -> String {
+        let line_cols = if opts.line_col { self.line_cols() } else { vec![] };
         let mut out = format!("Lexemes, incl <EOI>: {}\n", self.lexemes.len());
+        for (i, lexeme) in self.lexemes.iter().enumerate() {
+            let kind = format!("{:?}", lexeme.kind);
+            let snippet = if lexeme.kind == LexemeKind::EndOfInput { "<EOI>".into() }
+                else { lexeme.snippet.replace("\n", opts.newline_placeholder) };
+            let width = opts.kind_width;
+            // Get the position as a line and column, a byte offset, or nothing.
+            let pos = if opts.line_col {
+                let (line, col) = line_cols[i];
+                format!("{}:{}", line, col)
+            } else if opts.show_offset {
+                lexeme.chr.to_string()
+            } else {
+                "".to_string()
+            };
+            if opts.line_col || opts.show_offset {
+                out.push_str(&format!("{: <w$} {: >4}  {}", kind, pos, snippet, w = width));
+            } else {
+                out.push_str(&format!("{: <w$}  {}", kind, snippet, w = width));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Formats the Lexemes as JSON Lines, one compact JSON object per Lexeme.
+    /// 
+    /// Each line looks like `{"kind":"StringPlain","chr":0,"snippet":"\"a\""}`.
+    /// The `<EOI>` sentinel is included, just like the `Display` table.
+    /// 
+    /// ### Returns
+    /// `to_ndjson()` returns a `String`, where every line ends with `"\n"`.
+    pub fn to_ndjson(&self) -> String {
+        let mut out = String::new();
         for lexeme in &self.lexemes {
-            out.push_str(&lexeme.to_string());
-            out.push_str("\n");
+            out.push_str(&format!(
+                "{{\"kind\":\"{:?}\",\"chr\":{},\"snippet\":\"{}\"}}\n",
+                lexeme.kind,
+                lexeme.chr,
+                escape_json(lexeme.snippet),
+            ));
+        }
+        out
+    }
+
+    // Returns the 1-indexed line and column where each Lexeme starts. Columns
+    // count chars, not bytes. Like most editors, "\n", "\r\n" and a bare "\r"
+    // each count as a single line break.
+    fn line_cols(&self) -> Vec<(usize, usize)> {
+        let mut line = 1;
+        let mut col = 1;
+        // A "\r" only counts as a line break once we know that it is not
+        // followed by "\n". They can be in different Lexemes, eg "//x\r" "\n".
+        let mut after_cr = false;
+        let mut line_cols = Vec::with_capacity(self.lexemes.len());
+        for lexeme in &self.lexemes {
+            // If a bare "\r" ended the previous Lexeme, this starts a new line.
+            if after_cr && ! lexeme.snippet.starts_with('\n') {
+                line += 1;
+                col = 1;
+                after_cr = false;
+            }
+            line_cols.push((line, col));
+            for c in lexeme.snippet.chars() {
+                if after_cr && c != '\n' { line += 1; col = 1 }
+                after_cr = c == '\r';
+                if c == '\n' { line += 1; col = 1 } else { col += 1 }
+            }
+        }
+        line_cols
+    }
+}
+
+// Counts the line breaks in `s`, where "\n", "\r\n" and a bare "\r" each count
+// as one.
+fn count_line_breaks(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    (0..bytes.len()).filter(|i| bytes[*i] == b'\n'
+        || (bytes[*i] == b'\r' && bytes.get(i + 1) != Some(&b'\n'))).count()
+}
+
+// Escapes a string so that it can be placed between double quotes in JSON.
+fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            // Any other control character must be escaped as a code point.
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl Display for LexemizeResult<'_> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), Error> {
+        write!(fmt, "{}", self.format_with(DisplayOptions::default()))
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'a LexemizeResult<'b> {
+    type Item = &'a Lexeme<'b>;
+    type IntoIter = core::slice::Iter<'a, Lexeme<'b>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lexemes.iter()
+    }
+}
+
+impl<'a> IntoIterator for LexemizeResult<'a> {
+    type Item = Lexeme<'a>;
+    type IntoIter = alloc::vec::IntoIter<Lexeme<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.lexemes.into_iter()
+    }
+}
+
+/// Options which change the way `LexemizeResult::format_with()` lays out
+/// its table.
+#[derive(Clone,Copy,Debug)]
+pub struct DisplayOptions {
+    /// Show each Lexeme’s byte offset. Defaults to `true`.
+    pub show_offset: bool,
+    /// Show each Lexeme’s 1-indexed `line:column` instead of its byte offset.
+    /// Defaults to `false`.
+    pub line_col: bool,
+    /// Replaces each newline in a snippet. Defaults to `"<NL>"`.
+    pub newline_placeholder: &'static str,
+    /// The width of the column which shows each Lexeme’s kind. Defaults to `20`.
+    pub kind_width: usize,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            show_offset: true,
+            line_col: false,
+            newline_placeholder: "<NL>",
+            kind_width: 20,
         }
-        write!(fmt, "{}", out)
     }
 }
 
+/// Options which change the way `lexemize_with_options()` behaves.
+/// 
+/// `LexemizeOptions::default()` gives the same result as `lexemize()`.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct LexemizeOptions {
+    /// Merge each run of Whitespace and Comment Lexemes into a single `Trivia`.
+    pub compact_trivia: bool,
+    /// Run the functions in `CHECKERS`, which flag suspicious Lexemes, like
+    /// the inexact float `0.1`, as `Unexpected`.
+    pub checked: bool,
+    /// Give each unidentifiable character its own `Unidentifiable` Lexeme,
+    /// rather than merging a run of them, for more precise error reporting.
+    pub split_unidentifiable: bool,
+    /// Treat a carriage return which is not immediately followed by a line
+    /// feed, like the `\r` of `a\rb`, as `Unexpected`, rather than part of a
+    /// run of Whitespace. A `\r\n` is always fine.
+    pub forbid_lone_cr: bool,
+    /// Treat a NUL character which is outside a string or comment, like the
+    /// `\0` of `a\0b`, as `Unexpected`, rather than `Unidentifiable`. Each NUL
+    /// gets its own Lexeme. A NUL inside a string or comment is left alone.
+    pub nul_is_error: bool,
+    /// Accept a float with no integer part, like `.5` or `.5e3`, as a single
+    /// `NumberDecimalFloat`. Rust does not allow these, so by default `.5` is
+    /// the Punctuation `.` and then `5`. A `.` which follows something with
+    /// fields, like the `x` of `x.0`, is never part of a float.
+    pub allow_leading_dot_float: bool,
+    /// The Rust edition which `orig` is written for, which decides whether
+    /// words like `async` are Keywords, and whether prefixes like the `foo` of
+    /// `foo#bar` are reserved. Defaults to `Edition::Rust2018`.
+    pub edition: Edition,
+}
+
+/// A function which detects a Lexeme, like `detect_comment()`.
+pub type Detector = fn (&str, usize) -> (LexemeKind, usize);
+
+/// A function which refines Lexemes after detection, like `tag_macro_invocation()`.
+pub type Refiner = for<'a> fn (&'a str, Vec<Lexeme<'a>>) -> Vec<Lexeme<'a>>;
+
+/// A function which flags suspicious Lexemes, like `flag_inexact_floats()`.
+/// 
+/// It retags each flagged Lexeme as `Unexpected`, and returns its `chr` along
+/// with the reason it was flagged.
+pub type Checker = fn (&mut [Lexeme]) -> Vec<(usize, UnexpectedReason)>;
+
 /// An array which contains all the `detect_*()` functions, in the proper order.
 /// 
 /// We usually default to alphabetical order, but need to make one exception:
 /// `String` can start with an `"r"` character, so `detect_string()` must be
 /// placed before `detect_identifier()`.
-pub const DETECTORS: [fn (&str, usize) -> (LexemeKind, usize); 7] = [
+/// 
+/// `detect_unexpected()` is placed after the detectors for the tokens it looks
+/// for, so that it only has to do any work if they have already failed. It must
+/// also be placed before `detect_punctuation()`, which would otherwise take the
+/// `/` of an unterminated `/*` comment. For the same reason, `detect_shebang()`
+/// is placed before `detect_punctuation()`, which would take its `#` and `!`.
+pub const DETECTORS: [Detector; 10] = [
+    detect_byte_order_mark,
     detect_character,
     detect_comment,
+    detect_shebang,
     detect_string,
     detect_identifier,
     detect_number,
+    detect_unexpected,
     detect_punctuation,
     detect_whitespace,
 ];
 
+/// An array which contains all the `tag_*()` functions, in the proper order.
+/// 
+/// Each refinement runs after all the Lexemes have been detected. Some merge
+/// Lexemes, so `tag_lifetime()` must be placed before `tag_label()` and
+/// `tag_hrtb()`, which look for the Lifetime of `'a: loop` and `for<'a>`. Also,
+/// `tag_macro_invocation()` must be placed before `tag_macro_repetition()`,
+/// which looks for the Macro `macro_rules`, and `tag_macro_rules()` must be
+/// placed after it, because it retags that Macro as a Keyword. `tag_union()`
+/// only retags a Freeword, so it can go anywhere after the detectors.
+pub const REFINERS: [Refiner; 9] = [
+    tag_lifetime,
+    tag_label,
+    tag_hrtb,
+    tag_macro_invocation,
+    tag_macro_repetition,
+    tag_macro_rules,
+    tag_union,
+    tag_attribute,
+    tag_tuple_index,
+];
+
+/// An array which contains all the `flag_*()` functions, used in checked mode.
+pub const CHECKERS: [Checker; 2] = [
+    flag_inexact_floats,
+    flag_keyword_lifetimes,
+];
+
 /// Transforms a Rust 2018 program into a vector of `Lexemes`.
 /// 
 /// The primary purpose of `lexemize()` is to quickly divide Rust code into
 /// three basic sections — comments, strings, and everything else.
 /// 
 /// The ‘everything else’ section is then divided into literals, punctuation,
-/// whitespace and identifiers. Anything left over is marked as ‘Unidentifiable’.
+/// whitespace and identifiers. Tokens which look like they were meant to be
+/// valid but are malformed, like `"abc` with no closing quote, are marked as
+/// ‘Unexpected’. Anything left over is marked as ‘Unidentifiable’.
+/// 
+/// Finally, the functions in `REFINERS` look at neighbouring Lexemes to refine
+/// some categories, for example to tag the `foo` of `foo!()` as a macro.
 /// 
 /// Any input string can be lexemized, so this function never returns any kind
 /// of error. Checking `orig` for semantic correctness should be done later on,
@@ -61,29 +929,442 @@ pub const DETECTORS: [fn (&str, usize) -> (LexemeKind, usize); 7] = [
 /// ### Returns
 /// `lexemize()` returns a [`LexemizeResult`] object.
 pub fn lexemize(
-    orig: &'static str
-) -> LexemizeResult {
-    // Initialise `len`, and some mutable variables.
-    let len = orig.len();
+    orig: &str
+) -> LexemizeResult<'_> {
+    lexemize_with_options(orig, LexemizeOptions::default())
+}
+
+/// Transforms a Rust 2018 program into `Lexemes`, and passes each one to a
+/// callback, rather than returning a [`LexemizeResult`].
+/// 
+/// The whole program is never collected into a `Vec`. Instead, it is detected
+/// and refined one top-level item or statement at a time, ending at a `;` or
+/// `}` which is outside any brackets, and each Lexeme of that chunk is passed
+/// to `f` before the next chunk is detected. No refiner looks past such a `;`
+/// or `}`, so the Lexemes are exactly the same as `lexemize().lexemes`. Only
+/// the current chunk is held in memory, but code with no top-level `;` or `}`,
+/// like one big `mod` block, is still a single chunk. No diagnostics are found.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `f` Called with each Lexeme in order, the same as `lexemize().lexemes`,
+///   ending with the `<EOI>` sentinel
+pub fn lexemize_each<'a>(
+    orig: &'a str,
+    mut f: impl FnMut(Lexeme<'a>),
+) {
+    let lexemizer = Lexemizer::default();
+    let mut chunk: Vec<Lexeme<'a>> = vec![];
     let mut chr = 0;
-    let mut unident_chr = 0;
+    // The bracket depth, and whether a `macro_rules` is still waiting for its
+    // body, which `tag_macro_repetition()` looks for however far away it is.
+    let mut depth = 0usize;
+    let mut awaiting_body = false;
+    // The previous chunk’s final `;` or `}` is kept at the start of the next
+    // one, so that `follows_operand()` can see it. It is not passed on twice.
+    let mut context = 0;
+    loop {
+        let mut scanned = context;
+        chr = lexemizer.detect_lexemes(orig, chr, &mut chunk, |_, lexemes| {
+            for lexeme in &lexemes[scanned..] {
+                if lexeme.kind != LexemeKind::Punctuation {
+                    if lexeme.snippet == "macro_rules" { awaiting_body = true }
+                    continue
+                }
+                match lexeme.snippet {
+                    "(" | "[" | "{" => { depth += 1; awaiting_body = false }
+                    ")" | "]" | "}" => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            scanned = lexemes.len();
+            lexemes.len() > context && depth == 0 && ! awaiting_body
+                && lexemes.last().is_some_and(|last| last.kind == LexemeKind::Punctuation
+                    && (last.snippet == ";" || last.snippet == "}"))
+        });
+        if chr == orig.len() { break }
+        // Refine the chunk, without the `<EOI>` sentinel, and pass it on.
+        let refined = run_refiners(orig, core::mem::take(&mut chunk));
+        let last = refined[refined.len()-1];
+        refined.into_iter().skip(context).for_each(&mut f);
+        chunk.push(last);
+        context = 1;
+    }
+    refine(orig, chunk, chr).into_iter().skip(context).for_each(f);
+}
+
+/// Transforms a Rust 2018 program, given as bytes, into a vector of `Lexemes`.
+/// 
+/// Valid UTF-8 is checked once, and then lexemized exactly like `lexemize()`,
+/// without copying. Invalid UTF-8 never causes a panic. Instead, `orig` is
+/// copied into `buf`, with each byte of an invalid sequence replaced by the
+/// ASCII substitute character `\u{1A}`, and `buf` is lexemized in one pass.
+/// So every `chr` is the same in `buf` as in `orig`, and a string or comment
+/// which contains an invalid sequence is still a single Lexeme. Elsewhere,
+/// each invalid sequence becomes an ‘Unidentifiable’ Lexeme of its own, with
+/// its real length. Every invalid sequence also gets the diagnostic “invalid
+/// UTF-8”, even inside a string or comment.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to be UTF-8 and to conform to the
+///   2018 edition
+/// * `buf` The buffer which `orig` is copied into, if it is not valid UTF-8
+/// 
+/// ### Returns
+/// `lexemize_bytes()` returns a [`LexemizeResult`] object, whose snippets are
+/// borrowed from `orig` if it is valid UTF-8, or else from `buf`.
+pub fn lexemize_bytes<'a>(
+    orig: &'a [u8],
+    buf: &'a mut String,
+) -> LexemizeResult<'a> {
+    if let Ok(orig) = core::str::from_utf8(orig) { return lexemize(orig) }
+
+    // Copy `orig` into `buf`, noting the `chr` and length of each invalid
+    // sequence. A sequence cut short at the end uses every remaining byte.
+    buf.clear();
+    let mut invalid = vec![];
+    let mut start = 0;
+    while start < orig.len() {
+        let (valid_len, invalid_len) = match core::str::from_utf8(&orig[start..]) {
+            Ok(valid) => (valid.len(), 0),
+            Err(err) => (err.valid_up_to(), err.error_len()
+                .unwrap_or(orig.len() - start - err.valid_up_to())),
+        };
+        buf.push_str(core::str::from_utf8(&orig[start..start+valid_len])
+            .unwrap_or_default());
+        start += valid_len;
+        if invalid_len == 0 { continue }
+        buf.extend(core::iter::repeat_n('\u{1A}', invalid_len));
+        invalid.push((start, invalid_len));
+        start += invalid_len;
+    }
+    let buf: &'a str = buf;
+    let result = lexemize(buf);
+
+    // Split any Unidentifiable Lexeme at the start and end of each invalid
+    // sequence inside it, so that the sequence is not merged with its
+    // neighbours, like the `§` of `§\xFF`.
+    let mut lexemes = Vec::with_capacity(result.lexemes.len() + invalid.len());
+    for lexeme in result.lexemes {
+        if lexeme.kind != LexemeKind::Unidentifiable { lexemes.push(lexeme); continue }
+        let first = invalid.partition_point(|(chr, len)| chr + len <= lexeme.chr);
+        let inside = invalid[first..].iter().take_while(|(chr, _)| *chr < lexeme.end());
+        let mut rest = lexeme;
+        for (chr, len) in inside {
+            for cut in [*chr, chr + len] {
+                let offset = cut.saturating_sub(rest.chr);
+                if let Some((before, after)) = rest.split_at(offset) {
+                    lexemes.push(before);
+                    rest = after;
+                }
+            }
+        }
+        lexemes.push(rest);
+    }
+
+    // Describe each invalid sequence, rather than each Unidentifiable Lexeme
+    // which is exactly an invalid sequence.
+    let mut diagnostics = diagnose(&lexemes, &result.reasons);
+    diagnostics.retain(|d| ! invalid.contains(&(d.chr, d.len)));
+    diagnostics.extend(invalid.iter().map(|(chr, len)| Diagnostic {
+        chr: *chr,
+        len: *len,
+        message: "invalid UTF-8".into(),
+    }));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.chr);
+    LexemizeResult {
+        lexemes,
+        reasons: result.reasons,
+        diagnostics,
+        ends_with_newline: result.ends_with_newline,
+    }
+}
+
+/// Reads a Rust 2018 program into a buffer, and transforms it into a vector
+/// of `Lexemes`.
+/// 
+/// Every snippet borrows from `buf`, so the result cannot outlive it. Any
+/// previous contents of `buf` are cleared first. To keep the Lexemes after
+/// `buf` is dropped, copy them with `to_owned_lexemes()`.
+/// 
+/// Only available with the default `std` feature.
+/// 
+/// ### Arguments
+/// * `reader` Any source of UTF-8 Rust code, like a `File` or `Stdin`
+/// * `buf` The buffer which the code is read into
+/// 
+/// ### Returns
+/// `lexemize_reader()` returns a [`LexemizeResult`] object, the same as
+/// `lexemize()` would for the code, or an error if reading fails or the code
+/// is not valid UTF-8.
+#[cfg(feature = "std")]
+pub fn lexemize_reader<R: std::io::Read>(
+    mut reader: R,
+    buf: &mut String,
+) -> std::io::Result<LexemizeResult<'_>> {
+    buf.clear();
+    reader.read_to_string(buf)?;
+    Ok(lexemize(buf))
+}
+
+/// Transforms a large Rust 2018 program into a vector of `Lexemes`, using
+/// several threads.
+/// 
+/// `orig` is split into chunks, at newlines which are probably not inside a
+/// string or block comment: the newline must follow a `;` or `}`, and the
+/// next line must start with a non-whitespace ascii character. Each chunk is
+/// detected on its own thread. Detection of a chunk does not stop at the end
+/// of the chunk, but at the first Lexeme which starts there or after it. So a
+/// string which continues past a split is still detected whole.
+/// 
+/// The chunks are then joined. Where a chunk’s first Lexemes overlap the end
+/// of the previous chunk, they are skipped until a Lexeme starts in the same
+/// place as it would have done in a single pass. If no Lexeme does, detection
+/// carries on serially until one does. So a badly placed split only costs
+/// time, and the result is always the same as `lexemize(orig)`. Finally, the
+/// refiners run over every Lexeme, on the calling thread.
+/// 
+/// Only available with the `rayon` feature.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `threads` The number of threads to use, where `0` or `1` means serial
+/// 
+/// ### Returns
+/// `lexemize_parallel()` returns a [`LexemizeResult`] object, the same as
+/// `lexemize(orig)` would.
+#[cfg(feature = "rayon")]
+pub fn lexemize_parallel(
+    orig: &str,
+    threads: usize,
+) -> LexemizeResult<'_> {
+    use rayon::prelude::*;
+    let lexemizer = Lexemizer::default();
+    let len = orig.len();
+
+    // Find the start of each chunk. Small inputs are not worth splitting.
+    let mut starts = vec![0];
+    let target = len / threads.max(1);
+    if threads > 1 && target >= PARALLEL_MIN_CHUNK {
+        while let Some(start) = find_safe_split(orig, starts[starts.len()-1] + target) {
+            starts.push(start);
+        }
+    }
+    if starts.len() == 1 { return lexemizer.run(orig) }
+    let pool = match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool,
+        Err(_) => return lexemizer.run(orig),
+    };
+
+    // Detect each chunk on its own thread, until a Lexeme starts at or after
+    // the next chunk’s start. Also note where detection stopped.
+    let chunks: Vec<(Vec<Lexeme>, usize)> = pool.install(|| {
+        starts.par_iter().enumerate().map(|(i, start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(len);
+            let mut found = vec![];
+            let stop = lexemizer.detect_lexemes(
+                orig, *start, &mut found, |chr, _| chr >= end);
+            (found, stop)
+        }).collect()
+    });
+
+    // Join the chunks. `chr` is where the Lexemes joined so far stop. If a
+    // chunk has no Lexeme which starts at `chr`, detect serially until one
+    // does, or until the chunk is passed.
     let mut lexemes: Vec<Lexeme> = vec![];
+    let mut chr = 0;
+    for (found, stop) in chunks {
+        if chr >= stop { continue }
+        let starts_at = |c: usize| found.binary_search_by_key(&c, |l| l.chr).ok();
+        chr = lexemizer.detect_lexemes(orig, chr, &mut lexemes, |c, _|
+            c >= stop || starts_at(c).is_some());
+        if let Some(i) = starts_at(chr) {
+            if chr < stop {
+                lexemes.extend_from_slice(&found[i..]);
+                chr = stop;
+            }
+        }
+    }
+    lexemizer.finish(orig, lexemes, chr)
+}
+
+// Returns the position just after the first newline at or after `from` which
+// follows a `;` or `}`, and is followed by a non-whitespace ascii character.
+#[cfg(feature = "rayon")]
+fn find_safe_split(orig: &str, from: usize) -> Option<usize> {
+    let bytes = orig.as_bytes();
+    (from.max(1)..bytes.len().saturating_sub(1)).find(|i| bytes[*i] == b'\n'
+        && matches!(bytes[i-1], b';' | b'}')
+        && bytes[i+1].is_ascii_graphic()
+    ).map(|i| i + 1)
+}
+
+/// Transforms a Rust 2018 program into a vector of `Lexemes`, with options.
+/// 
+/// See `lexemize()` for details.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, assumed to conform to the 2018 edition
+/// * `options` A [`LexemizeOptions`] object, which modifies the result
+/// 
+/// ### Returns
+/// `lexemize_with_options()` returns a [`LexemizeResult`] object.
+pub fn lexemize_with_options(
+    orig: &str,
+    options: LexemizeOptions,
+) -> LexemizeResult<'_> {
+    Lexemizer::default().options(options).run(orig)
+}
+
+/// A customisable lexemizer, which runs its own list of `detect_*()` functions.
+/// 
+/// `Lexemizer::default()` uses the functions in `DETECTORS`, so it gives the
+/// same result as `lexemize()`. Detectors can then be removed or added, for
+/// example to skip Number detection, or to add a custom detector for a DSL:
+/// 
+/// ```
+/// use op8d_lexemizer::rust_2018::detect::number::detect_number;
+/// use op8d_lexemizer::rust_2018::lexemize::Lexemizer;
+/// let result = Lexemizer::default().without(detect_number).run("x = 1;");
+/// assert_eq!(result.lexemes[4].snippet, "1");
+/// assert_eq!(result.lexemes[4].kind.is_number(), false);
+/// ```
+#[derive(Clone,Debug)]
+pub struct Lexemizer {
+    detectors: Vec<Detector>,
+    options: LexemizeOptions,
+}
+
+impl Default for Lexemizer {
+    fn default() -> Self {
+        Self {
+            detectors: DETECTORS.to_vec(),
+            options: LexemizeOptions::default(),
+        }
+    }
+}
+
+impl Lexemizer {
+    /// Adds a detector after the existing ones.
+    /// 
+    /// Detectors are tried in order, so the new detector only sees characters
+    /// which all the existing detectors have rejected.
+    pub fn with(mut self, detector: Detector) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    /// Removes a detector, like `detect_number()`, if it is in the pipeline.
+    /// 
+    /// Detectors are compared by function pointer, which Rust does not
+    /// guarantee to be unique. A function may get different addresses in
+    /// different codegen units, or two identical functions may be merged. So
+    /// in rare cases, `without()` may miss the detector, or remove another.
+    pub fn without(mut self, detector: Detector) -> Self {
+        self.detectors.retain(|d| ! core::ptr::fn_addr_eq(*d, detector));
+        self
+    }
+
+    /// Sets the [`LexemizeOptions`], which modify the result.
+    pub fn options(mut self, options: LexemizeOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Transforms Rust code into a vector of `Lexemes`, using this pipeline.
+    /// 
+    /// See `lexemize()` for details.
+    /// 
+    /// ### Arguments
+    /// * `orig` The original Rust code, assumed to conform to the 2018 edition
+    /// 
+    /// ### Returns
+    /// `run()` returns a [`LexemizeResult`] object.
+    pub fn run<'a>(
+        &self,
+        orig: &'a str,
+    ) -> LexemizeResult<'a> {
+        // Detect every Lexeme, from the start to the end of the input.
+        let mut lexemes: Vec<Lexeme> = vec![];
+        let chr = self.detect_lexemes(orig, 0, &mut lexemes, |_, _| false);
+        self.finish(orig, lexemes, chr)
+    }
+
+    // Adds the `<EOI>` sentinel at `chr` to some detected Lexemes, then refines
+    // and checks them, and wraps them in a `LexemizeResult`.
+    fn finish<'a>(
+        &self,
+        orig: &'a str,
+        lexemes: Vec<Lexeme<'a>>,
+        chr: usize,
+    ) -> LexemizeResult<'a> {
+        let mut lexemes = refine(orig, lexemes, chr);
+
+        // In checked mode, step through the array of `flag_*()` functions, each of
+        // which can retag suspicious Lexemes as Unexpected, and say why.
+        let mut reasons = vec![];
+        if self.options.checked {
+            for checker in CHECKERS.iter() {
+                reasons.append(&mut checker(&mut lexemes));
+            }
+            reasons.sort_by_key(|(chr, _)| *chr);
+        }
+
+        // Describe each malformed token, for an IDE to show.
+        let diagnostics = diagnose(&lexemes, &reasons);
+
+        // If requested, merge each run of Whitespace and Comments into one Lexeme.
+        if self.options.compact_trivia {
+            lexemes = compact_trivia(orig, lexemes);
+        }
 
-    // Loop until we reach the last character of the input.
-    'outer: while chr < len {
-        // Only try to detect a Lexeme if this is the start of a character.
-        if orig.is_char_boundary(chr) {
-            // Step through the array of `detect_*()` functions, and their
-            // associated `LexemeKinds`.
-            for detector in DETECTORS.iter() {
-
-                // If `detector()` does not detect the Lexeme, it will return
-                // the same char-position as `chr`. In that case, just return `chr`.
-                let (kind, next_chr) = detector(orig, chr);
-                if kind != LexemeKind::Undetected {
-
-                    // If any ‘Unidentifiable’ characters precede this Lexeme,
-                    // record them before recording this Lexeme.
+        // Create and return a result object.
+        let ends_with_newline = ends_with_newline(&lexemes);
+        LexemizeResult {
+            lexemes,
+            reasons,
+            diagnostics,
+            ends_with_newline,
+        }
+    }
+
+    // Detects Lexemes from `chr` onwards, and appends them to `lexemes`. Before
+    // each Lexeme, `done()` is passed its `chr` and every Lexeme so far, and can
+    // return `true` to stop early. Returns the position detection stopped at,
+    // which is `orig.len()` unless `done()` stopped it.
+    fn detect_lexemes<'a>(
+        &self,
+        orig: &'a str,
+        mut chr: usize,
+        lexemes: &mut Vec<Lexeme<'a>>,
+        mut done: impl FnMut(usize, &[Lexeme<'a>]) -> bool,
+    ) -> usize {
+        // Initialise `len`, and some mutable variables.
+        let len = orig.len();
+        let mut unident_chr = chr;
+
+        // Loop until we reach the last character of the input.
+        'outer: while chr < len {
+            // Only try to detect a Lexeme if this is the start of a character.
+            if orig.is_char_boundary(chr) {
+                // If requested, record the ‘Unidentifiable’ character before this
+                // one, so that it is not merged with any that follow it.
+                if self.options.split_unidentifiable && unident_chr != chr {
+                    lexemes.push(Lexeme {
+                        kind: LexemeKind::Unidentifiable,
+                        chr: unident_chr,
+                        snippet: &orig[unident_chr..chr],
+                    });
+                    unident_chr = chr;
+                }
+
+                // Stop early if the caller has seen enough. Only check between
+                // Lexemes, never part way through ‘Unidentifiable’ characters.
+                if unident_chr == chr && done(chr, lexemes) { return chr }
+
+                // If requested, record a NUL as ‘Unexpected’, after any
+                // ‘Unidentifiable’ characters which precede it.
+                if self.options.nul_is_error && orig.as_bytes()[chr] == 0 {
                     if unident_chr != chr {
                         lexemes.push(Lexeme {
                             kind: LexemeKind::Unidentifiable,
@@ -92,56 +1373,104 @@ pub fn lexemize(
                         });
                     }
                     lexemes.push(Lexeme {
-                        kind,
+                        kind: LexemeKind::Unexpected,
                         chr,
-                        snippet: &orig[chr..next_chr],
+                        snippet: &orig[chr..chr+1],
                     });
-
-                    // Step forward to the position after this Lexeme.
-                    chr = next_chr;
-                    unident_chr = next_chr;
+                    chr += 1;
+                    unident_chr = chr;
                     continue 'outer;
                 }
-            }
-            // Anything else is an unidentifiable character, which will be
-            // picked up by the `unident_chr != chr` conditional above.
-        }
 
-        // Step forward one byte.
-        chr += 1;
-    }
+                // If requested, try for a float like `.5` first, unless it
+                // follows something with fields, like the `x` of `x.0`.
+                let leading_dot: Option<Detector> =
+                    if self.options.allow_leading_dot_float
+                    && orig.as_bytes()[chr] == b'.' && unident_chr == chr
+                    && ! follows_operand(lexemes)
+                        { Some(detect_number_leading_dot) } else { None };
 
-    // If there are unidentifiable characters at the end of `orig`, add a final 
-    // `Unidentifiable` Lexeme before the end-of-input Lexeme.
-    if unident_chr != chr {
-        lexemes.push(Lexeme {
-            kind: LexemeKind::Unidentifiable,
-            chr: unident_chr,
-            snippet: &orig[unident_chr..chr],
-        });
-    }
+                // Step through the `detect_*()` functions, and their
+                // associated `LexemeKinds`.
+                for detector in leading_dot.iter().chain(self.detectors.iter()) {
 
-    // Add a special end-of-input Whitespace Lexeme. This simplifies parsing
-    // code which does not already end in whitespace.
-    lexemes.push(Lexeme {
-        kind: LexemeKind::WhitespaceTrimmable,
-        chr,
-        snippet: "<EOI>",
-    });
+                    // If `detector()` does not detect the Lexeme, it will return
+                    // the same char-position as `chr`. In that case, just return `chr`.
+                    // Identifiers depend on the edition, because words like
+                    // `async` were not Keywords before 2018.
+                    let (mut kind, next_chr) =
+                        if core::ptr::fn_addr_eq(*detector, detect_identifier as Detector)
+                            { detect_identifier_for(orig, chr, self.options.edition) }
+                        else { detector(orig, chr) };
+                    if kind != LexemeKind::Undetected {
 
-    // Create and return a result object.
-    LexemizeResult {
-        lexemes,
+                        // From Rust 2024, words like `foo` in `foo#bar` are
+                        // reserved prefixes, rather than Identifiers.
+                        if kind.is_identifier()
+                        && self.options.edition >= Edition::Rust2024
+                        && is_reserved_prefix(orig, chr, next_chr) {
+                            kind = LexemeKind::ReservedPrefix;
+                        }
+
+                        // If any ‘Unidentifiable’ characters precede this Lexeme,
+                        // record them before recording this Lexeme.
+                        if unident_chr != chr {
+                            lexemes.push(Lexeme {
+                                kind: LexemeKind::Unidentifiable,
+                                chr: unident_chr,
+                                snippet: &orig[unident_chr..chr],
+                            });
+                        }
+
+                        // If requested, split each lone carriage return out of
+                        // a run of Whitespace, as an ‘Unexpected’ Lexeme.
+                        if kind == LexemeKind::WhitespaceTrimmable
+                        && self.options.forbid_lone_cr {
+                            push_whitespace_forbidding_lone_cr(
+                                orig, chr, next_chr, lexemes);
+                        } else {
+                            lexemes.push(Lexeme {
+                                kind,
+                                chr,
+                                snippet: &orig[chr..next_chr],
+                            });
+                        }
+
+                        // Step forward to the position after this Lexeme.
+                        chr = next_chr;
+                        unident_chr = next_chr;
+                        continue 'outer;
+                    }
+                }
+                // Anything else is an unidentifiable character, which will be
+                // picked up by the `unident_chr != chr` conditional above.
+            }
+
+            // Step forward one byte.
+            chr += 1;
+        }
+
+        // If there are unidentifiable characters at the end of `orig`, add a final 
+        // `Unidentifiable` Lexeme, which the caller follows with `<EOI>`.
+        if unident_chr != chr {
+            lexemes.push(Lexeme {
+                kind: LexemeKind::Unidentifiable,
+                chr: unident_chr,
+                snippet: &orig[unident_chr..chr],
+            });
+        }
+
+        chr
     }
 }
 
-fn _detect(
+fn _detect<'a>(
     detector: fn (&str, usize) -> usize,
     kind: LexemeKind,
-    orig: &'static str,
+    orig: &'a str,
     chr: usize,
     unident_chr: usize,
-    lexemes: &mut Vec<Lexeme>,
+    lexemes: &mut Vec<Lexeme<'a>>,
 ) -> usize {
     // If the passed-in `detector()` does not detect the Lexeme, it will return
     // the same char-position as `chr`. In that case, just return `chr`.
@@ -167,11 +1496,99 @@ fn _detect(
     next_chr
 }
 
+// Returns `true` if the last Lexeme which is not trivia could be followed by a
+// field or tuple index, like an Identifier, a literal, or a closing bracket.
+fn follows_operand(lexemes: &[Lexeme]) -> bool {
+    match lexemes.iter().rev().find(|lexeme| ! lexeme.is_trivia()) {
+        Some(lexeme) => lexeme.kind.is_identifier() || lexeme.kind.is_number()
+            || lexeme.kind.is_string() || lexeme.kind.is_character()
+            || lexeme.kind == LexemeKind::Punctuation
+                && lexeme.snippet.ends_with([')', ']', '}', '?']),
+        None => false,
+    }
+}
+
+// Adds the `<EOI>` sentinel at `chr` to some detected Lexemes, then runs each
+// of the `REFINERS` over them.
+fn refine<'a>(
+    orig: &'a str,
+    mut lexemes: Vec<Lexeme<'a>>,
+    chr: usize,
+) -> Vec<Lexeme<'a>> {
+    // Add a special end-of-input Lexeme. This simplifies parsing code
+    // which does not already end in whitespace.
+    lexemes.push(Lexeme {
+        kind: LexemeKind::EndOfInput,
+        chr,
+        snippet: "",
+    });
+
+    run_refiners(orig, lexemes)
+}
+
+// Runs each of the `REFINERS` over some detected Lexemes.
+fn run_refiners<'a>(
+    orig: &'a str,
+    mut lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    // Step through the array of `tag_*()` functions, each of which can look at
+    // neighbouring Lexemes to refine their categories.
+    for refiner in REFINERS.iter() {
+        lexemes = refiner(orig, lexemes);
+    }
+    lexemes
+}
+
+// Pushes the Whitespace from `chr` to `end`, but with each `\r` which is not
+// followed by `\n` pushed as a separate ‘Unexpected’ Lexeme.
+fn push_whitespace_forbidding_lone_cr<'a>(
+    orig: &'a str,
+    chr: usize,
+    end: usize,
+    lexemes: &mut Vec<Lexeme<'a>>,
+) {
+    let mut start = chr;
+    let bytes = orig.as_bytes();
+    for i in chr..end {
+        if bytes[i] != b'\r' || bytes.get(i + 1) == Some(&b'\n') { continue }
+        if start != i {
+            lexemes.push(Lexeme {
+                kind: LexemeKind::WhitespaceTrimmable,
+                chr: start,
+                snippet: &orig[start..i],
+            });
+        }
+        lexemes.push(Lexeme {
+            kind: LexemeKind::Unexpected,
+            chr: i,
+            snippet: &orig[i..i+1],
+        });
+        start = i + 1;
+    }
+    if start != end {
+        lexemes.push(Lexeme {
+            kind: LexemeKind::WhitespaceTrimmable,
+            chr: start,
+            snippet: &orig[start..end],
+        });
+    }
+}
+
+// Returns `true` if the last Lexeme before the `<EOI>` sentinel ends with a
+// newline. Empty snippets, like the sentinel’s, are skipped.
+fn ends_with_newline(lexemes: &[Lexeme]) -> bool {
+    lexemes.iter().rev().find(|lexeme| ! lexeme.snippet.is_empty())
+        .is_some_and(|lexeme| lexeme.snippet.ends_with('\n'))
+}
+
 
 #[cfg(test)]
 mod tests {
-    use super::{LexemizeResult,lexemize};
-    use super::super::lexeme::{Lexeme,LexemeKind};
+    use super::{DisplayOptions,LexemizeOptions,LexemizeResult,Lexemizer};
+    use super::{detect_string,lexemize,lexemize_with_options,split_number_suffix};
+    use super::super::edition::Edition;
+    use super::super::lexeme::{DocPlacement,Lexeme,LexemeKind,OwnedLexeme,UnexpectedReason};
+    use super::super::test_helpers::{summary,summary_with};
 
     #[test]
     fn lexemize_result_to_string_as_expected() {
@@ -188,26 +1605,565 @@ mod tests {
                     snippet: "44.4",
                 },
                 Lexeme {
-                    kind: LexemeKind::WhitespaceTrimmable,
+                    kind: LexemeKind::EndOfInput,
                     chr: 27,
-                    snippet: "<EOI>",
+                    snippet: "",
                 },
             ],
+            reasons: vec![],
+            diagnostics: vec![],
+            ends_with_newline: false,
         };
         assert_eq!(result.to_string(),
             "Lexemes, incl <EOI>: 3\n\
              CommentMultiline        0  /* This is a comment */\n\
              NumberDecimal          23  44.4\n\
-             WhitespaceTrimmable    27  <EOI>\n"
+             EndOfInput             27  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_result_iter() {
+        let result = lexemize("a b");
+        // By reference, including the `<EOI>` sentinel.
+        let mut snippets = vec![];
+        for lexeme in &result { snippets.push(lexeme.snippet) }
+        assert_eq!(snippets, vec!["a", " ", "b", ""]);
+        assert_eq!(result.iter().count(), 4);
+        assert_eq!(result.iter().last().map(|l| l.kind), Some(LexemeKind::EndOfInput));
+        // Without the `<EOI>` sentinel.
+        let snippets: Vec<&str> = result.iter_no_eoi().map(|l| l.snippet).collect();
+        assert_eq!(snippets, vec!["a", " ", "b"]);
+        assert_eq!(lexemize("").iter_no_eoi().count(), 0);
+        // By value.
+        let kinds: Vec<LexemeKind> = result.into_iter().map(|l| l.kind).collect();
+        assert_eq!(kinds, vec![LexemeKind::IdentifierFreeword,
+            LexemeKind::WhitespaceTrimmable, LexemeKind::IdentifierFreeword,
+            LexemeKind::EndOfInput]);
+    }
+
+    #[test]
+    fn lexemize_result_significant_as_expected() {
+        let result = lexemize("let x /*c*/ = 1;\n");
+        let snippets: Vec<&str> = result.significant().map(|lexeme| lexeme.snippet).collect();
+        assert_eq!(snippets, vec!["let", "x", "=", "1", ";"]);
+        // Doc comments and compacted Trivia are also skipped.
+        let options = LexemizeOptions {
+            compact_trivia: true,
+            ..LexemizeOptions::default()
+        };
+        let result = lexemize_with_options("/// doc\nfn  f() {}", options);
+        let snippets: Vec<&str> = result.significant().map(|lexeme| lexeme.snippet).collect();
+        assert_eq!(snippets, vec!["fn", "f", "(", ")", "{", "}"]);
+        // Only trivia, or nothing at all.
+        assert_eq!(lexemize(" // c\n").significant().count(), 0);
+        assert_eq!(lexemize("").significant().count(), 0);
+        // Without the `<EOI>` sentinel, the last Lexeme is not dropped.
+        let mut result = lexemize("x = 1");
+        result.lexemes.pop();
+        assert_eq!(result.significant().last().map(|lexeme| lexeme.snippet), Some("1"));
+        result.lexemes.clear();
+        assert_eq!(result.significant().count(), 0);
+    }
+
+    #[test]
+    fn lexemize_result_doc_comments_as_expected() {
+        let orig = "//! Module.\n\n/// Item.\nfn f() {} // not a doc\n\
+                    /** Block. */\nstruct S; /* nor this */\n";
+        let result = lexemize(orig);
+        let docs: Vec<(usize, String)> = result.doc_comments().iter()
+            .map(|lexeme| (lexeme.chr, lexeme.doc_text().unwrap().into_owned()))
+            .collect();
+        assert_eq!(docs, vec![
+            (0, " Module.".into()),
+            (13, " Item.".into()),
+            (46, " Block. ".into()),
+        ]);
+        // No doc comments, or nothing at all.
+        assert!(lexemize("// a\n/* b */ /**/ ////c").doc_comments().is_empty());
+        assert!(lexemize("").doc_comments().is_empty());
+    }
+
+    #[test]
+    fn lexemize_result_with_attached_trivia_as_expected() {
+        // Returns the snippets of each Lexeme and its trivia.
+        fn snippets(orig: &'static str) -> Vec<(&'static str, Vec<&'static str>)> {
+            lexemize(orig).with_attached_trivia().into_iter()
+                .map(|(lexeme, trivia)| (lexeme.snippet,
+                    trivia.iter().map(|lexeme| lexeme.snippet).collect()))
+                .collect()
+        }
+        // The comment and whitespace are attached to `x`.
+        assert_eq!(snippets("  // c\n x"), vec![
+            ("x", vec!["  ", "// c", "\n "]),
+            ("", vec![]),
+        ]);
+        // Trailing trivia is attached to the `<EOI>` sentinel.
+        assert_eq!(snippets("a /** d */b /* e */\n"), vec![
+            ("a", vec![]),
+            ("b", vec![" ", "/** d */"]),
+            ("", vec![" ", "/* e */", "\n"]),
+        ]);
+        // Only trivia, or nothing at all.
+        assert_eq!(snippets(" "), vec![("", vec![" "])]);
+        assert_eq!(snippets(""), vec![("", vec![])]);
+    }
+
+    #[test]
+    fn lexemize_result_counts_as_expected() {
+        // One of each basic Lexeme, from `lexemize_all_lexemes()`.
+        let result = lexemize("'A'/*B*/C 1!\"D\"\n");
+        let counts: Vec<(LexemeKind, usize)> = result.counts().into_iter().collect();
+        assert_eq!(counts, vec![
+            (LexemeKind::CharacterPlain, 1),
+            (LexemeKind::CommentMultiline, 1),
+            (LexemeKind::IdentifierFreeword, 1),
+            (LexemeKind::NumberDecimal, 1),
+            (LexemeKind::Punctuation, 1),
+            (LexemeKind::StringPlain, 1),
+            (LexemeKind::WhitespaceTrimmable, 2), // not including <EOI>
+        ]);
+        assert_eq!(result.count_group(LexemeKind::is_comment), 1);
+        assert_eq!(result.count_group(LexemeKind::is_whitespace), 2);
+        assert_eq!(result.count_group(LexemeKind::is_other), 0);
+        // A "Hello, World!" one-liner.
+        let result = lexemize("println!(\"Hello, World!\");\n");
+        assert_eq!(result.counts().get(&LexemeKind::Punctuation), Some(&4));
+        assert_eq!(result.counts().get(&LexemeKind::IdentifierMacro), Some(&1));
+        assert_eq!(result.counts().get(&LexemeKind::NumberDecimal), None);
+        assert_eq!(result.count_group(LexemeKind::is_identifier), 1);
+        // Empty input, which only has the <EOI> sentinel.
+        assert!(lexemize("").counts().is_empty());
+        assert_eq!(lexemize("").count_group(LexemeKind::is_whitespace), 0);
+    }
+
+    #[test]
+    fn lexemize_result_format_with_as_expected() {
+        let result = lexemize("let a =\n  1;");
+        // The default options give the same result as `to_string()`.
+        assert_eq!(result.format_with(DisplayOptions::default()),
+            result.to_string());
+        // A custom width and newline placeholder.
+        let opts = DisplayOptions {
+            kind_width: 22,
+            newline_placeholder: "\\n",
+            ..DisplayOptions::default()
+        };
+        assert_eq!(result.format_with(opts),
+            "Lexemes, incl <EOI>: 9\n\
+             IdentifierKeyword         0  let\n\
+             WhitespaceTrimmable       3   \n\
+             IdentifierFreeword        4  a\n\
+             WhitespaceTrimmable       5   \n\
+             Punctuation               6  =\n\
+             WhitespaceTrimmable       7  \\n  \n\
+             NumberDecimal            10  1\n\
+             Punctuation              11  ;\n\
+             EndOfInput               12  <EOI>\n"
+        );
+        // Offsets disabled.
+        let opts = DisplayOptions { show_offset: false, ..DisplayOptions::default() };
+        assert_eq!(result.format_with(opts),
+            "Lexemes, incl <EOI>: 9\n\
+             IdentifierKeyword     let\n\
+             WhitespaceTrimmable    \n\
+             IdentifierFreeword    a\n\
+             WhitespaceTrimmable    \n\
+             Punctuation           =\n\
+             WhitespaceTrimmable   <NL>  \n\
+             NumberDecimal         1\n\
+             Punctuation           ;\n\
+             EndOfInput            <EOI>\n"
+        );
+        // Line and column instead of the byte offset.
+        let opts = DisplayOptions { line_col: true, ..DisplayOptions::default() };
+        assert_eq!(result.format_with(opts),
+            "Lexemes, incl <EOI>: 9\n\
+             IdentifierKeyword     1:1  let\n\
+             WhitespaceTrimmable   1:4   \n\
+             IdentifierFreeword    1:5  a\n\
+             WhitespaceTrimmable   1:6   \n\
+             Punctuation           1:7  =\n\
+             WhitespaceTrimmable   1:8  <NL>  \n\
+             NumberDecimal         2:3  1\n\
+             Punctuation           2:4  ;\n\
+             EndOfInput            2:5  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_result_tokens_on_line() {
+        let result = lexemize("let a = 1;\n/* one\ntwo\nthree */ b\n\nc // d\r\ne");
+        let on_line = |line| result.tokens_on_line(line).iter()
+            .map(|lexeme| lexeme.snippet).collect::<Vec<_>>();
+        assert_eq!(on_line(1), vec!["let", " ", "a", " ", "=", " ", "1", ";", "\n"]);
+        // The multiline comment spans lines 2 to 4.
+        assert_eq!(on_line(2), vec!["/* one\ntwo\nthree */"]);
+        assert_eq!(on_line(3), vec!["/* one\ntwo\nthree */"]);
+        assert_eq!(on_line(4), vec!["/* one\ntwo\nthree */", " ", "b", "\n\n"]);
+        // A blank line is just the Whitespace which spans it.
+        assert_eq!(on_line(5), vec!["\n\n"]);
+        // The `\r` of `\r\n` ends the comment, and the `\n` is Whitespace.
+        assert_eq!(on_line(6), vec!["c", " ", "// d\r", "\n"]);
+        assert_eq!(on_line(7), vec!["e"]);
+        // Out of range, and the `<EOI>` sentinel is never found.
+        assert_eq!(on_line(0), Vec::<&str>::new());
+        assert_eq!(on_line(8), Vec::<&str>::new());
+        assert_eq!(lexemize("").tokens_on_line(1), Vec::<&Lexeme>::new());
+    }
+
+    #[test]
+    fn lexemize_result_line_cols_as_expected() {
+        // Unix, Windows and old Mac line endings.
+        assert_eq!(lexemize("a\nb").line_cols(),   vec![(1,1), (1,2), (2,1), (2,2)]);
+        assert_eq!(lexemize("a\r\nb").line_cols(), vec![(1,1), (1,2), (2,1), (2,2)]);
+        assert_eq!(lexemize("a\rb").line_cols(),   vec![(1,1), (1,2), (2,1), (2,2)]);
+        // Two Windows line endings, and "\r\r" which is two line breaks.
+        assert_eq!(lexemize("a\r\n\r\nb").line_cols()[2], (3,1));
+        assert_eq!(lexemize("a\r\rb").line_cols()[2],     (3,1));
+        // "\r" and "\n" in different Lexemes.
+        assert_eq!(lexemize("//a\r\nb").line_cols(), vec![(1,1), (1,5), (2,1), (2,2)]);
+        // Columns count chars, not bytes.
+        assert_eq!(lexemize("€ b").line_cols()[2], (1,3));
+    }
+
+    #[test]
+    fn lexemize_result_to_ndjson_as_expected() {
+        // A string containing an escaped double quote, then a newline.
+        assert_eq!(lexemize("\"a\\\"b\"\n").to_ndjson(),
+            "{\"kind\":\"StringPlain\",\"chr\":0,\"snippet\":\"\\\"a\\\\\\\"b\\\"\"}\n\
+             {\"kind\":\"WhitespaceTrimmable\",\"chr\":6,\"snippet\":\"\\n\"}\n\
+             {\"kind\":\"EndOfInput\",\"chr\":7,\"snippet\":\"\"}\n"
+        );
+        // Tabs, carriage returns and other control characters.
+        assert_eq!(lexemize("\t\r\u{000B}").to_ndjson(),
+            "{\"kind\":\"WhitespaceTrimmable\",\"chr\":0,\"snippet\":\"\\t\\r\\u000b\"}\n\
+             {\"kind\":\"EndOfInput\",\"chr\":3,\"snippet\":\"\"}\n"
+        );
+        // Non-ascii is left as it is.
+        assert_eq!(lexemize("€").to_ndjson(),
+            "{\"kind\":\"Unidentifiable\",\"chr\":0,\"snippet\":\"€\"}\n\
+             {\"kind\":\"EndOfInput\",\"chr\":3,\"snippet\":\"\"}\n"
         );
     }
 
+    #[test]
+    fn lexemize_result_to_source_as_expected() {
+        let orig = "let a = 1; // one\n/* €€ */\r\n";
+        assert_eq!(lexemize(orig).to_source(), orig);
+        assert_eq!(lexemize("").to_source(), "");
+        let options = LexemizeOptions {
+            compact_trivia: true,
+            ..LexemizeOptions::default()
+        };
+        assert_eq!(lexemize_with_options(orig, options).to_source(), orig);
+    }
+
+    #[test]
+    fn lexemize_result_ends_with_newline() {
+        assert!(lexemize("abc\n").ends_with_newline);
+        assert!(lexemize("abc\r\n").ends_with_newline);
+        assert!(lexemize("\n").ends_with_newline);
+        assert!(lexemize("x // end\n").ends_with_newline); // whitespace after comment
+        assert!(! lexemize("abc ").ends_with_newline);
+        assert!(! lexemize("abc").ends_with_newline);
+        assert!(! lexemize("abc\n ").ends_with_newline);
+        assert!(! lexemize("x /* \n */").ends_with_newline);
+        assert!(! lexemize("").ends_with_newline);
+        // The same with compact Trivia, and after relexing.
+        let options = LexemizeOptions { compact_trivia: true, ..LexemizeOptions::default() };
+        assert!(lexemize_with_options("a /* b */\n", options).ends_with_newline);
+        assert!(lexemize("abc").relex("abc\n", 3..3, 1).ends_with_newline);
+        assert!(! lexemize("abc\n").relex("abc", 3..4, 0).ends_with_newline);
+    }
+
+    #[test]
+    fn lexemize_result_attributes_as_expected() {
+        // Returns the text of each attribute.
+        let attributes = |orig: &'static str| lexemize(orig).attributes().into_iter()
+            .map(|range| &orig[range]).collect::<Vec<_>>();
+        assert_eq!(attributes("#[derive(Debug, Clone)]\nstruct A;"),
+            vec!["#[derive(Debug, Clone)]"]);
+        assert_eq!(attributes("#![feature(foo)]\n#![no_std]"),
+            vec!["#![feature(foo)]", "#![no_std]"]);
+        assert_eq!(attributes("#[a(b[c])] fn f() { x[[1]]; }"), vec!["#[a(b[c])]"]);
+        assert_eq!(lexemize("x; #[a(b[c])]").attributes(), vec![3..13]);
+        // Brackets in strings and comments do not count.
+        assert_eq!(attributes("#[doc = \"]\"] /* [ */ #[b]"), vec!["#[doc = \"]\"]", "#[b]"]);
+        // Spread over several lines.
+        assert_eq!(attributes("#[cfg(all(\n    a,\n    b,\n))]"),
+            vec!["#[cfg(all(\n    a,\n    b,\n))]"]);
+        // Not attributes, or not closed.
+        assert_eq!(attributes("# [a] x[0] #"), Vec::<&str>::new());
+        assert_eq!(attributes("#[a[b] c"), Vec::<&str>::new());
+        assert_eq!(attributes("#[a[b] #[c]"), vec!["#[c]"]); // only the second closes
+        assert_eq!(attributes(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn lexemize_result_collapse_trivia_as_expected() {
+        use LexemeKind::{EndOfInput as E,IdentifierFreeword as F,Trivia as T};
+        // A run of Whitespace and a Comment becomes one Trivia Lexeme.
+        let orig = " // c\n ";
+        let result = lexemize(orig);
+        assert_eq!(result.lexemes.len(), 4);
+        let collapsed = result.collapse_trivia(orig);
+        assert_eq!(summary(collapsed.lexemes.clone()), vec![(T,0," // c\n "), (E,7,"")]);
+        assert_eq!(collapsed.to_source(), orig);
+        // Significant Lexemes are untouched.
+        let orig = "a /* b */ /// c\n\tz";
+        let collapsed = lexemize(orig).collapse_trivia(orig);
+        assert_eq!(summary(collapsed.lexemes.clone()),
+            vec![(F,0,"a"), (T,1," /* b */ /// c\n\t"), (F,17,"z"), (E,18,"")]);
+        assert_eq!(collapsed.to_source(), orig);
+        // The same as the `compact_trivia` option.
+        let options = LexemizeOptions { compact_trivia: true, ..LexemizeOptions::default() };
+        assert_eq!(collapsed.lexemes, lexemize_with_options(orig, options).lexemes);
+        // Diagnostics are kept.
+        let orig = " \"abc";
+        let collapsed = lexemize(orig).collapse_trivia(orig);
+        assert_eq!(collapsed.diagnostics, lexemize(orig).diagnostics);
+        assert_eq!(summary(lexemize("").collapse_trivia("").lexemes), vec![(E,0,"")]);
+    }
+
+    #[test]
+    fn lexemize_result_rewrite() {
+        let orig = "let  a =\t0xAB_cd; // 0xFF\nlet b = \"0xEE\" as u8 + 0XFF;\n";
+        let result = lexemize(orig);
+        // Lowercase every hex number, leaving comments and strings alone.
+        let lower_hex = result.rewrite(|l| (l.kind == LexemeKind::NumberHex)
+            .then(|| l.snippet.to_lowercase()));
+        assert_eq!(lower_hex,
+            "let  a =\t0xab_cd; // 0xFF\nlet b = \"0xEE\" as u8 + 0XFF;\n");
+        // Normalise Whitespace within each line to a single space.
+        let normalised = result.rewrite(|l| (l.kind.is_whitespace()
+            && ! l.snippet.contains('\n')).then(|| " ".into()));
+        assert_eq!(normalised,
+            "let a = 0xAB_cd; // 0xFF\nlet b = \"0xEE\" as u8 + 0XFF;\n");
+        // Nothing replaced is the original source, and `<EOI>` is never seen.
+        assert_eq!(result.rewrite(|_| None), orig);
+        assert_eq!(lexemize("").rewrite(|_| Some("x".into())), "");
+    }
+
+    #[test]
+    fn lexemize_result_joined_as_expected() {
+        let result = lexemize("/// Doc.\nfn a() {} // End.\n/* B */ let b = 2;\n");
+        // Comment-stripped source.
+        assert_eq!(result.joined(|l| ! l.kind.is_comment()),
+            "\nfn a() {} \n let b = 2;\n");
+        // Comments only.
+        assert_eq!(result.joined(|l| l.kind.is_comment()),
+            "/// Doc.// End./* B */");
+        // Significant Lexemes only.
+        assert_eq!(result.joined(|l| ! l.is_trivia()), "fna(){}letb=2;");
+        // Nothing matches, or nothing to match.
+        assert_eq!(result.joined(|_| false), "");
+        assert_eq!(lexemize("").joined(|_| true), "");
+    }
+
+    #[test]
+    fn lexemize_result_to_owned_lexemes_as_expected() {
+        let owned = lexemize("a+1").to_owned_lexemes();
+        let snippets: Vec<&str> = owned.iter().map(|l| l.snippet.as_str()).collect();
+        assert_eq!(snippets, vec!["a", "+", "1", ""]);
+        assert_eq!(owned[3], OwnedLexeme {
+            kind: LexemeKind::EndOfInput,
+            chr: 3,
+            snippet: "".into(),
+        });
+    }
+
+    #[test]
+    fn lexemize_bytes_as_expected() {
+        use super::lexemize_bytes;
+        let mut buf = String::new();
+        // Valid UTF-8 gives the same result as `lexemize()`, without copying.
+        let orig = "let s = \"€\"; // euro\n";
+        let result = lexemize_bytes(orig.as_bytes(), &mut buf);
+        assert_eq!(result.lexemes, lexemize(orig).lexemes);
+        assert_eq!(lexemize_bytes(b"", &mut buf).lexemes, lexemize("").lexemes);
+        assert_eq!(buf, "");
+        // An invalid byte is Unidentifiable, and the Lexemes around it are
+        // still found.
+        let result = lexemize_bytes(b"let x\xFF= 1;", &mut buf);
+        assert_eq!(result.to_string(),
+            "Lexemes, incl <EOI>: 9\n\
+             IdentifierKeyword       0  let\n\
+             WhitespaceTrimmable     3   \n\
+             IdentifierFreeword      4  x\n\
+             Unidentifiable          5  \u{1A}\n\
+             Punctuation             6  =\n\
+             WhitespaceTrimmable     7   \n\
+             NumberDecimal           8  1\n\
+             Punctuation             9  ;\n\
+             EndOfInput             10  <EOI>\n"
+        );
+        assert_eq!(result.diagnostics, vec![super::Diagnostic {
+            chr: 5, len: 1, message: "invalid UTF-8".into() }]);
+        assert_eq!(result.to_source(), "let x\u{1A}= 1;");
+        // A string which contains an invalid byte is still one Lexeme, and an
+        // invalid sequence is never merged with other Unidentifiable chars.
+        let orig: &[u8] = b"\"a\xFFb\" \xA7\xFE\xFF\xC2\xA7\xE2\x82";
+        let result = lexemize_bytes(orig, &mut buf);
+        let spans: Vec<(LexemeKind, usize, usize)> = result.lexemes.iter()
+            .map(|l| (l.kind, l.chr, l.snippet.len())).collect();
+        assert_eq!(spans, vec![(LexemeKind::StringPlain, 0, 5),
+            (LexemeKind::WhitespaceTrimmable, 5, 1), (LexemeKind::Unidentifiable, 6, 1),
+            (LexemeKind::Unidentifiable, 7, 1), (LexemeKind::Unidentifiable, 8, 1),
+            (LexemeKind::Unidentifiable, 9, 2), (LexemeKind::Unidentifiable, 11, 2),
+            (LexemeKind::EndOfInput, 13, 0)]); // `\xE2\x82` is cut short
+        let spans: Vec<(usize, usize, &str)> = result.diagnostics.iter()
+            .map(|d| (d.chr, d.len, d.message.as_str())).collect();
+        assert_eq!(spans, vec![(2, 1, "invalid UTF-8"), (6, 1, "invalid UTF-8"),
+            (7, 1, "invalid UTF-8"), (8, 1, "invalid UTF-8"),
+            (9, 2, "unidentifiable character"), (11, 2, "invalid UTF-8")]);
+        // An unterminated string is one Unexpected Lexeme, whatever it holds.
+        let result = lexemize_bytes(b"x\"\xFE", &mut buf);
+        assert_eq!(result.to_string(),
+            "Lexemes, incl <EOI>: 3\n\
+             IdentifierFreeword      0  x\n\
+             Unexpected              1  \"\u{1A}\n\
+             EndOfInput              3  <EOI>\n"
+        );
+        assert_eq!(result.diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn lexemize_bytes_spans_are_contiguous() {
+        use super::lexemize_bytes;
+        let mut buf = String::new();
+        let origs: [&[u8]; 6] = [b"ab\xFFcd", b"x\"\xFE", b"\xF0\x9F\x98", b"\xFF\xFE ",
+            b"// \xC0\xAF\nfn f() {}", b"'\xFF' \xC2\xA7\xFF\xE2\x82\xAC"];
+        for orig in origs {
+            let result = lexemize_bytes(orig, &mut buf);
+            let mut chr = 0;
+            for lexeme in &result {
+                assert_eq!(lexeme.chr, chr, "{:?}", orig);
+                chr = lexeme.end();
+            }
+            assert_eq!(chr, orig.len(), "{:?}", orig);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn lexemize_reader_as_expected() {
+        use std::io::{Cursor,ErrorKind};
+        let code: &[u8] = b"let s = \"\xE2\x82\xAC\"; // euro\n";
+        let mut buf = String::new();
+        let result = super::lexemize_reader(Cursor::new(code), &mut buf).unwrap();
+        assert_eq!(result.lexemes, lexemize("let s = \"€\"; // euro\n").lexemes);
+        assert_eq!(result.lexemes[6].snippet, "\"€\"");
+        assert!(result.ends_with_newline);
+        // The diagnostics are kept, and `buf` is cleared before reading.
+        let result = super::lexemize_reader(Cursor::new(&b"x \"a"[..]), &mut buf).unwrap();
+        assert_eq!(result.to_source(), "x \"a");
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].chr, 2);
+        assert!(! result.ends_with_newline);
+        // Empty input still gives the `<EOI>` sentinel.
+        let result = super::lexemize_reader(Cursor::new(&b""[..]), &mut buf).unwrap();
+        assert_eq!(result.lexemes.len(), 1);
+        // Invalid UTF-8 is an error.
+        let err = super::lexemize_reader(Cursor::new(&b"x\xFF"[..]), &mut buf).err();
+        assert_eq!(err.map(|err| err.kind()), Some(ErrorKind::InvalidData));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn lexemize_parallel_as_expected() {
+        use super::{find_safe_split,lexemize_parallel};
+        // Many of the safe-looking newlines are inside strings and comments,
+        // so some chunks must start part way through a Lexeme.
+        let part = "fn f() {}\nlet s = \"a;\nb\";\n/* c;\nd } */\nlet r = r#\"\n\
+            e;\nf\"#; // g;\n'h';\n€€;\nx.0.1e2;\n";
+        let orig = part.repeat(5_000);
+        let serial = lexemize(&orig);
+        for threads in [2, 3, 4, 6] {
+            let parallel = lexemize_parallel(&orig, threads);
+            assert_eq!(parallel.lexemes, serial.lexemes, "{} threads", threads);
+            assert_eq!(parallel.diagnostics, serial.diagnostics);
+        }
+        // Serial, for one thread or a small input.
+        assert_eq!(lexemize_parallel(&orig, 1).lexemes, serial.lexemes);
+        assert_eq!(lexemize_parallel(&orig, 0).lexemes, serial.lexemes);
+        assert_eq!(lexemize_parallel(part, 4).lexemes, lexemize(part).lexemes);
+        assert_eq!(lexemize_parallel("", 4).lexemes, lexemize("").lexemes);
+        // Safe-looking splits.
+        assert_eq!(find_safe_split("a;\nb", 0), Some(3));
+        assert_eq!(find_safe_split("a}\nb", 0), Some(3));
+        assert_eq!(find_safe_split("a;\n b;\nc", 0), Some(7)); // not indented
+        assert_eq!(find_safe_split("a,\nb", 0), None); // not after ; or }
+        assert_eq!(find_safe_split("a;\n", 0), None); // nothing after
+        assert_eq!(find_safe_split("a;\nb;\nc", 3), Some(6)); // from 3
+    }
+
+    #[test]
+    fn lexemize_each_as_expected() {
+        // Each is streamed in several chunks, and must exactly match the
+        // Lexemes which `lexemize()` collects.
+        for orig in [
+            "macro_rules! m { ($($x:expr),*) => {} }\n'a: loop { x.0.1; }",
+            "macro_rules!\nm\n;\n{ ($x:ident) => { $x; }; }; m!(y);",
+            "fn f<'a>(x: &'a u8) {} union U { a: u8 } let union = 1; union;",
+            "match x { _ => (1, 2) }.0; {}.1; let y = match x {}\n/2;",
+            "#[derive(Debug)] struct S; #![allow(x)] 'b: { break 'b; } - 1",
+            "fn main() { x; } impl<T> for<'a> Tr<'a> for T {} \"unterminated",
+            "let x = a } b; ) c ] d; r#union u {} 1.0.x",
+        ] {
+            let mut found = vec![];
+            super::lexemize_each(orig, |lexeme| found.push(lexeme));
+            assert_eq!(found, lexemize(orig).lexemes, "{}", orig);
+            assert_eq!(found.last().map(|l| l.kind), Some(LexemeKind::EndOfInput));
+        }
+        // Empty input still gives the `<EOI>` sentinel, and nothing else.
+        let mut count = 0;
+        super::lexemize_each("", |lexeme| {
+            assert_eq!(lexeme.kind, LexemeKind::EndOfInput);
+            count += 1;
+        });
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn lexemize_result_lexeme_at_as_expected() {
+        let result = lexemize("x = \"€€\";");
+        let kind_at = |chr| result.lexeme_at(chr).map(|lexeme| (lexeme.kind, lexeme.chr));
+        assert_eq!(kind_at(0),  Some((LexemeKind::IdentifierFreeword, 0))); // x
+        assert_eq!(kind_at(1),  Some((LexemeKind::WhitespaceTrimmable, 1))); // space
+        assert_eq!(kind_at(2),  Some((LexemeKind::Punctuation, 2))); // =
+        assert_eq!(kind_at(4),  Some((LexemeKind::StringPlain, 4))); // opening "
+        assert_eq!(kind_at(5),  Some((LexemeKind::StringPlain, 4))); // start of €
+        assert_eq!(kind_at(6),  Some((LexemeKind::StringPlain, 4))); // inside €
+        assert_eq!(kind_at(11), Some((LexemeKind::StringPlain, 4))); // closing "
+        assert_eq!(kind_at(12), Some((LexemeKind::Punctuation, 12))); // ;
+        // The <EOI> sentinel is only found exactly at the end of input.
+        assert_eq!(kind_at(13), Some((LexemeKind::EndOfInput, 13)));
+        assert_eq!(kind_at(14), None);
+        assert_eq!(kind_at(100), None);
+        // Empty input only has the <EOI> sentinel.
+        assert_eq!(lexemize("").lexeme_at(0).map(|lexeme| lexeme.kind), Some(LexemeKind::EndOfInput));
+        assert_eq!(lexemize("").lexeme_at(1), None);
+    }
+
+    #[test]
+    fn lexemize_lexeme_ranges_are_contiguous() {
+        let orig = "fn main() { let s = \"€\"; } // done\n";
+        let result = lexemize(orig);
+        for pair in result.lexemes.windows(2) {
+            assert_eq!(pair[0].end(), pair[1].chr, "{:?}", pair);
+            assert_eq!(&orig[pair[0].range()], pair[0].snippet);
+        }
+        let eoi = result.lexemes.last().unwrap();
+        assert_eq!(eoi.range(), orig.len()..orig.len());
+    }
+
     #[test]
     fn lexemize_all_lexemes() {
         // Empty string.
         assert_eq!(lexemize("").to_string(),
             "Lexemes, incl <EOI>: 1\n\
-             WhitespaceTrimmable     0  <EOI>\n");
+             EndOfInput              0  <EOI>\n");
         // One of each basic Lexeme.
         assert_eq!(lexemize("'A'/*B*/C 1!\"D\"\n").to_string(),
             "Lexemes, incl <EOI>: 9\n\
@@ -219,7 +2175,7 @@ mod tests {
              Punctuation            11  !\n\
              StringPlain            12  \"D\"\n\
              WhitespaceTrimmable    15  <NL>\n\
-             WhitespaceTrimmable    16  <EOI>\n");
+             EndOfInput             16  <EOI>\n");
         // One of each basic Lexeme, with non-ascii.
         assert_eq!(lexemize("'€'/*€*/€1!\"€\"\n").to_string(),
             "Lexemes, incl <EOI>: 8\n\
@@ -230,31 +2186,286 @@ mod tests {
              Punctuation            16  !\n\
              StringPlain            17  \"€\"\n\
              WhitespaceTrimmable    22  <NL>\n\
-             WhitespaceTrimmable    23  <EOI>\n");
+             EndOfInput             23  <EOI>\n");
         // A simple "Hello, World!" one-liner.
         assert_eq!(lexemize("println!(\"Hello, World!\");\n").to_string(),
             "Lexemes, incl <EOI>: 8\n\
-             IdentifierFreeword      0  println\n\
+             IdentifierMacro         0  println\n\
              Punctuation             7  !\n\
              Punctuation             8  (\n\
              StringPlain             9  \"Hello, World!\"\n\
              Punctuation            24  )\n\
              Punctuation            25  ;\n\
              WhitespaceTrimmable    26  <NL>\n\
-             WhitespaceTrimmable    27  <EOI>\n");
+             EndOfInput             27  <EOI>\n");
+    }
+
+    #[test]
+    fn lexemize_attributes() {
+        // Outer and inner attributes.
+        assert_eq!(lexemize("#[derive]#![feature]").to_string(),
+            "Lexemes, incl <EOI>: 9\n\
+             AttributeStart          0  #\n\
+             Punctuation             1  [\n\
+             IdentifierFreeword      2  derive\n\
+             Punctuation             8  ]\n\
+             AttributeStart          9  #!\n\
+             Punctuation            11  [\n\
+             IdentifierFreeword     12  feature\n\
+             Punctuation            19  ]\n\
+             EndOfInput             20  <EOI>\n"
+        );
+        // A bare `#` followed by whitespace stays Punctuation.
+        assert_eq!(lexemize("# x").to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             Punctuation             0  #\n\
+             WhitespaceTrimmable     1   \n\
+             IdentifierFreeword      2  x\n\
+             EndOfInput              3  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_characters() {
+        // Three Characters.
+        assert_eq!(lexemize("'Z''\\t''\\x3F''\\u{3F}'").to_string(),
+            "Lexemes, incl <EOI>: 5\n\
+             CharacterPlain          0  \'Z\'\n\
+             CharacterPlain          3  \'\\t\'\n\
+             CharacterHex            7  \'\\x3F\'\n\
+             CharacterUnicode       13  \'\\u{3F}\'\n\
+             EndOfInput             21  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_checked() {
+        let options = LexemizeOptions {
+            checked: true,
+            ..LexemizeOptions::default()
+        };
+        // `0.1` is inexact, but `0.5` is exact.
+        let result = lexemize_with_options("0.1+0.5", options);
+        assert_eq!(result.to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             Unexpected              0  0.1\n\
+             Punctuation             3  +\n\
+             NumberDecimalFloat      4  0.5\n\
+             EndOfInput              7  <EOI>\n"
+        );
+        assert_eq!(result.reasons, vec![(0, UnexpectedReason::InexactFloat)]);
+        // `'fn` is flagged, but `'static`, `'_` and `'a` are fine.
+        let result = lexemize_with_options("&'fn &'static &'_ &'a", options);
+        assert_eq!(result.to_string(),
+            "Lexemes, incl <EOI>: 12\n\
+             Punctuation             0  &\n\
+             Unexpected              1  'fn\n\
+             WhitespaceTrimmable     4   \n\
+             Punctuation             5  &\n\
+             IdentifierLifetime      6  'static\n\
+             WhitespaceTrimmable    13   \n\
+             Punctuation            14  &\n\
+             IdentifierLifetime     15  '_\n\
+             WhitespaceTrimmable    17   \n\
+             Punctuation            18  &\n\
+             IdentifierLifetime     19  'a\n\
+             EndOfInput             21  <EOI>\n"
+        );
+        assert_eq!(result.reasons, vec![(1, UnexpectedReason::KeywordLifetime)]);
+        // Not checked by default.
+        assert_eq!(lexemize("0.1").reasons, vec![]);
+    }
+
+    #[test]
+    fn lexemize_result_suspicious_number_prefixes() {
+        assert_eq!(lexemize("0B11").suspicious_number_prefixes(), vec![0]);
+        assert_eq!(lexemize("x = 0XAB;").suspicious_number_prefixes(), vec![4]);
+        assert_eq!(lexemize("0O17 + 0O7").suspicious_number_prefixes(), vec![0,7]);
+        // Correct lowercase prefixes.
+        assert_eq!(lexemize("0b11 0o17 0xAB").suspicious_number_prefixes(), vec![]);
+        // Not a valid digit for the base, so probably not a typo.
+        assert_eq!(lexemize("0B2 0O8 0Xg 0X").suspicious_number_prefixes(), vec![]);
+        // Not just a `0`, or not immediately followed by the letter.
+        assert_eq!(lexemize("10B1 0 B1 0E1").suspicious_number_prefixes(), vec![]);
+        assert_eq!(lexemize("").suspicious_number_prefixes(), vec![]);
+    }
+
+    #[test]
+    fn lexemize_result_unbalanced_delimiters() {
+        let unbalanced = |orig: &'static str| lexemize(orig).unbalanced_delimiters();
+        // Balanced.
+        assert_eq!(unbalanced("([{}])"), vec![]);
+        assert_eq!(unbalanced("fn f() { g([1, 2]); }"), vec![]);
+        // Mismatched, unopened and unclosed.
+        assert_eq!(unbalanced("(]"), vec![(0,'('), (1,']')]);
+        assert_eq!(unbalanced("([)]"), vec![(0,'('), (2,')')]);
+        assert_eq!(unbalanced(")"), vec![(0,')')]);
+        assert_eq!(unbalanced("{{"), vec![(0,'{'), (1,'{')]);
+        assert_eq!(unbalanced("a) (b"), vec![(1,')'), (3,'(')]);
+        // Delimiters in strings, chars and comments are ignored.
+        assert_eq!(unbalanced("f(\")\", ']', /* } */)"), vec![]);
+        assert_eq!(unbalanced(""), vec![]);
+    }
+
+    #[test]
+    fn lexemize_result_empty_groups() {
+        let empty = |orig: &'static str| lexemize(orig).empty_groups();
+        assert_eq!(empty("()"), vec![0]);
+        assert_eq!(empty("[]"), vec![0]);
+        assert_eq!(empty("{}"), vec![0]);
+        // Trivia is ignored.
+        assert_eq!(empty("( )"), vec![0]);
+        assert_eq!(empty("{ /* none */\n}"), vec![0]);
+        // Nested, where only the inner group is empty.
+        assert_eq!(empty("({})"), vec![1]);
+        assert_eq!(empty("fn f() -> () { [(); 0] }"), vec![4, 10, 16]);
+        // Not empty, or not a matching pair.
+        assert_eq!(empty("(x) (]"), vec![]);
+        assert_eq!(empty(")("), vec![]);
+        assert_eq!(empty("\"()\" '{' /* [] */"), vec![]);
+        assert_eq!(empty(""), vec![]);
+    }
+
+    #[test]
+    fn lexemize_result_separator_positions() {
+        let trailing = |orig: &'static str| lexemize(orig).separator_positions();
+        assert_eq!(trailing("[1, 2,]"), vec![5]);
+        assert_eq!(trailing("[1, 2]"), vec![]);
+        assert_eq!(trailing("foo(a,)"), vec![5]);
+        // Semicolons, and separators at the end of input.
+        assert_eq!(trailing("{ a; b; }"), vec![6]);
+        assert_eq!(trailing("a;"), vec![1]);
+        assert_eq!(trailing("a, // c\n"), vec![1]);
+        // Trivia is ignored.
+        assert_eq!(trailing("(a, /* b */\n)"), vec![2]);
+        // Nested groups, each with its own trailing comma.
+        assert_eq!(trailing("f([1,], {x,},)"), vec![4, 10, 12]);
+        // A closer which does not match its opener.
+        assert_eq!(trailing("(a,]"), vec![]);
+        assert_eq!(trailing("[a,)]"), vec![]);
+        // Not separators.
+        assert_eq!(trailing("\",)\" ',' /* ;} */"), vec![]);
+        assert_eq!(trailing("(a, b) c"), vec![]);
+        assert_eq!(trailing(""), vec![]);
+    }
+
+    #[test]
+    fn lexemize_result_adjacent_string_literals() {
+        let adjacent = |orig: &'static str| lexemize(orig).adjacent_string_literals();
+        assert_eq!(adjacent("\"a\" \"b\""), vec![(0, 4)]);
+        assert_eq!(adjacent("\"a\"\n\"b\""), vec![(0, 4)]);
+        assert_eq!(adjacent("\"a\"/* c */b\"b\" r\"c\""), vec![(0, 10), (10, 15)]);
+        // Separated by something other than trivia.
+        assert_eq!(adjacent("\"a\" + \"b\""), vec![]);
+        assert_eq!(adjacent("f(\"a\", \"b\")"), vec![]);
+        assert_eq!(adjacent("'a' 'b' \"a\""), vec![]);
+        assert_eq!(adjacent(""), vec![]);
+    }
+
+    #[test]
+    fn lexemize_result_suspicious_numbers() {
+        let suspicious = |orig: &'static str| lexemize(orig).suspicious_numbers();
+        // A sign between two numbers, where an exponent may have been meant.
+        assert_eq!(suspicious("1+1"), vec![0]);
+        assert_eq!(suspicious("x = 2.5-3;"), vec![4]);
+        // A bare exponent, which is Unexpected.
+        assert_eq!(suspicious("10e"), vec![0]);
+        assert_eq!(suspicious("9E+ 2.5e- 1.0E"), vec![0, 4, 10]);
+        assert_eq!(suspicious("1+1 10e"), vec![0, 4]);
+        // Valid exponents, and ordinary arithmetic.
+        assert_eq!(suspicious("1e+1"), vec![]);
+        assert_eq!(suspicious("1E-1 + 2"), vec![]);
+        assert_eq!(suspicious("1 + 1"), vec![]);
+        assert_eq!(suspicious("1e2+1"), vec![]); // already has an exponent
+        assert_eq!(suspicious("1u8+1"), vec![]); // has a type suffix
+        assert_eq!(suspicious("x+1 1+x 1+=1"), vec![]);
+        // Other malformed numbers.
+        assert_eq!(suspicious("0b12 \"e"), vec![]);
+        assert_eq!(suspicious(""), vec![]);
+    }
+
+    #[test]
+    fn lexemize_result_flag_oversized_numbers() {
+        let mut result = lexemize("255u8+256u8");
+        assert_eq!(result.reasons, vec![]);
+        result.flag_oversized_numbers();
+        assert_eq!(result.to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             NumberDecimal           0  255u8\n\
+             Punctuation             5  +\n\
+             Unexpected              6  256u8\n\
+             EndOfInput             11  <EOI>\n"
+        );
+        assert_eq!(result.reasons, vec![(6, UnexpectedReason::OversizedNumber)]);
+        // Combined with checked mode, the reasons stay in `chr` order.
+        let options = LexemizeOptions { checked: true, ..Default::default() };
+        let mut result = lexemize_with_options(
+            "0x1_0000_0000_0000_0000_0000_0000_0000_0000 0.1", options);
+        result.flag_oversized_numbers();
+        assert_eq!(result.reasons, vec![
+            (0, UnexpectedReason::OversizedNumber),
+            (44, UnexpectedReason::InexactFloat),
+        ]);
+    }
+
+    // Replaces `edit` in `before` with `text`, and checks that `relex()` gives
+    // the same Lexemes and Diagnostics as a full `lexemize()` of the result.
+    fn check_relex(before: &'static str, edit: core::ops::Range<usize>, text: &str) {
+        let after = format!("{}{}{}", &before[..edit.start], text, &before[edit.end..]);
+        let relexed = lexemize(before).relex(&after, edit.clone(), text.len());
+        let expected = lexemize(&after);
+        assert_eq!(relexed.lexemes, expected.lexemes, "{:?} {:?}", edit, text);
+        assert_eq!(relexed.diagnostics, expected.diagnostics, "{:?} {:?}", edit, text);
     }
 
+    const RELEX_CODE: &str = "\
+        /// Says hello.\n\
+        fn hello<'a>(name: &'a str) -> String {\n    \
+            let x = (1.5, 2); let y = x.0; /* a /* nested */ comment */\n    \
+            println!(\"Hello, {}! {}\", name, y);\n    \
+            let c = '\\u{1F600}'; let r = r#\"raw \"quoted\" text\"#;\n    \
+            for<'b> fn(&'b u8) -> u8; #[allow(unused)] t.0.1\n\
+        }\n\
+        macro_rules! m { ($($x:expr),*) => { $($x)+* }; }\n\
+        fn main() { hello(\"world\"); }\n";
+
     #[test]
-    fn lexemize_characters() {
-        // Three Characters.
-        assert_eq!(lexemize("'Z''\\t''\\x3F''\\u{3F}'").to_string(),
-            "Lexemes, incl <EOI>: 5\n\
-             CharacterPlain          0  \'Z\'\n\
-             CharacterPlain          3  \'\\t\'\n\
-             CharacterHex            7  \'\\x3F\'\n\
-             CharacterUnicode       13  \'\\u{3F}\'\n\
-             WhitespaceTrimmable    21  <EOI>\n"
-        );
+    fn lexemize_result_relex_as_expected() {
+        // An edit inside a string.
+        check_relex(RELEX_CODE, 134..139, "Goodbye");
+        check_relex("let a = \"abc\"; let b = 1; let c = 2; let d = 3;", 10..11, "xyz");
+        // Unterminating a string, and terminating it again.
+        check_relex("let a = 1; let b = 2; let c = 3; let s = \"abc\"; x", 41..42, "");
+        check_relex("let a = 1; let b = 2; let c = 3; let s = \"abc; x", 45..45, "\"");
+        // An edit inside a comment, which can end or open the comment.
+        check_relex(RELEX_CODE, 99..100, "N");
+        check_relex(RELEX_CODE, 106..108, ""); // the inner `*/`
+        check_relex(RELEX_CODE, 97..97, "*/");
+        // An edit in plain code.
+        check_relex(RELEX_CODE, 47..53, "u32");
+        check_relex(RELEX_CODE, 64..65, "xs");
+        check_relex(RELEX_CODE, 0..0, "use std::fmt;\n");
+        let end = RELEX_CODE.len();
+        check_relex(RELEX_CODE, end..end, "// the end");
+        check_relex(RELEX_CODE, 0..end, "");
+        // Completing a raw string with unbalanced hashes, far from its start.
+        check_relex("r#\"abc\" x y z 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9; z", 51..51, "\"#");
+        // An edit which does not fit the old code falls back to `lexemize()`.
+        assert_eq!(lexemize("abc").relex("x", 5..9, 1).lexemes, lexemize("x").lexemes);
+        assert_eq!(lexemize("abc").relex("x", 0..3, 7).lexemes, lexemize("x").lexemes);
+    }
+
+    #[test]
+    fn lexemize_result_relex_every_edit() {
+        // Insert, delete and replace at every position in the code.
+        let code = RELEX_CODE;
+        for (start, _) in code.char_indices() {
+            let next = start + code[start..].chars().next().unwrap().len_utf8();
+            for text in &["", "\"", "'", "/*", "*/", "r#", "x", "!", "$("] {
+                check_relex(code, start..start, text);
+                check_relex(code, start..next, text);
+            }
+        }
     }
 
     #[test]
@@ -262,12 +2473,38 @@ mod tests {
         // Three Comments.
         assert_eq!(lexemize("/**A/*A'*/*///B\n//C").to_string(),
             "Lexemes, incl <EOI>: 5\n\
-             CommentMultiline        0  /**A/*A'*/*/\n\
+             CommentDocMultiline     0  /**A/*A'*/*/\n\
              CommentInline          12  //B\n\
              WhitespaceTrimmable    15  <NL>\n\
              CommentInline          16  //C\n\
-             WhitespaceTrimmable    19  <EOI>\n"
+             EndOfInput             19  <EOI>\n"
         );
+        // Doc Comments, and Comments which only look like them.
+        assert_eq!(lexemize("//! A\n/// B\n//// C\n/*! D */ /** E */ /*** F */ /**/").to_string(),
+            "Lexemes, incl <EOI>: 14\n\
+             CommentDocInline        0  //! A\n\
+             WhitespaceTrimmable     5  <NL>\n\
+             CommentDocInline        6  /// B\n\
+             WhitespaceTrimmable    11  <NL>\n\
+             CommentInline          12  //// C\n\
+             WhitespaceTrimmable    18  <NL>\n\
+             CommentDocMultiline    19  /*! D */\n\
+             WhitespaceTrimmable    27   \n\
+             CommentDocMultiline    28  /** E */\n\
+             WhitespaceTrimmable    36   \n\
+             CommentMultiline       37  /*** F */\n\
+             WhitespaceTrimmable    46   \n\
+             CommentMultiline       47  /**/\n\
+             EndOfInput             51  <EOI>\n"
+        );
+        // Inner and Outer doc comments.
+        let result = lexemize("//! A\n/// B\n// C\n/*! D */ /** E */");
+        let placements: Vec<DocPlacement> = result.lexemes.iter()
+            .filter_map(Lexeme::doc_placement).collect();
+        assert_eq!(placements, vec![
+            DocPlacement::Inner, DocPlacement::Outer,
+            DocPlacement::Inner, DocPlacement::Outer,
+        ]);
     }
 
     #[test]
@@ -283,26 +2520,482 @@ mod tests {
              WhitespaceTrimmable    11   \n\
              IdentifierKeyword      12  as\n\
              WhitespaceTrimmable    14   \n\
-             IdentifierFreeword     15  foo\n\
+             IdentifierMacro        15  foo\n\
              Punctuation            18  !\n\
-             WhitespaceTrimmable    19  <EOI>\n"
+             EndOfInput             19  <EOI>\n"
+        );
+        // Raw Identifiers, including a raw macro name and a forbidden one.
+        assert_eq!(lexemize("r#fn r#match! r#self").to_string(),
+            "Lexemes, incl <EOI>: 7\n\
+             IdentifierFreeword      0  r#fn\n\
+             WhitespaceTrimmable     4   \n\
+             IdentifierMacro         5  r#match\n\
+             Punctuation            12  !\n\
+             WhitespaceTrimmable    13   \n\
+             Unexpected             14  r#self\n\
+             EndOfInput             20  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_hrtb() {
+        // A higher-ranked trait bound.
+        assert_eq!(lexemize("for<'a> Fn(&'a u8)").to_string(),
+            "Lexemes, incl <EOI>: 13\n\
+             IdentifierHrtbFor       0  for\n\
+             Punctuation             3  <\n\
+             IdentifierLifetime      4  'a\n\
+             Punctuation             6  >\n\
+             WhitespaceTrimmable     7   \n\
+             IdentifierFreeword      8  Fn\n\
+             Punctuation            10  (\n\
+             Punctuation            11  &\n\
+             IdentifierLifetime     12  'a\n\
+             WhitespaceTrimmable    14   \n\
+             IdentifierStdType      15  u8\n\
+             Punctuation            17  )\n\
+             EndOfInput             18  <EOI>\n"
+        );
+        // A `for` loop is left as a Keyword.
+        assert_eq!(lexemize("for x in xs").to_string(),
+            "Lexemes, incl <EOI>: 8\n\
+             IdentifierKeyword       0  for\n\
+             WhitespaceTrimmable     3   \n\
+             IdentifierFreeword      4  x\n\
+             WhitespaceTrimmable     5   \n\
+             IdentifierKeyword       6  in\n\
+             WhitespaceTrimmable     8   \n\
+             IdentifierFreeword      9  xs\n\
+             EndOfInput             11  <EOI>\n"
+        );
+        // Two lifetimes, with whitespace before the `<`.
+        assert_eq!(lexemize("for <'a, 'b>").to_string(),
+            "Lexemes, incl <EOI>: 9\n\
+             IdentifierHrtbFor       0  for\n\
+             WhitespaceTrimmable     3   \n\
+             Punctuation             4  <\n\
+             IdentifierLifetime      5  'a\n\
+             Punctuation             7  ,\n\
+             WhitespaceTrimmable     8   \n\
+             IdentifierLifetime      9  'b\n\
+             Punctuation            11  >\n\
+             EndOfInput             12  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_underscores() {
+        // The wildcard is Punctuation, but `_x` and `__` are Freewords.
+        assert_eq!(lexemize("(_, _x, __)_").to_string(),
+            "Lexemes, incl <EOI>: 11\n\
+             Punctuation             0  (\n\
+             Punctuation             1  _\n\
+             Punctuation             2  ,\n\
+             WhitespaceTrimmable     3   \n\
+             IdentifierFreeword      4  _x\n\
+             Punctuation             6  ,\n\
+             WhitespaceTrimmable     7   \n\
+             IdentifierFreeword      8  __\n\
+             Punctuation            10  )\n\
+             Punctuation            11  _\n\
+             EndOfInput             12  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_byte_order_mark() {
+        // A BOM-prefixed program.
+        assert_eq!(lexemize("\u{FEFF}fn main() {}").to_string(),
+            "Lexemes, incl <EOI>: 10\n\
+             ByteOrderMark           0  \u{FEFF}\n\
+             IdentifierKeyword       3  fn\n\
+             WhitespaceTrimmable     5   \n\
+             IdentifierFreeword      6  main\n\
+             Punctuation            10  (\n\
+             Punctuation            11  )\n\
+             WhitespaceTrimmable    12   \n\
+             Punctuation            13  {\n\
+             Punctuation            14  }\n\
+             EndOfInput             15  <EOI>\n"
+        );
+        // A BOM in the middle is not valid Rust.
+        assert_eq!(lexemize("a\u{FEFF}b").to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             IdentifierFreeword      0  a\n\
+             Unidentifiable          1  \u{FEFF}\n\
+             IdentifierFreeword      4  b\n\
+             EndOfInput              5  <EOI>\n"
+        );
+        // No BOM.
+        assert_eq!(lexemize("fn").to_string(),
+            "Lexemes, incl <EOI>: 2\n\
+             IdentifierKeyword       0  fn\n\
+             EndOfInput              2  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_shebang() {
+        // A BOM, then a shebang, then the code.
+        assert_eq!(lexemize("\u{FEFF}#!/bin/sh\ncode").to_string(),
+            "Lexemes, incl <EOI>: 5\n\
+             ByteOrderMark           0  \u{FEFF}\n\
+             CommentShebang          3  #!/bin/sh\n\
+             WhitespaceTrimmable    12  <NL>\n\
+             IdentifierFreeword     13  code\n\
+             EndOfInput             17  <EOI>\n"
+        );
+        // No BOM.
+        assert_eq!(lexemize("#!/usr/bin/env rustx\nfn").to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             CommentShebang          0  #!/usr/bin/env rustx\n\
+             WhitespaceTrimmable    20  <NL>\n\
+             IdentifierKeyword      21  fn\n\
+             EndOfInput             23  <EOI>\n"
+        );
+        // An inner attribute, or a `#!` after the first line, is not a shebang.
+        assert_eq!(lexemize("#![no_std]\n#!x").to_string(),
+            "Lexemes, incl <EOI>: 9\n\
+             AttributeStart          0  #!\n\
+             Punctuation             2  [\n\
+             IdentifierFreeword      3  no_std\n\
+             Punctuation             9  ]\n\
+             WhitespaceTrimmable    10  <NL>\n\
+             Punctuation            11  #\n\
+             Punctuation            12  !\n\
+             IdentifierFreeword     13  x\n\
+             EndOfInput             14  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_labels() {
+        // A loop label, including its colon.
+        assert_eq!(lexemize("'outer: loop {}").to_string(),
+            "Lexemes, incl <EOI>: 7\n\
+             Label                   0  'outer:\n\
+             WhitespaceTrimmable     7   \n\
+             IdentifierKeyword       8  loop\n\
+             WhitespaceTrimmable    12   \n\
+             Punctuation            13  {\n\
+             Punctuation            14  }\n\
+             EndOfInput             15  <EOI>\n"
+        );
+        // `'a:` before a block is a label, but `'a ` is just a lifetime.
+        assert_eq!(lexemize("'a: {} 'a {}").to_string(),
+            "Lexemes, incl <EOI>: 10\n\
+             Label                   0  'a:\n\
+             WhitespaceTrimmable     3   \n\
+             Punctuation             4  {\n\
+             Punctuation             5  }\n\
+             WhitespaceTrimmable     6   \n\
+             IdentifierLifetime      7  'a\n\
+             WhitespaceTrimmable     9   \n\
+             Punctuation            10  {\n\
+             Punctuation            11  }\n\
+             EndOfInput             12  <EOI>\n"
+        );
+        // A generic lifetime, and a lifetime bound, stay as Lifetimes.
+        assert_eq!(lexemize("<'a, 'b: 'a>").to_string(),
+            "Lexemes, incl <EOI>: 10\n\
+             Punctuation             0  <\n\
+             IdentifierLifetime      1  'a\n\
+             Punctuation             3  ,\n\
+             WhitespaceTrimmable     4   \n\
+             IdentifierLifetime      5  'b\n\
+             Punctuation             7  :\n\
+             WhitespaceTrimmable     8   \n\
+             IdentifierLifetime      9  'a\n\
+             Punctuation            11  >\n\
+             EndOfInput             12  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_lifetimes() {
+        // The `'static` special case, followed by a StdType.
+        assert_eq!(lexemize("&'static str").to_string(),
+            "Lexemes, incl <EOI>: 5\n\
+             Punctuation             0  &\n\
+             IdentifierLifetime      1  'static\n\
+             WhitespaceTrimmable     8   \n\
+             IdentifierStdType       9  str\n\
+             EndOfInput             12  <EOI>\n"
+        );
+        // Ordinary lifetimes.
+        assert_eq!(lexemize("'a: 'b").to_string(),
+            "Lexemes, incl <EOI>: 5\n\
+             IdentifierLifetime      0  'a\n\
+             Punctuation             2  :\n\
+             WhitespaceTrimmable     3   \n\
+             IdentifierLifetime      4  'b\n\
+             EndOfInput              6  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_macros() {
+        // A macro invocation, with arguments.
+        assert_eq!(lexemize("println!(x)").to_string(),
+            "Lexemes, incl <EOI>: 6\n\
+             IdentifierMacro         0  println\n\
+             Punctuation             7  !\n\
+             Punctuation             8  (\n\
+             IdentifierFreeword      9  x\n\
+             Punctuation            10  )\n\
+             EndOfInput             11  <EOI>\n"
+        );
+        // Not a macro, because "!=" is a single Punctuation Lexeme.
+        assert_eq!(lexemize("foo != bar").to_string(),
+            "Lexemes, incl <EOI>: 6\n\
+             IdentifierFreeword      0  foo\n\
+             WhitespaceTrimmable     3   \n\
+             Punctuation             4  !=\n\
+             WhitespaceTrimmable     6   \n\
+             IdentifierFreeword      7  bar\n\
+             EndOfInput             10  <EOI>\n"
+        );
+        // A macro at the end of the input.
+        assert_eq!(lexemize("vec!").to_string(),
+            "Lexemes, incl <EOI>: 3\n\
+             IdentifierMacro         0  vec\n\
+             Punctuation             3  !\n\
+             EndOfInput              4  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_union() {
+        // A definition, where `union` is a Keyword, and a variable, where it
+        // is not.
+        assert_eq!(lexemize("union U{} let union=U;").to_string(),
+            "Lexemes, incl <EOI>: 13\n\
+             IdentifierKeyword       0  union\n\
+             WhitespaceTrimmable     5   \n\
+             IdentifierFreeword      6  U\n\
+             Punctuation             7  {\n\
+             Punctuation             8  }\n\
+             WhitespaceTrimmable     9   \n\
+             IdentifierKeyword      10  let\n\
+             WhitespaceTrimmable    13   \n\
+             IdentifierFreeword     14  union\n\
+             Punctuation            19  =\n\
+             IdentifierFreeword     20  U\n\
+             Punctuation            21  ;\n\
+             EndOfInput             22  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_macro_rules() {
+        // A definition, where `macro_rules` is a Keyword.
+        assert_eq!(lexemize("macro_rules! foo {").to_string(),
+            "Lexemes, incl <EOI>: 7\n\
+             IdentifierKeyword       0  macro_rules\n\
+             Punctuation            11  !\n\
+             WhitespaceTrimmable    12   \n\
+             IdentifierFreeword     13  foo\n\
+             WhitespaceTrimmable    16   \n\
+             Punctuation            17  {\n\
+             EndOfInput             18  <EOI>\n"
+        );
+        // A variable, and an invocation, where it is not.
+        assert_eq!(lexemize("let macro_rules = macro_rules!(x);").to_string(),
+            "Lexemes, incl <EOI>: 13\n\
+             IdentifierKeyword       0  let\n\
+             WhitespaceTrimmable     3   \n\
+             IdentifierFreeword      4  macro_rules\n\
+             WhitespaceTrimmable    15   \n\
+             Punctuation            16  =\n\
+             WhitespaceTrimmable    17   \n\
+             IdentifierMacro        18  macro_rules\n\
+             Punctuation            29  !\n\
+             Punctuation            30  (\n\
+             IdentifierFreeword     31  x\n\
+             Punctuation            32  )\n\
+             Punctuation            33  ;\n\
+             EndOfInput             34  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_macro_repetition() {
+        assert_eq!(lexemize("macro_rules! m{($($x),*)=>{$(a)+$(b)?}} x?").to_string(),
+            "Lexemes, incl <EOI>: 32\n\
+             IdentifierKeyword       0  macro_rules\n\
+             Punctuation            11  !\n\
+             WhitespaceTrimmable    12   \n\
+             IdentifierFreeword     13  m\n\
+             Punctuation            14  {\n\
+             Punctuation            15  (\n\
+             Punctuation            16  $\n\
+             Punctuation            17  (\n\
+             Punctuation            18  $\n\
+             IdentifierFreeword     19  x\n\
+             Punctuation            20  )\n\
+             Punctuation            21  ,\n\
+             MacroRepeatOp          22  *\n\
+             Punctuation            23  )\n\
+             Punctuation            24  =>\n\
+             Punctuation            26  {\n\
+             Punctuation            27  $\n\
+             Punctuation            28  (\n\
+             IdentifierFreeword     29  a\n\
+             Punctuation            30  )\n\
+             MacroRepeatOp          31  +\n\
+             Punctuation            32  $\n\
+             Punctuation            33  (\n\
+             IdentifierFreeword     34  b\n\
+             Punctuation            35  )\n\
+             MacroRepeatOp          36  ?\n\
+             Punctuation            37  }\n\
+             Punctuation            38  }\n\
+             WhitespaceTrimmable    39   \n\
+             IdentifierFreeword     40  x\n\
+             Punctuation            41  ?\n\
+             EndOfInput             42  <EOI>\n"
         );
     }
 
     #[test]
     fn lexemize_numbers() {
+        // Integers and floats, including an integer with a float suffix.
+        assert_eq!(lexemize("42 3.14 1e3 1f64").to_string(),
+            "Lexemes, incl <EOI>: 8\n\
+             NumberDecimal           0  42\n\
+             WhitespaceTrimmable     2   \n\
+             NumberDecimalFloat      3  3.14\n\
+             WhitespaceTrimmable     7   \n\
+             NumberDecimalFloat      8  1e3\n\
+             WhitespaceTrimmable    11   \n\
+             NumberDecimalFloat     12  1f64\n\
+             EndOfInput             16  <EOI>\n"
+        );
         // Three Numbers.
         assert_eq!(lexemize("0b1001_0011 1_2.3_4E+_5_ 0x__01aB__ 0o1_7").to_string(),
             "Lexemes, incl <EOI>: 8\n\
              NumberBinary            0  0b1001_0011\n\
              WhitespaceTrimmable    11   \n\
-             NumberDecimal          12  1_2.3_4E+_5_\n\
+             NumberDecimalFloat     12  1_2.3_4E+_5_\n\
              WhitespaceTrimmable    24   \n\
              NumberHex              25  0x__01aB__\n\
              WhitespaceTrimmable    35   \n\
              NumberOctal            36  0o1_7\n\
-             WhitespaceTrimmable    41  <EOI>\n"
+             EndOfInput             41  <EOI>\n"
+        );
+        // Hex followed by a method call and a tuple index.
+        assert_eq!(lexemize("0xAB.len();0xAB.0").to_string(),
+            "Lexemes, incl <EOI>: 10\n\
+             NumberHex               0  0xAB\n\
+             Punctuation             4  .\n\
+             IdentifierFreeword      5  len\n\
+             Punctuation             8  (\n\
+             Punctuation             9  )\n\
+             Punctuation            10  ;\n\
+             NumberHex              11  0xAB\n\
+             Punctuation            15  .\n\
+             NumberDecimal          16  0\n\
+             EndOfInput             17  <EOI>\n"
+        );
+        // Tuple indices, where `0.1` is not a float.
+        assert_eq!(lexemize("x.0;x.0.1;tuple.12;1.0").to_string(),
+            "Lexemes, incl <EOI>: 16\n\
+             IdentifierFreeword      0  x\n\
+             Punctuation             1  .\n\
+             NumberDecimal           2  0\n\
+             Punctuation             3  ;\n\
+             IdentifierFreeword      4  x\n\
+             Punctuation             5  .\n\
+             NumberDecimal           6  0\n\
+             Punctuation             7  .\n\
+             NumberDecimal           8  1\n\
+             Punctuation             9  ;\n\
+             IdentifierFreeword     10  tuple\n\
+             Punctuation            15  .\n\
+             NumberDecimal          16  12\n\
+             Punctuation            18  ;\n\
+             NumberDecimalFloat     19  1.0\n\
+             EndOfInput             22  <EOI>\n"
+        );
+        // A float suffix is part of any decimal number, but an integer suffix
+        // is never part of a float, so `1.0u8` is split.
+        assert_eq!(lexemize("1f64 1.0f32 1.0u8 1u8").to_string(),
+            "Lexemes, incl <EOI>: 9\n\
+             NumberDecimalFloat      0  1f64\n\
+             WhitespaceTrimmable     4   \n\
+             NumberDecimalFloat      5  1.0f32\n\
+             WhitespaceTrimmable    11   \n\
+             NumberDecimalFloat     12  1.0\n\
+             IdentifierStdType      15  u8\n\
+             WhitespaceTrimmable    17   \n\
+             NumberDecimal          18  1u8\n\
+             EndOfInput             21  <EOI>\n"
         );
+        // A tuple index is just digits, so `x.1e2` is the index 1 then `e2`.
+        assert_eq!(lexemize("x.1;x.1e2;x.0.0").to_string(),
+            "Lexemes, incl <EOI>: 15\n\
+             IdentifierFreeword      0  x\n\
+             Punctuation             1  .\n\
+             NumberDecimal           2  1\n\
+             Punctuation             3  ;\n\
+             IdentifierFreeword      4  x\n\
+             Punctuation             5  .\n\
+             NumberDecimal           6  1\n\
+             IdentifierFreeword      7  e2\n\
+             Punctuation             9  ;\n\
+             IdentifierFreeword     10  x\n\
+             Punctuation            11  .\n\
+             NumberDecimal          12  0\n\
+             Punctuation            13  .\n\
+             NumberDecimal          14  0\n\
+             EndOfInput             15  <EOI>\n"
+        );
+        // Ranges, where the first Number must not take the first dot.
+        assert_eq!(lexemize("1..2;1..=2;1.0..2.0;1.").to_string(),
+            "Lexemes, incl <EOI>: 14\n\
+             NumberDecimal           0  1\n\
+             Punctuation             1  ..\n\
+             NumberDecimal           3  2\n\
+             Punctuation             4  ;\n\
+             NumberDecimal           5  1\n\
+             Punctuation             6  ..=\n\
+             NumberDecimal           9  2\n\
+             Punctuation            10  ;\n\
+             NumberDecimalFloat     11  1.0\n\
+             Punctuation            14  ..\n\
+             NumberDecimalFloat     16  2.0\n\
+             Punctuation            19  ;\n\
+             NumberDecimalFloat     20  1.\n\
+             EndOfInput             22  <EOI>\n"
+        );
+        // Type suffixes after underscores, which are part of the Number.
+        assert_eq!(lexemize("1_u8+0xFF_u16+1_000_i32+1_").to_string(),
+            "Lexemes, incl <EOI>: 8\n\
+             NumberDecimal           0  1_u8\n\
+             Punctuation             4  +\n\
+             NumberHex               5  0xFF_u16\n\
+             Punctuation            13  +\n\
+             NumberDecimal          14  1_000_i32\n\
+             Punctuation            23  +\n\
+             NumberDecimal          24  1_\n\
+             EndOfInput             26  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_numbers_float_has_dot_exponent_or_suffix() {
+        // Every NumberDecimalFloat snippet must show why it is a float.
+        let orig = "1 1. 1.0 1e3 1E+3 1f32 1_f64 1.0f32 1.0u8 1u8 1.f32 1e3f64 \
+            .5 .5f32 .5u8 x.0 x.0.1 0x1f32 1f16 1f64x 2.5_f64 1__2.3e_4";
+        for lexeme in lexemize(orig).lexemes {
+            if lexeme.kind != LexemeKind::NumberDecimalFloat { continue }
+            let (number, suffix) = split_number_suffix(lexeme.snippet);
+            assert!(
+                number.contains(['.', 'e', 'E'])
+                    || suffix == "f32" || suffix == "f64",
+                "{:?} at {} is a float", lexeme.snippet, lexeme.chr
+            );
+        }
     }
 
     #[test]
@@ -313,10 +3006,28 @@ mod tests {
              Punctuation             0  ;\n\
              Punctuation             1  *=\n\
              Punctuation             3  >>=\n\
-             WhitespaceTrimmable     6  <EOI>\n"
+             EndOfInput              6  <EOI>\n"
         );
     }
 
+    #[test]
+    fn lexemize_dot_punctuation_ops() {
+        use super::super::lexeme::PunctOp::{Dot,DotDot,DotDotDot,DotDotEq};
+        let ops = |orig: &'static str| lexemize(orig).significant()
+            .filter_map(|lexeme| lexeme.punctuation_op()).collect::<Vec<_>>();
+        // A struct update, a range, an inclusive range and a field.
+        assert_eq!(ops("S { ..Default::default() }").get(1), Some(&DotDot));
+        assert_eq!(ops("0..9"), vec![DotDot]);
+        assert_eq!(ops("0..=9"), vec![DotDotEq]);
+        assert_eq!(ops("a.b"), vec![Dot]);
+        // `...` is still detected, for variadic functions and old patterns.
+        assert!(ops("fn f(x: u8, ...);").contains(&DotDotDot));
+        assert_eq!(ops("0...9"), vec![DotDotDot]);
+        // The longest match wins, so `....` is `...` then `.`.
+        assert_eq!(ops("...."), vec![DotDotDot, Dot]);
+        assert_eq!(ops("..=..."), vec![DotDotEq, DotDotDot]);
+    }
+
     #[test]
     fn lexemize_strings() {
         // Three Strings.
@@ -325,8 +3036,133 @@ mod tests {
              StringPlain             0  \"\"\n\
              StringPlain             2  \"ok\"\n\
              StringRaw               6  r##\"\\\"\"##\n\
-             WhitespaceTrimmable    15  <EOI>\n"
+             EndOfInput             15  <EOI>\n"
       );
+        // With a space after the `r`, it’s an Identifier, and not a Raw string.
+        assert_eq!(lexemize("r #\"x\"#").to_string(),
+            "Lexemes, incl <EOI>: 6\n\
+             IdentifierFreeword      0  r\n\
+             WhitespaceTrimmable     1   \n\
+             Punctuation             2  #\n\
+             StringPlain             3  \"x\"\n\
+             Punctuation             6  #\n\
+             EndOfInput              7  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_string_prefixes() {
+        // An Identifier which ends in "r" is not the prefix of a Raw string.
+        assert_eq!(lexemize("foor\"x\" abcr#\"x\"#").to_string(),
+            "Lexemes, incl <EOI>: 8\n\
+             IdentifierFreeword      0  foor\n\
+             StringPlain             4  \"x\"\n\
+             WhitespaceTrimmable     7   \n\
+             IdentifierFreeword      8  abcr\n\
+             Punctuation            12  #\n\
+             StringPlain            13  \"x\"\n\
+             Punctuation            16  #\n\
+             EndOfInput             17  <EOI>\n"
+        );
+        // `b` and `br` are prefixes, but `cr` is not, in Rust 2018.
+        assert_eq!(lexemize("b\"x\" br\"x\" cr\"x\"").to_string(),
+            "Lexemes, incl <EOI>: 7\n\
+             StringByte              0  b\"x\"\n\
+             WhitespaceTrimmable     4   \n\
+             StringByteRaw           5  br\"x\"\n\
+             WhitespaceTrimmable    10   \n\
+             IdentifierFreeword     11  cr\n\
+             StringPlain            13  \"x\"\n\
+             EndOfInput             16  <EOI>\n"
+        );
+        // Identifiers which start with a prefix are not strings.
+        assert_eq!(lexemize("break crate raw bytes brown\"x\"").to_string(),
+            "Lexemes, incl <EOI>: 11\n\
+             IdentifierKeyword       0  break\n\
+             WhitespaceTrimmable     5   \n\
+             IdentifierKeyword       6  crate\n\
+             WhitespaceTrimmable    11   \n\
+             IdentifierFreeword     12  raw\n\
+             WhitespaceTrimmable    15   \n\
+             IdentifierFreeword     16  bytes\n\
+             WhitespaceTrimmable    21   \n\
+             IdentifierFreeword     22  brown\n\
+             StringPlain            27  \"x\"\n\
+             EndOfInput             30  <EOI>\n"
+        );
+        // A lone `r` followed by a division is not a Raw string.
+        assert_eq!(lexemize("r / \"x\"; r/\"x\"").to_string(),
+            "Lexemes, incl <EOI>: 11\n\
+             IdentifierFreeword      0  r\n\
+             WhitespaceTrimmable     1   \n\
+             Punctuation             2  /\n\
+             WhitespaceTrimmable     3   \n\
+             StringPlain             4  \"x\"\n\
+             Punctuation             7  ;\n\
+             WhitespaceTrimmable     8   \n\
+             IdentifierFreeword      9  r\n\
+             Punctuation            10  /\n\
+             StringPlain            11  \"x\"\n\
+             EndOfInput             14  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_raw_string_inner_quote() {
+        // The `"` inside the raw string does not end it, and the `\` before
+        // the closing `"#` is not an escape. So the rest of the input is
+        // lexemized as normal, and not lost to an Unexpected Lexeme.
+        assert_eq!(lexemize("let s = r#\"a\"b\\\"#; let t = 1;").to_string(),
+            "Lexemes, incl <EOI>: 18\n\
+             IdentifierKeyword       0  let\n\
+             WhitespaceTrimmable     3   \n\
+             IdentifierFreeword      4  s\n\
+             WhitespaceTrimmable     5   \n\
+             Punctuation             6  =\n\
+             WhitespaceTrimmable     7   \n\
+             StringRaw               8  r#\"a\"b\\\"#\n\
+             Punctuation            17  ;\n\
+             WhitespaceTrimmable    18   \n\
+             IdentifierKeyword      19  let\n\
+             WhitespaceTrimmable    22   \n\
+             IdentifierFreeword     23  t\n\
+             WhitespaceTrimmable    24   \n\
+             Punctuation            25  =\n\
+             WhitespaceTrimmable    26   \n\
+             NumberDecimal          27  1\n\
+             Punctuation            28  ;\n\
+             EndOfInput             29  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_unterminated_string() {
+        use super::{Lexemizer,detect_unexpected};
+        use super::super::detect::string::detect_string_or_unterminated;
+        use LexemeKind::{EndOfInput as E,IdentifierFreeword as F};
+        use LexemeKind::{StringPlain as S,Unexpected as X,Unidentifiable as U};
+        use LexemeKind::WhitespaceTrimmable as W;
+        // A terminated string is fine.
+        assert_eq!(summary(lexemize("x \"abc\"").lexemes),
+            vec![(F,0,"x"), (W,1," "), (S,2,"\"abc\""), (E,7,"")]);
+        // An unterminated string is a single Unexpected, to the end of input.
+        assert_eq!(summary(lexemize("x \"abc").lexemes),
+            vec![(F,0,"x"), (W,1," "), (X,2,"\"abc"), (E,6,"")]);
+        assert_eq!(summary(lexemize("\"abc\ny;").lexemes),
+            vec![(X,0,"\"abc\ny;"), (E,7,"")]);
+        assert_eq!(lexemize("x \"abc").diagnostics[0].message,
+            "unterminated string literal");
+        // Without `detect_unexpected()`, the quote is Unidentifiable.
+        let lexemizer = Lexemizer::default().without(detect_unexpected);
+        assert_eq!(summary(lexemizer.run("x \"abc").lexemes),
+            vec![(F,0,"x"), (W,1," "), (U,2,"\""), (F,3,"abc"), (E,6,"")]);
+        // Opting in to `detect_string_or_unterminated()` makes it Unexpected
+        // again, and leaves terminated strings alone.
+        let lexemizer = lexemizer.with(detect_string_or_unterminated);
+        assert_eq!(summary(lexemizer.clone().run("x \"abc").lexemes),
+            vec![(F,0,"x"), (W,1," "), (X,2,"\"abc"), (E,6,"")]);
+        assert_eq!(summary(lexemizer.run("x \"abc\"").lexemes),
+            vec![(F,0,"x"), (W,1," "), (S,2,"\"abc\""), (E,7,"")]);
     }
 
     #[test]
@@ -337,19 +3173,414 @@ mod tests {
              Unidentifiable          0  ~¶\n\
              WhitespaceTrimmable     3   \n\
              Unidentifiable          4  €\n\
-             WhitespaceTrimmable     7  <EOI>\n"
+             EndOfInput              7  <EOI>\n"
         );
         // Non-ascii.
         assert_eq!(lexemize("~`\\").to_string(),
             "Lexemes, incl <EOI>: 2\n\
              Unidentifiable          0  ~`\\\n\
-             WhitespaceTrimmable     3  <EOI>\n"
+             EndOfInput              3  <EOI>\n"
         );
         // Ascii.
         assert_eq!(lexemize("é¢€±").to_string(),
             "Lexemes, incl <EOI>: 2\n\
              Unidentifiable          0  é¢€±\n\
-             WhitespaceTrimmable     9  <EOI>\n"
+             EndOfInput              9  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemizer_default_matches_lexemize() {
+        let orig = "let r = r\"x\"; // 1.0\n";
+        assert_eq!(Lexemizer::default().run(orig).to_string(), lexemize(orig).to_string());
+        let options = LexemizeOptions {
+            compact_trivia: true,
+            ..LexemizeOptions::default()
+        };
+        assert_eq!(Lexemizer::default().options(options).run(orig).to_string(),
+            lexemize_with_options(orig, options).to_string());
+    }
+
+    #[test]
+    fn lexemizer_with_and_without() {
+        // Without `detect_string()`, a Raw string starts with an Identifier.
+        let lexemizer = Lexemizer::default().without(detect_string);
+        assert_eq!(lexemizer.run("r\"x\"").to_string(),
+            "Lexemes, incl <EOI>: 5\n\
+             IdentifierFreeword      0  r\n\
+             Unidentifiable          1  \"\n\
+             IdentifierFreeword      2  x\n\
+             Unexpected              3  \"\n\
+             EndOfInput              4  <EOI>\n"
+        );
+        // A custom detector, which treats a backtick as whitespace.
+        fn detect_backtick(orig: &str, chr: usize) -> (LexemeKind, usize) {
+            if orig.get(chr..chr+1) == Some("`")
+                { (LexemeKind::WhitespaceTrimmable, chr + 1) }
+                else { (LexemeKind::Undetected, 0) }
+        }
+        assert_eq!(lexemize("a`b").lexemes[1].kind, LexemeKind::Unidentifiable);
+        assert_eq!(Lexemizer::default().with(detect_backtick).run("a`b").to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             IdentifierFreeword      0  a\n\
+             WhitespaceTrimmable     1  `\n\
+             IdentifierFreeword      2  b\n\
+             EndOfInput              3  <EOI>\n"
+        );
+        // Removing a detector which is not in the pipeline does nothing.
+        let lexemizer = Lexemizer::default().without(detect_backtick);
+        assert_eq!(lexemizer.run("a`b").to_string(), lexemize("a`b").to_string());
+    }
+
+    #[test]
+    fn detectors_in_order() {
+        use super::{DETECTORS,Detector,detect_identifier,detect_punctuation};
+        use super::{detect_shebang,detect_unexpected};
+        let position = |detector: Detector| DETECTORS.iter()
+            .position(|d| core::ptr::fn_addr_eq(*d, detector))
+            .expect("every detector is in DETECTORS");
+        // A Raw string starts with an `r`, which would otherwise be an Identifier.
+        assert!(position(detect_string) < position(detect_identifier),
+            "detect_string() must come before detect_identifier()");
+        // Punctuation would otherwise take the `/` of an unterminated `/*`.
+        assert!(position(detect_unexpected) < position(detect_punctuation),
+            "detect_unexpected() must come before detect_punctuation()");
+        // Punctuation would otherwise take the `#!` of a shebang.
+        assert!(position(detect_shebang) < position(detect_punctuation),
+            "detect_shebang() must come before detect_punctuation()");
+        // Through the full pipeline, Raw strings are detected whole.
+        assert_eq!(lexemize("r\"x\" r#\"y\"#").to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             StringRaw               0  r\"x\"\n\
+             WhitespaceTrimmable     4   \n\
+             StringRaw               5  r#\"y\"#\n\
+             EndOfInput             11  <EOI>\n"
+        );
+        // Moving `detect_string()` after `detect_identifier()` breaks them.
+        let lexemizer = Lexemizer::default().without(detect_string).with(detect_string);
+        assert_eq!(lexemizer.run("r\"x\"").lexemes[0].snippet, "r");
+    }
+
+    #[test]
+    fn lexemize_edition() {
+        let options = LexemizeOptions {
+            edition: Edition::Rust2015,
+            ..LexemizeOptions::default()
+        };
+        // In Rust 2015, `async` is an ordinary identifier.
+        assert_eq!(lexemize_with_options("let async = 1;", options).to_string(),
+            "Lexemes, incl <EOI>: 9\n\
+             IdentifierKeyword       0  let\n\
+             WhitespaceTrimmable     3   \n\
+             IdentifierFreeword      4  async\n\
+             WhitespaceTrimmable     9   \n\
+             Punctuation            10  =\n\
+             WhitespaceTrimmable    11   \n\
+             NumberDecimal          12  1\n\
+             Punctuation            13  ;\n\
+             EndOfInput             14  <EOI>\n"
+        );
+        // In Rust 2018, `async` is a Keyword.
+        assert_eq!(lexemize("let async = 1;").to_string(),
+            "Lexemes, incl <EOI>: 9\n\
+             IdentifierKeyword       0  let\n\
+             WhitespaceTrimmable     3   \n\
+             IdentifierKeyword       4  async\n\
+             WhitespaceTrimmable     9   \n\
+             Punctuation            10  =\n\
+             WhitespaceTrimmable    11   \n\
+             NumberDecimal          12  1\n\
+             Punctuation            13  ;\n\
+             EndOfInput             14  <EOI>\n"
+        );
+        // A custom pipeline passes the edition to `detect_identifier_for()`.
+        let lexemizer = Lexemizer::default()
+            .without(super::detect_number)
+            .options(options);
+        let result = lexemizer.run("dyn r#try await fn");
+        assert_eq!(result.iter_no_eoi().filter(|l| l.kind.is_identifier())
+            .map(|l| (l.kind, l.snippet)).collect::<Vec<_>>(), vec![
+            (LexemeKind::IdentifierFreeword, "dyn"),
+            (LexemeKind::IdentifierFreeword, "r#try"),
+            (LexemeKind::IdentifierFreeword, "await"),
+            (LexemeKind::IdentifierKeyword, "fn"),
+        ]);
+    }
+
+    #[test]
+    fn lexemize_reserved_prefix() {
+        let orig = "foo#bar; foo\"x\"; r#foo; b'x' k #z";
+        let options = LexemizeOptions {
+            edition: Edition::Rust2024,
+            ..LexemizeOptions::default()
+        };
+        // In Rust 2024, `foo#` and `foo"` begin with a reserved prefix.
+        assert_eq!(lexemize_with_options(orig, options).to_string(),
+            "Lexemes, incl <EOI>: 19\n\
+             ReservedPrefix          0  foo\n\
+             Punctuation             3  #\n\
+             IdentifierFreeword      4  bar\n\
+             Punctuation             7  ;\n\
+             WhitespaceTrimmable     8   \n\
+             ReservedPrefix          9  foo\n\
+             StringPlain            12  \"x\"\n\
+             Punctuation            15  ;\n\
+             WhitespaceTrimmable    16   \n\
+             IdentifierFreeword     17  r#foo\n\
+             Punctuation            22  ;\n\
+             WhitespaceTrimmable    23   \n\
+             CharacterByte          24  b'x'\n\
+             WhitespaceTrimmable    28   \n\
+             IdentifierFreeword     29  k\n\
+             WhitespaceTrimmable    30   \n\
+             Punctuation            31  #\n\
+             IdentifierFreeword     32  z\n\
+             EndOfInput             33  <EOI>\n"
+        );
+        // In Rust 2021, `foo` is an ordinary identifier, so `foo#bar` is split
+        // the same way as in Rust 2018.
+        let options = LexemizeOptions {
+            edition: Edition::Rust2021,
+            ..LexemizeOptions::default()
+        };
+        assert_eq!(lexemize_with_options(orig, options).to_string(),
+            "Lexemes, incl <EOI>: 19\n\
+             IdentifierFreeword      0  foo\n\
+             Punctuation             3  #\n\
+             IdentifierFreeword      4  bar\n\
+             Punctuation             7  ;\n\
+             WhitespaceTrimmable     8   \n\
+             IdentifierFreeword      9  foo\n\
+             StringPlain            12  \"x\"\n\
+             Punctuation            15  ;\n\
+             WhitespaceTrimmable    16   \n\
+             IdentifierFreeword     17  r#foo\n\
+             Punctuation            22  ;\n\
+             WhitespaceTrimmable    23   \n\
+             CharacterByte          24  b'x'\n\
+             WhitespaceTrimmable    28   \n\
+             IdentifierFreeword     29  k\n\
+             WhitespaceTrimmable    30   \n\
+             Punctuation            31  #\n\
+             IdentifierFreeword     32  z\n\
+             EndOfInput             33  <EOI>\n"
+        );
+        // Rust 2018 splits it the same way.
+        assert_eq!(lexemize(orig).to_string(),
+            lexemize_with_options(orig, options).to_string());
+    }
+
+    #[test]
+    fn lexemize_split_unidentifiable() {
+        let options = LexemizeOptions {
+            split_unidentifiable: true,
+            ..LexemizeOptions::default()
+        };
+        // By default, a run of Unidentifiable characters is merged.
+        assert_eq!(lexemize("é¢€±").to_string(),
+            "Lexemes, incl <EOI>: 2\n\
+             Unidentifiable          0  é¢€±\n\
+             EndOfInput              9  <EOI>\n"
+        );
+        // When split, each character (not each byte) gets its own Lexeme.
+        assert_eq!(lexemize_with_options("é¢€±", options).to_string(),
+            "Lexemes, incl <EOI>: 5\n\
+             Unidentifiable          0  é\n\
+             Unidentifiable          2  ¢\n\
+             Unidentifiable          4  €\n\
+             Unidentifiable          7  ±\n\
+             EndOfInput              9  <EOI>\n"
+        );
+        // Ascii and non-ascii, next to other Lexemes.
+        assert_eq!(lexemize_with_options("~¶ €~", options).to_string(),
+            "Lexemes, incl <EOI>: 6\n\
+             Unidentifiable          0  ~\n\
+             Unidentifiable          1  ¶\n\
+             WhitespaceTrimmable     3   \n\
+             Unidentifiable          4  €\n\
+             Unidentifiable          7  ~\n\
+             EndOfInput              8  <EOI>\n"
+        );
+    }
+
+    #[test]
+    fn lexemize_allow_leading_dot_float() {
+        use LexemeKind::{EndOfInput as E,IdentifierFreeword as F};
+        use LexemeKind::{NumberDecimal as D,Punctuation as P};
+        use LexemeKind::{NumberDecimalFloat as R,WhitespaceTrimmable as W};
+        let options = LexemizeOptions {
+            allow_leading_dot_float: true,
+            ..LexemizeOptions::default()
+        };
+        // By default, `.5` is Punctuation and a Number.
+        assert_eq!(lexemize("x = .5e3;").to_string(),
+            "Lexemes, incl <EOI>: 9\n\
+             IdentifierFreeword      0  x\n\
+             WhitespaceTrimmable     1   \n\
+             Punctuation             2  =\n\
+             WhitespaceTrimmable     3   \n\
+             Punctuation             4  .\n\
+             NumberDecimal           5  5\n\
+             IdentifierFreeword      6  e3\n\
+             Punctuation             8  ;\n\
+             EndOfInput              9  <EOI>\n"
+        );
+        // When allowed, `.5` and `.5e3` are single Numbers.
+        assert_eq!(summary_with("x = .5e3; f(.5)", options), vec![(F,0,"x"),
+            (W,1," "), (P,2,"="), (W,3," "), (R,4,".5e3"), (P,8,";"), (W,9," "),
+            (F,10,"f"), (P,11,"("), (R,12,".5"), (P,14,")"), (E,15,"")]);
+        assert_eq!(summary_with(".5", options), vec![(R,0,".5"), (E,2,"")]);
+        // Fields, tuple indices and ranges are unchanged.
+        assert_eq!(summary_with("x.0; f().1; a[0] .2", options), vec![(F,0,"x"),
+            (P,1,"."), (D,2,"0"), (P,3,";"), (W,4," "), (F,5,"f"), (P,6,"("),
+            (P,7,")"), (P,8,"."), (D,9,"1"), (P,10,";"), (W,11," "), (F,12,"a"),
+            (P,13,"["), (D,14,"0"), (P,15,"]"), (W,16," "), (P,17,"."),
+            (D,18,"2"), (E,19,"")]);
+        assert_eq!(summary_with("1..5; ..5", options), vec![(D,0,"1"),
+            (P,1,".."), (D,3,"5"), (P,4,";"), (W,5," "), (P,6,".."), (D,8,"5"),
+            (E,9,"")]);
+        // Not a float, because the digits are followed by another `.`, so it
+        // lexemizes like a tuple index.
+        assert_eq!(summary_with(".5.0", options), vec![(P,0,"."), (D,1,"5"),
+            (P,2,"."), (D,3,"0"), (E,4,"")]);
+    }
+
+    #[test]
+    fn lexemize_forbid_lone_cr() {
+        use LexemeKind::{EndOfInput as E,IdentifierFreeword as F};
+        use LexemeKind::{Unexpected as X,WhitespaceTrimmable as W};
+        let options = LexemizeOptions {
+            forbid_lone_cr: true,
+            ..LexemizeOptions::default()
+        };
+        // By default, a lone `\r` is just Whitespace.
+        assert_eq!(summary_with("a\rb", LexemizeOptions::default()),
+            vec![(F,0,"a"), (W,1,"\r"), (F,2,"b"), (E,3,"")]);
+        // When forbidden, a lone `\r` is Unexpected, and splits the Whitespace.
+        assert_eq!(summary_with("a\rb", options),
+            vec![(F,0,"a"), (X,1,"\r"), (F,2,"b"), (E,3,"")]);
+        assert_eq!(summary_with("a \r\t\r\rb", options), vec![(F,0,"a"), (W,1," "),
+            (X,2,"\r"), (W,3,"\t"), (X,4,"\r"), (X,5,"\r"), (F,6,"b"), (E,7,"")]);
+        assert_eq!(summary_with("a\r", options), vec![(F,0,"a"), (X,1,"\r"), (E,2,"")]);
+        // `\r\n` is always fine, even after a lone `\r`.
+        assert_eq!(summary_with("a\r\nb", options),
+            vec![(F,0,"a"), (W,1,"\r\n"), (F,3,"b"), (E,4,"")]);
+        assert_eq!(summary_with("a\r\r\n", options),
+            vec![(F,0,"a"), (X,1,"\r"), (W,2,"\r\n"), (E,4,"")]);
+        // Each lone `\r` is described.
+        assert_eq!(lexemize_with_options("a\rb", options).diagnostics[0].message,
+            "lone carriage return, not followed by a newline");
+        // A `\r` in a comment or string is not Whitespace, so is left alone.
+        assert_eq!(lexemize_with_options("// a\rb\n\"\r\"", options).diagnostics,
+            vec![]);
+    }
+
+    #[test]
+    fn lexemize_nul_is_error() {
+        use LexemeKind::{EndOfInput as E,IdentifierFreeword as F};
+        use LexemeKind::{Unexpected as X,Unidentifiable as U};
+        let options = LexemizeOptions {
+            nul_is_error: true,
+            ..LexemizeOptions::default()
+        };
+        // By default, a NUL in code is Unidentifiable.
+        assert_eq!(summary_with("a\0b", LexemizeOptions::default()),
+            vec![(F,0,"a"), (U,1,"\0"), (F,2,"b"), (E,3,"")]);
+        // When it is an error, each NUL is Unexpected.
+        assert_eq!(summary_with("a\0b", options),
+            vec![(F,0,"a"), (X,1,"\0"), (F,2,"b"), (E,3,"")]);
+        assert_eq!(summary_with("\0\0", options),
+            vec![(X,0,"\0"), (X,1,"\0"), (E,2,"")]);
+        // Other Unidentifiable characters are not merged with the NUL.
+        assert_eq!(summary_with("€\0€", options),
+            vec![(U,0,"€"), (X,3,"\0"), (U,4,"€"), (E,7,"")]);
+        // Each NUL is described.
+        assert_eq!(lexemize_with_options("a\0b", options).diagnostics[0].message,
+            "NUL character outside a string or comment");
+        // A NUL in a string or comment is part of that Lexeme.
+        assert_eq!(summary_with("\"a\0b\"", options),
+            vec![(LexemeKind::StringPlain,0,"\"a\0b\""), (E,5,"")]);
+        assert_eq!(summary_with("// a\0b", options),
+            vec![(LexemeKind::CommentInline,0,"// a\0b"), (E,6,"")]);
+        assert_eq!(summary_with("/* \0 */", options),
+            vec![(LexemeKind::CommentMultiline,0,"/* \0 */"), (E,7,"")]);
+    }
+
+    #[test]
+    fn lexemize_compact_trivia() {
+        let options = LexemizeOptions {
+            compact_trivia: true,
+            ..LexemizeOptions::default()
+        };
+        // Whitespace and Comments between two Identifiers.
+        assert_eq!(lexemize_with_options("a /*c*/  /*d*/ b", options).to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             IdentifierFreeword      0  a\n\
+             Trivia                  1   /*c*/  /*d*/ \n\
+             IdentifierFreeword     15  b\n\
+             EndOfInput             16  <EOI>\n"
+        );
+        // Trivia at the start and end, and a lone Whitespace in the middle.
+        assert_eq!(lexemize_with_options("// x\na b\n", options).to_string(),
+            "Lexemes, incl <EOI>: 6\n\
+             Trivia                  0  // x<NL>\n\
+             IdentifierFreeword      5  a\n\
+             Trivia                  6   \n\
+             IdentifierFreeword      7  b\n\
+             Trivia                  8  <NL>\n\
+             EndOfInput              9  <EOI>\n"
+        );
+        // The option is off by default.
+        assert_eq!(lexemize_with_options("a /*c*/ b", LexemizeOptions::default())
+            .to_string(), lexemize("a /*c*/ b").to_string());
+    }
+
+    #[test]
+    fn lexemize_unexpected() {
+        // Unterminated string.
+        assert_eq!(lexemize("x = \"abc").to_string(),
+            "Lexemes, incl <EOI>: 6\n\
+             IdentifierFreeword      0  x\n\
+             WhitespaceTrimmable     1   \n\
+             Punctuation             2  =\n\
+             WhitespaceTrimmable     3   \n\
+             Unexpected              4  \"abc\n\
+             EndOfInput              8  <EOI>\n"
+        );
+        // Unterminated chars, next to a valid char and a lifetime.
+        assert_eq!(lexemize("'1 'a' &'ab c '\\").to_string(),
+            "Lexemes, incl <EOI>: 13\n\
+             Unexpected              0  '\n\
+             NumberDecimal           1  1\n\
+             WhitespaceTrimmable     2   \n\
+             CharacterPlain          3  'a'\n\
+             WhitespaceTrimmable     6   \n\
+             Punctuation             7  &\n\
+             IdentifierLifetime      8  'ab\n\
+             WhitespaceTrimmable    11   \n\
+             IdentifierFreeword     12  c\n\
+             WhitespaceTrimmable    13   \n\
+             Unexpected             14  '\n\
+             Unidentifiable         15  \\\n\
+             EndOfInput             16  <EOI>\n"
+        );
+        // Unterminated multiline comment.
+        assert_eq!(lexemize("x /*abc").to_string(),
+            "Lexemes, incl <EOI>: 4\n\
+             IdentifierFreeword      0  x\n\
+             WhitespaceTrimmable     1   \n\
+             Unexpected              2  /*abc\n\
+             EndOfInput              7  <EOI>\n"
+        );
+        // Malformed number.
+        assert_eq!(lexemize("0b12 + 1").to_string(),
+            "Lexemes, incl <EOI>: 6\n\
+             Unexpected              0  0b12\n\
+             WhitespaceTrimmable     4   \n\
+             Punctuation             5  +\n\
+             WhitespaceTrimmable     6   \n\
+             NumberDecimal           7  1\n\
+             EndOfInput              8  <EOI>\n"
         );
     }
 
@@ -363,7 +3594,104 @@ mod tests {
              WhitespaceTrimmable     3   <NL><NL>\n\
              IdentifierFreeword      6  b\n\
              WhitespaceTrimmable     7  \r \n\
-             WhitespaceTrimmable     9  <EOI>\n"
+             EndOfInput              9  <EOI>\n"
       );
     }
 }
+
+// The accessors on `LexemizeResult`, for tiny inputs where the `<EOI>` sentinel
+// is the only Lexeme, or nearly so.
+#[cfg(test)]
+mod edge_case_tests {
+    use super::{lexemize,lexemize_with_options,LexemizeOptions};
+    use super::super::lexeme::LexemeKind;
+
+    // Returns the snippets of the significant Lexemes.
+    fn significant(orig: &'static str) -> Vec<&'static str> {
+        lexemize(orig).significant().map(|lexeme| lexeme.snippet).collect()
+    }
+
+    // Returns the counts, as a vector of (kind, count) pairs.
+    fn counts(orig: &'static str) -> Vec<(LexemeKind, usize)> {
+        lexemize(orig).counts().into_iter().collect()
+    }
+
+    #[test]
+    fn empty_input() {
+        let result = lexemize("");
+        assert_eq!(result.lexemes.len(), 1); // just the `<EOI>` sentinel
+        assert_eq!(result.to_source(), "");
+        assert_eq!(counts(""), vec![]);
+        assert_eq!(result.count_group(LexemeKind::is_whitespace), 0);
+        assert_eq!(significant(""), Vec::<&str>::new());
+        assert_eq!(result.lexeme_at(0).map(|lexeme| lexeme.kind), Some(LexemeKind::EndOfInput));
+        assert_eq!(result.lexeme_at(1), None);
+        assert_eq!(result.to_ndjson(), // includes the sentinel, like `Display`
+            "{\"kind\":\"EndOfInput\",\"chr\":0,\"snippet\":\"\"}\n");
+        assert_eq!(result.semantic_tokens(), vec![]);
+        assert_eq!(result.diagnostics, vec![]);
+        assert_eq!(result.reasons, vec![]);
+        // Every option still gives just the sentinel.
+        let options = LexemizeOptions {
+            compact_trivia: true,
+            checked: true,
+            split_unidentifiable: true,
+            ..LexemizeOptions::default()
+        };
+        assert_eq!(lexemize_with_options("", options).lexemes, result.lexemes);
+    }
+
+    #[test]
+    fn whitespace_only_input() {
+        let result = lexemize("   ");
+        assert_eq!(result.to_source(), "   ");
+        assert_eq!(counts("   "), vec![(LexemeKind::WhitespaceTrimmable, 1)]);
+        assert_eq!(result.count_group(LexemeKind::is_whitespace), 1);
+        assert_eq!(significant("   "), Vec::<&str>::new());
+        assert_eq!(result.lexeme_at(2).map(|lexeme| lexeme.snippet), Some("   "));
+        assert_eq!(result.lexeme_at(3).map(|lexeme| lexeme.kind), Some(LexemeKind::EndOfInput));
+        assert_eq!(result.semantic_tokens(), vec![]);
+        assert_eq!(lexemize("\n\r\n\t").to_source(), "\n\r\n\t");
+    }
+
+    #[test]
+    fn comment_only_input() {
+        let result = lexemize("/* a */");
+        assert_eq!(result.to_source(), "/* a */");
+        assert_eq!(counts("/* a */"), vec![(LexemeKind::CommentMultiline, 1)]);
+        assert_eq!(result.count_group(LexemeKind::is_comment), 1);
+        assert_eq!(significant("/* a */"), Vec::<&str>::new());
+        assert_eq!(significant("// a\n"), Vec::<&str>::new());
+        assert_eq!(result.semantic_tokens().len(), 1);
+        assert_eq!(result.diagnostics, vec![]);
+        // Compacted into a single Trivia, which is still not significant.
+        let options = LexemizeOptions {
+            compact_trivia: true,
+            ..LexemizeOptions::default()
+        };
+        let result = lexemize_with_options(" /* a */\n", options);
+        assert_eq!(result.to_source(), " /* a */\n");
+        assert_eq!(result.lexemes.len(), 2);
+        assert_eq!(result.significant().count(), 0);
+    }
+
+    #[test]
+    fn single_character_input() {
+        let result = lexemize(";");
+        assert_eq!(result.to_source(), ";");
+        assert_eq!(counts(";"), vec![(LexemeKind::Punctuation, 1)]);
+        assert_eq!(significant(";"), vec![";"]);
+        assert_eq!(result.lexeme_at(0).map(|lexeme| lexeme.snippet), Some(";"));
+        assert_eq!(result.lexeme_at(1).map(|lexeme| lexeme.kind), Some(LexemeKind::EndOfInput));
+        // A single multibyte character.
+        let result = lexemize("€");
+        assert_eq!(result.to_source(), "€");
+        assert_eq!(counts("€"), vec![(LexemeKind::Unidentifiable, 1)]);
+        assert_eq!(significant("€"), vec!["€"]);
+        assert_eq!(result.lexeme_at(2).map(|lexeme| lexeme.snippet), Some("€"));
+        assert_eq!(result.diagnostics.len(), 1);
+        // A single character which starts, but does not complete, a Lexeme.
+        assert_eq!(lexemize("\"").to_source(), "\"");
+        assert_eq!(counts("'"), vec![(LexemeKind::Unexpected, 1)]);
+    }
+}
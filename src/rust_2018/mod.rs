@@ -1,5 +1,12 @@
 //! Tools for transforming Rust 2018 code to a vector of Lexemes.
 
+pub mod check;
 pub mod detect;
+pub mod diagnostic;
+pub mod edition;
 pub mod lexeme;
 pub mod lexemize;
+pub mod refine;
+pub mod semantic_tokens;
+#[cfg(test)]
+mod test_helpers;
@@ -0,0 +1,119 @@
+//! Tags the `#` or `#!` which opens an attribute, like `#[derive(Debug)]`.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const ATTRIBUTE_START: LexemeKind = LexemeKind::AttributeStart;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+
+/// Tags the `#` or `#!` which opens an attribute, like `#[derive(Debug)]`.
+/// 
+/// `detect_punctuation()` splits `#![feature(x)]` into `#`, `!` and `[`. Here,
+/// a `#` immediately followed by `[` is retagged as an AttributeStart. A `#`
+/// and `!` immediately followed by `[` are merged into a single `#!`
+/// AttributeStart. A `#` followed by whitespace is left as Punctuation.
+/// 
+/// This only retags the start of each attribute. It does not try to find the
+/// closing `]`, which `LexemizeResult::attributes()` does afterwards.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, which the merged `snippet` is sliced from
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_attribute()` returns `lexemes`, with the `#` or `#!` of each attribute
+/// replaced by a single `LexemeKind::AttributeStart`.
+pub fn tag_attribute<'a>(
+    orig: &'a str,
+    lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    let mut refined: Vec<Lexeme> = Vec::with_capacity(lexemes.len());
+    let mut i = 0;
+    // Step through each Lexeme.
+    while i < lexemes.len() {
+        let lexeme = lexemes[i];
+        // If this Lexeme is a "#":
+        if is_punctuation(&lexemes, i, "#") {
+            // If it’s followed by "[", this is an outer attribute.
+            if is_punctuation(&lexemes, i + 1, "[") {
+                refined.push(Lexeme { kind: ATTRIBUTE_START, ..lexeme });
+                i += 1;
+                continue;
+            }
+            // If it’s followed by "!" and "[", this is an inner attribute.
+            if is_punctuation(&lexemes, i + 1, "!")
+            && is_punctuation(&lexemes, i + 2, "[") {
+                refined.push(Lexeme {
+                    kind: ATTRIBUTE_START,
+                    chr: lexeme.chr,
+                    snippet: &orig[lexeme.chr..lexeme.chr + 2],
+                });
+                i += 2;
+                continue;
+            }
+        }
+        // Otherwise, keep this Lexeme unchanged.
+        refined.push(lexeme);
+        i += 1;
+    }
+    refined
+}
+
+// Returns true if the Lexeme at `i` is Punctuation, exactly matching `snippet`.
+fn is_punctuation(lexemes: &[Lexeme], i: usize, snippet: &str) -> bool {
+    match lexemes.get(i) {
+        Some(lexeme) => lexeme.kind == PUNCTUATION && lexeme.snippet == snippet,
+        None => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_attribute as tag;
+    use super::super::super::lexeme::LexemeKind;
+    use super::super::super::test_helpers::{lx,summary};
+    use super::ATTRIBUTE_START as A;
+    use super::PUNCTUATION as P;
+    const F: LexemeKind = LexemeKind::IdentifierFreeword;
+    const W: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+    #[test]
+    fn tag_attribute_correct() {
+        // #[derive]
+        let orig = "#[derive]";
+        let lexemes = vec![lx(P,0,"#"), lx(P,1,"["), lx(F,2,"derive"),
+                           lx(P,8,"]")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (A,0,"#"), (P,1,"["), (F,2,"derive"), (P,8,"]")]);
+        // #![feature]
+        let orig = "#![feature]";
+        let lexemes = vec![lx(P,0,"#"), lx(P,1,"!"), lx(P,2,"["),
+                           lx(F,3,"feature"), lx(P,10,"]")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (A,0,"#!"), (P,2,"["), (F,3,"feature"), (P,10,"]")]);
+    }
+
+    #[test]
+    fn tag_attribute_incorrect() {
+        // A bare # followed by whitespace.
+        let orig = "# [x]";
+        let lexemes = vec![lx(P,0,"#"), lx(W,1," "), lx(P,2,"["),
+                           lx(F,3,"x"), lx(P,4,"]")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (P,0,"#"), (W,1," "), (P,2,"["), (F,3,"x"), (P,4,"]")]);
+        // #! not followed by [
+        let orig = "#!x";
+        let lexemes = vec![lx(P,0,"#"), lx(P,1,"!"), lx(F,2,"x")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (P,0,"#"), (P,1,"!"), (F,2,"x")]);
+    }
+
+    #[test]
+    fn tag_attribute_will_not_panic() {
+        assert_eq!(summary(tag("", vec![])), vec![]); // no Lexemes
+        assert_eq!(summary(tag("#", vec![lx(P,0,"#")])), vec![(P,0,"#")]);
+        let lexemes = vec![lx(P,0,"#"), lx(P,1,"!")]; // #! at the end
+        assert_eq!(summary(tag("#!", lexemes)), vec![(P,0,"#"), (P,1,"!")]);
+    }
+}
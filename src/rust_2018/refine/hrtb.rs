@@ -0,0 +1,114 @@
+//! Tags the `for` of a higher-ranked trait bound, like `for<'a> Fn(&'a u8)`.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const HRTB_FOR: LexemeKind = LexemeKind::IdentifierHrtbFor;
+const KEYWORD: LexemeKind = LexemeKind::IdentifierKeyword;
+const LIFETIME: LexemeKind = LexemeKind::IdentifierLifetime;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+const WHITESPACE: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+/// Tags the `for` of a higher-ranked trait bound, like `for<'a> Fn(&'a u8)`.
+/// 
+/// `detect_identifier()` categorises every `for` as a Keyword, whether it
+/// starts a loop like `for x in xs` or an HRTB. Here, a `for` Keyword is
+/// retagged as an HrtbFor if it is followed by a `<` and then a lifetime.
+/// Whitespace is allowed between the `for`, the `<` and the lifetime, so
+/// `for <'a, 'b>` is also tagged.
+/// 
+/// This relies on `tag_lifetime()` having already merged each `'a` into a
+/// single Lifetime Lexeme.
+/// 
+/// ### Arguments
+/// * `_orig` The original Rust code, not needed by `tag_hrtb()`
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_hrtb()` returns `lexemes`, with any HRTB `for` Keywords retagged as
+/// `LexemeKind::IdentifierHrtbFor`.
+pub fn tag_hrtb<'a>(
+    _orig: &'a str,
+    mut lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    // Step through each Lexeme.
+    for i in 0..lexemes.len() {
+        // If this Lexeme is not the `for` Keyword, move on to the next one.
+        if lexemes[i].kind != KEYWORD || lexemes[i].snippet != "for" { continue }
+        // If the next significant Lexeme is a "<", and the one after that
+        // is a Lifetime, this is an HRTB.
+        let lt = next_significant(&lexemes, i + 1);
+        let lifetime = next_significant(&lexemes, lt + 1);
+        if is_punctuation(&lexemes, lt, "<")
+        && lexemes.get(lifetime).map(|l| l.kind) == Some(LIFETIME) {
+            lexemes[i].kind = HRTB_FOR;
+        }
+    }
+    lexemes
+}
+
+// Returns the position of the first non-whitespace Lexeme, from `i` onwards.
+// If there are none, it returns the length of `lexemes`.
+fn next_significant(lexemes: &[Lexeme], mut i: usize) -> usize {
+    while i < lexemes.len() && lexemes[i].kind == WHITESPACE { i += 1 }
+    i
+}
+
+// Returns true if the Lexeme at `i` is Punctuation, exactly matching `snippet`.
+fn is_punctuation(lexemes: &[Lexeme], i: usize, snippet: &str) -> bool {
+    match lexemes.get(i) {
+        Some(lexeme) => lexeme.kind == PUNCTUATION && lexeme.snippet == snippet,
+        None => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_hrtb as tag;
+    use super::super::super::lexeme::LexemeKind;
+    use super::super::super::test_helpers::{kinds,lx};
+    use super::HRTB_FOR as H;
+    use super::KEYWORD as K;
+    use super::LIFETIME as L;
+    use super::PUNCTUATION as P;
+    use super::WHITESPACE as W;
+    const F: LexemeKind = LexemeKind::IdentifierFreeword;
+
+    #[test]
+    fn tag_hrtb_correct() {
+        // for<'a>
+        let orig = vec![lx(K,0,"for"), lx(P,3,"<"), lx(L,4,"'a"), lx(P,6,">")];
+        assert_eq!(kinds(tag("", orig)), vec![H,P,L,P]);
+        // for < 'a
+        let orig = vec![lx(K,0,"for"), lx(W,3," "), lx(P,4,"<"),
+                        lx(W,5," "), lx(L,6,"'a")];
+        assert_eq!(kinds(tag("", orig)), vec![H,W,P,W,L]);
+    }
+
+    #[test]
+    fn tag_hrtb_incorrect() {
+        // for x
+        let orig = vec![lx(K,0,"for"), lx(W,3," "), lx(F,4,"x")];
+        assert_eq!(kinds(tag("", orig)), vec![K,W,F]);
+        // for<T> has no lifetime
+        let orig = vec![lx(K,0,"for"), lx(P,3,"<"), lx(F,4,"T"), lx(P,5,">")];
+        assert_eq!(kinds(tag("", orig)), vec![K,P,F,P]);
+        // if<'a is not `for`
+        let orig = vec![lx(K,0,"if"), lx(P,2,"<"), lx(L,3,"'a")];
+        assert_eq!(kinds(tag("", orig)), vec![K,P,L]);
+        // for<' has not been merged into a Lifetime
+        let orig = vec![lx(K,0,"for"), lx(P,3,"<"), lx(P,4,"'")];
+        assert_eq!(kinds(tag("", orig)), vec![K,P,P]);
+    }
+
+    #[test]
+    fn tag_hrtb_will_not_panic() {
+        assert_eq!(kinds(tag("", vec![])), vec![]); // no Lexemes
+        assert_eq!(kinds(tag("", vec![lx(K,0,"for")])), vec![K]); // lone `for`
+        let orig = vec![lx(K,0,"for"), lx(P,3,"<")]; // for< at the end
+        assert_eq!(kinds(tag("", orig)), vec![K,P]);
+        let orig = vec![lx(K,0,"for"), lx(W,3," ")]; // `for ` at the end
+        assert_eq!(kinds(tag("", orig)), vec![K,W]);
+    }
+}
@@ -0,0 +1,137 @@
+//! Merges a Lifetime and the `:` after it into a Label, like `'outer:`.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const KEYWORD: LexemeKind = LexemeKind::IdentifierKeyword;
+const LABEL: LexemeKind = LexemeKind::Label;
+const LIFETIME: LexemeKind = LexemeKind::IdentifierLifetime;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+const WHITESPACE: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+/// Merges a Lifetime and the `:` after it into a Label, like `'outer:`.
+/// 
+/// A loop label like the `'outer` of `'outer: loop {}` looks just like a
+/// lifetime, so `tag_lifetime()` tags it as one. Here, a Lifetime which is
+/// immediately followed by a lone `:` is merged with it into a single Label
+/// Lexeme. The `::` of a path is a single Punctuation Lexeme, so it can never
+/// be mistaken for the `:` of a label.
+/// 
+/// A lifetime bound, like the `'a: 'b` of `<'a: 'b>`, also has a `:` after
+/// the lifetime. So the `:` must also be followed by `loop`, `while`, `for`
+/// or the `{` of a labelled block, optionally after some Whitespace.
+/// 
+/// This relies on `tag_lifetime()` having already merged each `'a` into a
+/// single Lifetime Lexeme.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, which the merged `snippet` is sliced from
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_label()` returns `lexemes`, with each label and its `:` replaced by a
+/// single `LexemeKind::Label`.
+pub fn tag_label<'a>(
+    orig: &'a str,
+    lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    let mut refined: Vec<Lexeme> = Vec::with_capacity(lexemes.len());
+    let mut i = 0;
+    // Step through each Lexeme.
+    while i < lexemes.len() {
+        let lexeme = lexemes[i];
+        // If this Lexeme is a Lifetime, followed directly by a lone ":", and
+        // then something which can be labelled:
+        if lexeme.kind == LIFETIME
+        && i + 1 < lexemes.len()
+        && lexemes[i+1].kind == PUNCTUATION
+        && lexemes[i+1].snippet == ":"
+        && lexemes[i+1].chr == lexeme.chr + lexeme.snippet.len()
+        && is_labelled(&lexemes, i + 2) {
+            // Record a single Lexeme which covers both, and skip past them.
+            let colon = lexemes[i+1];
+            refined.push(Lexeme {
+                kind: LABEL,
+                chr: lexeme.chr,
+                snippet: &orig[lexeme.chr..colon.chr + 1],
+            });
+            i += 2;
+        } else {
+            // Otherwise, keep this Lexeme unchanged.
+            refined.push(lexeme);
+            i += 1;
+        }
+    }
+    refined
+}
+
+// Returns true if the Lexeme at `i`, after any Whitespace, is a loop keyword
+// or the `{` of a block.
+fn is_labelled(lexemes: &[Lexeme], mut i: usize) -> bool {
+    if lexemes.get(i).map(|lexeme| lexeme.kind) == Some(WHITESPACE) { i += 1 }
+    match lexemes.get(i) {
+        Some(lexeme) if lexeme.kind == KEYWORD =>
+            matches!(lexeme.snippet, "loop" | "while" | "for"),
+        Some(lexeme) => lexeme.kind == PUNCTUATION && lexeme.snippet == "{",
+        None => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_label as tag;
+    use super::super::super::test_helpers::{lx,summary};
+    use super::KEYWORD as K;
+    use super::LABEL as B;
+    use super::LIFETIME as L;
+    use super::PUNCTUATION as P;
+    use super::WHITESPACE as W;
+
+    #[test]
+    fn tag_label_correct() {
+        // 'outer: loop
+        let orig = "'outer: loop";
+        let lexemes = vec![lx(L,0,"'outer"), lx(P,6,":"), lx(W,7," "),
+                           lx(K,8,"loop")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (B,0,"'outer:"), (W,7," "), (K,8,"loop")]);
+        // 'a:while and 'b: {
+        let orig = "'a:while 'b: {";
+        let lexemes = vec![lx(L,0,"'a"), lx(P,2,":"), lx(K,3,"while"),
+                           lx(W,8," "), lx(L,9,"'b"), lx(P,11,":"),
+                           lx(W,12," "), lx(P,13,"{")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (B,0,"'a:"), (K,3,"while"), (W,8," "),
+            (B,9,"'b:"), (W,12," "), (P,13,"{")]);
+    }
+
+    #[test]
+    fn tag_label_incorrect() {
+        // 'a : loop has whitespace before the colon
+        let orig = "'a : loop";
+        let lexemes = vec![lx(L,0,"'a"), lx(W,2," "), lx(P,3,":"),
+                           lx(W,4," "), lx(K,5,"loop")];
+        assert_eq!(summary(tag(orig, lexemes.clone())), summary(lexemes));
+        // 'a: 'b is a lifetime bound
+        let orig = "'a: 'b";
+        let lexemes = vec![lx(L,0,"'a"), lx(P,2,":"), lx(W,3," "),
+                           lx(L,4,"'b")];
+        assert_eq!(summary(tag(orig, lexemes.clone())), summary(lexemes));
+        // 'a::loop has a path separator, not a colon
+        let orig = "'a::loop";
+        let lexemes = vec![lx(L,0,"'a"), lx(P,2,"::"), lx(K,4,"loop")];
+        assert_eq!(summary(tag(orig, lexemes.clone())), summary(lexemes));
+    }
+
+    #[test]
+    fn tag_label_will_not_panic() {
+        assert_eq!(summary(tag("", vec![])), vec![]); // no Lexemes
+        assert_eq!(summary(tag("'a", vec![lx(L,0,"'a")])), vec![(L,0,"'a")]);
+        let lexemes = vec![lx(L,0,"'a"), lx(P,2,":")];
+        assert_eq!(summary(tag("'a:", lexemes)), vec![(L,0,"'a"), (P,2,":")]);
+        let lexemes = vec![lx(L,0,"'a"), lx(P,2,":"), lx(W,3," ")];
+        assert_eq!(summary(tag("'a: ", lexemes)),
+            vec![(L,0,"'a"), (P,2,":"), (W,3," ")]);
+    }
+}
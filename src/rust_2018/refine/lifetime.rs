@@ -0,0 +1,123 @@
+//! Merges a `'` and the identifier after it into a Lifetime, like `'static`.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const LIFETIME: LexemeKind = LexemeKind::IdentifierLifetime;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+
+/// Merges a `'` and the identifier after it into a Lifetime, like `'static`.
+/// 
+/// `detect_character()` does not detect `'a` or `'static`, because there is no
+/// closing single-quote. So they are detected as a `'` Punctuation Lexeme,
+/// followed by a Freeword `a` or a Keyword `static`. Here, those two Lexemes
+/// are merged into a single Lifetime Lexeme. The anonymous lifetime `'_` is
+/// merged in the same way, from a `'` and a wildcard `_` Punctuation.
+/// 
+/// This is lexically permissive, so `'fn` is also a Lifetime, even though a
+/// lifetime cannot be named after a keyword. `flag_keyword_lifetimes()` can
+/// flag those in checked mode.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, which the merged `snippet` is sliced from
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_lifetime()` returns `lexemes`, with each `'` and identifier pair
+/// replaced by a single `LexemeKind::IdentifierLifetime`.
+pub fn tag_lifetime<'a>(
+    orig: &'a str,
+    lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    let mut refined: Vec<Lexeme> = Vec::with_capacity(lexemes.len());
+    let mut i = 0;
+    // Step through each Lexeme.
+    while i < lexemes.len() {
+        let lexeme = lexemes[i];
+        // If this Lexeme is a lone "'", and the next one is an identifier or
+        // the wildcard "_":
+        if lexeme.kind == PUNCTUATION
+        && lexeme.snippet == "'"
+        && i + 1 < lexemes.len()
+        && is_name(&lexemes[i+1]) {
+            // Record a single Lexeme which covers both, and skip past them.
+            let next = lexemes[i+1];
+            refined.push(Lexeme {
+                kind: LIFETIME,
+                chr: lexeme.chr,
+                snippet: &orig[lexeme.chr..next.chr + next.snippet.len()],
+            });
+            i += 2;
+        } else {
+            // Otherwise, keep this Lexeme unchanged.
+            refined.push(lexeme);
+            i += 1;
+        }
+    }
+    refined
+}
+
+// Returns true if the Lexeme was produced by `detect_identifier()`, or is the
+// wildcard "_".
+fn is_name(lexeme: &Lexeme) -> bool {
+    lexeme.kind == LexemeKind::IdentifierFreeword
+    || lexeme.kind == LexemeKind::IdentifierKeyword
+    || lexeme.kind == LexemeKind::IdentifierStdType
+    || lexeme.kind == PUNCTUATION && lexeme.snippet == "_"
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_lifetime as tag;
+    use super::super::super::lexeme::LexemeKind;
+    use super::super::super::test_helpers::{lx,summary};
+    use super::LIFETIME as L;
+    use super::PUNCTUATION as P;
+    const F: LexemeKind = LexemeKind::IdentifierFreeword;
+    const K: LexemeKind = LexemeKind::IdentifierKeyword;
+    const S: LexemeKind = LexemeKind::IdentifierStdType;
+    const W: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+    #[test]
+    fn tag_lifetime_correct() {
+        // &'static str
+        let orig = "&'static str";
+        let lexemes = vec![lx(P,0,"&"), lx(P,1,"'"), lx(K,2,"static"),
+                           lx(W,8," "), lx(S,9,"str")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (P,0,"&"), (L,1,"'static"), (W,8," "), (S,9,"str")]);
+        // 'a,'b
+        let orig = "'a,'b";
+        let lexemes = vec![lx(P,0,"'"), lx(F,1,"a"), lx(P,2,","),
+                           lx(P,3,"'"), lx(F,4,"b")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (L,0,"'a"), (P,2,","), (L,3,"'b")]);
+        // '_ and 'fn
+        let orig = "'_ 'fn";
+        let lexemes = vec![lx(P,0,"'"), lx(P,1,"_"), lx(W,2," "),
+                           lx(P,3,"'"), lx(K,4,"fn")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (L,0,"'_"), (W,2," "), (L,3,"'fn")]);
+    }
+
+    #[test]
+    fn tag_lifetime_incorrect() {
+        // ' a has whitespace after the single-quote
+        let orig = "' a";
+        let lexemes = vec![lx(P,0,"'"), lx(W,1," "), lx(F,2,"a")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![
+            (P,0,"'"), (W,1," "), (F,2,"a")]);
+        // '& is followed by Punctuation, not an identifier
+        let orig = "'&";
+        let lexemes = vec![lx(P,0,"'"), lx(P,1,"&")];
+        assert_eq!(summary(tag(orig, lexemes)), vec![(P,0,"'"), (P,1,"&")]);
+    }
+
+    #[test]
+    fn tag_lifetime_will_not_panic() {
+        assert_eq!(summary(tag("", vec![])), vec![]); // no Lexemes
+        assert_eq!(summary(tag("'", vec![lx(P,0,"'")])), vec![(P,0,"'")]);
+        assert_eq!(summary(tag("a", vec![lx(F,0,"a")])), vec![(F,0,"a")]);
+    }
+}
@@ -0,0 +1,75 @@
+//! Tags the name of an invoked macro, like the `println` of `println!()`.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const FREEWORD: LexemeKind = LexemeKind::IdentifierFreeword;
+const MACRO: LexemeKind = LexemeKind::IdentifierMacro;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+
+/// Tags the name of an invoked macro, like the `println` of `println!()`.
+/// 
+/// `detect_identifier()` stops at the `!`, so it categorises the `foo` of
+/// `foo!` as a Freeword. Here, that Freeword is retagged as a Macro if it is
+/// immediately followed by a lone `!`. The `!=` of `foo != bar` is a single
+/// Punctuation Lexeme, so it can never be mistaken for a macro invocation.
+/// 
+/// ### Arguments
+/// * `_orig` The original Rust code, not needed by `tag_macro_invocation()`
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_macro_invocation()` returns `lexemes`, with any invoked macro names
+/// retagged as `LexemeKind::IdentifierMacro`.
+pub fn tag_macro_invocation<'a>(
+    _orig: &'a str,
+    mut lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    // Step through each Lexeme, from the second to the last.
+    for i in 1..lexemes.len() {
+        // If this Lexeme is a lone "!", and the previous one is a Freeword:
+        if lexemes[i].kind == PUNCTUATION
+        && lexemes[i].snippet == "!"
+        && lexemes[i-1].kind == FREEWORD {
+            // Retag the Freeword as a macro.
+            lexemes[i-1].kind = MACRO;
+        }
+    }
+    lexemes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_macro_invocation as tag;
+    use super::super::super::lexeme::LexemeKind;
+    use super::super::super::test_helpers::{kinds,lx};
+    use super::FREEWORD as F;
+    use super::MACRO as M;
+    use super::PUNCTUATION as P;
+    const W: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+    #[test]
+    fn tag_macro_invocation_correct() {
+        // println!(
+        let orig = vec![lx(F,0,"println"), lx(P,7,"!"), lx(P,8,"(")];
+        assert_eq!(kinds(tag("", orig)), vec![M,P,P]);
+        // foo != bar
+        let orig = vec![lx(F,0,"foo"), lx(W,3," "), lx(P,4,"!="),
+                        lx(W,6," "), lx(F,7,"bar")];
+        assert_eq!(kinds(tag("", orig)), vec![F,W,P,W,F]);
+        // foo !x is not a macro, because of the space
+        let orig = vec![lx(F,0,"foo"), lx(W,3," "), lx(P,4,"!"), lx(F,5,"x")];
+        assert_eq!(kinds(tag("", orig)), vec![F,W,P,F]);
+        // vec!
+        let orig = vec![lx(F,0,"vec"), lx(P,3,"!")];
+        assert_eq!(kinds(tag("", orig)), vec![M,P]);
+    }
+
+    #[test]
+    fn tag_macro_invocation_will_not_panic() {
+        assert_eq!(kinds(tag("", vec![])), vec![]);            // no Lexemes
+        assert_eq!(kinds(tag("", vec![lx(P,0,"!")])), vec![P]); // lone "!"
+        assert_eq!(kinds(tag("", vec![lx(F,0,"a")])), vec![F]); // lone Freeword
+    }
+}
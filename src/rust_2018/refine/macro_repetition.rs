@@ -0,0 +1,194 @@
+//! Tags the `*`, `+` or `?` after a `$(...)` group in a `macro_rules!`.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const KEYWORD: LexemeKind = LexemeKind::IdentifierKeyword;
+const MACRO: LexemeKind = LexemeKind::IdentifierMacro;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+const REPEAT_OP: LexemeKind = LexemeKind::MacroRepeatOp;
+const WHITESPACE: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+/// Tags the `*`, `+` or `?` after a `$(...)` group in a `macro_rules!`.
+/// 
+/// In a `macro_rules!` matcher or transcriber, `$($x),*` means ‘zero or more
+/// `$x`, separated by commas’. The `*`, `+` and `?` repetition operators are
+/// quite different to multiplication, addition or the try operator, so here
+/// they are retagged as `MacroRepeatOp`. An optional separator, like the `,`
+/// of `$($x),*`, may appear between the `)` and a `*` or `+`, but not a `?`.
+/// 
+/// Only the body of a `macro_rules!` definition is looked at, so the `?` of
+/// `x?` is left alone. This relies on `tag_macro_invocation()` having already
+/// tagged `macro_rules` as a Macro. When `relex()` runs the refiners again,
+/// `tag_macro_rules()` may have retagged it as a Keyword, which is also fine.
+/// 
+/// ### Arguments
+/// * `_orig` The original Rust code, not needed by `tag_macro_repetition()`
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_macro_repetition()` returns `lexemes`, with any repetition operators
+/// retagged as `LexemeKind::MacroRepeatOp`.
+pub fn tag_macro_repetition<'a>(
+    _orig: &'a str,
+    mut lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    let len = lexemes.len();
+    let mut i = 0;
+    // Step through each Lexeme.
+    while i < len {
+        // If this Lexeme does not begin a `macro_rules!`, move on to the next.
+        if lexemes[i].kind != MACRO && lexemes[i].kind != KEYWORD
+        || lexemes[i].snippet != "macro_rules" {
+            i += 1;
+            continue;
+        }
+        // The body starts at the first opening bracket after the macro name.
+        let start = match (i+1..len).find(|&j| is_opening(&lexemes[j])) {
+            Some(start) => start,
+            None => break,
+        };
+        // Tag the repetition operators in the body, and skip past it.
+        let end = find_closing(&lexemes, start);
+        tag_body(&mut lexemes, start, end);
+        i = end;
+    }
+    lexemes
+}
+
+// Tags each repetition operator between the Lexemes at `start` and `end`.
+fn tag_body(lexemes: &mut [Lexeme], start: usize, end: usize) {
+    for i in start..end {
+        // If this Lexeme is not a "$" followed by a "(", move on to the next.
+        if ! is_punctuation(lexemes, i, "$")
+        || ! is_punctuation(lexemes, i + 1, "(") { continue }
+        // Find the first significant Lexeme after the group’s ")".
+        let close = find_closing(lexemes, i + 1);
+        let op = next_significant(lexemes, close + 1);
+        if op >= end { continue }
+        // If it’s a repetition operator, tag it.
+        if is_punctuation(lexemes, op, "*")
+        || is_punctuation(lexemes, op, "+")
+        || is_punctuation(lexemes, op, "?") {
+            lexemes[op].kind = REPEAT_OP;
+            continue;
+        }
+        // Otherwise, it could be a separator, followed by a "*" or "+".
+        if is_opening(&lexemes[op]) || is_closing(&lexemes[op]) { continue }
+        let op = next_significant(lexemes, op + 1);
+        if op >= end { continue }
+        if is_punctuation(lexemes, op, "*")
+        || is_punctuation(lexemes, op, "+") {
+            lexemes[op].kind = REPEAT_OP;
+        }
+    }
+}
+
+// Returns the position of the bracket which closes the one at `open`. If the
+// brackets are unbalanced, it returns the length of `lexemes`.
+fn find_closing(lexemes: &[Lexeme], open: usize) -> usize {
+    let mut depth = 0;
+    for (i, lexeme) in lexemes.iter().enumerate().skip(open) {
+        if is_opening(lexeme) { depth += 1 }
+        if is_closing(lexeme) { depth -= 1 }
+        if depth == 0 { return i }
+    }
+    lexemes.len()
+}
+
+// Returns the position of the first non-whitespace Lexeme, from `i` onwards.
+// If there are none, it returns the length of `lexemes`.
+fn next_significant(lexemes: &[Lexeme], mut i: usize) -> usize {
+    while i < lexemes.len() && lexemes[i].kind == WHITESPACE { i += 1 }
+    i
+}
+
+// Returns true if the Lexeme at `i` is Punctuation, exactly matching `snippet`.
+fn is_punctuation(lexemes: &[Lexeme], i: usize, snippet: &str) -> bool {
+    match lexemes.get(i) {
+        Some(lexeme) => lexeme.kind == PUNCTUATION && lexeme.snippet == snippet,
+        None => false,
+    }
+}
+
+// Returns true if the Lexeme is "(", "[" or "{".
+fn is_opening(lexeme: &Lexeme) -> bool {
+    lexeme.kind == PUNCTUATION && ["(", "[", "{"].contains(&lexeme.snippet)
+}
+
+// Returns true if the Lexeme is ")", "]" or "}".
+fn is_closing(lexeme: &Lexeme) -> bool {
+    lexeme.kind == PUNCTUATION && [")", "]", "}"].contains(&lexeme.snippet)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_macro_repetition as tag;
+    use super::super::super::lexeme::{Lexeme,LexemeKind};
+    use super::super::super::test_helpers::{kinds,lx};
+    use super::MACRO as M;
+    use super::PUNCTUATION as P;
+    use super::REPEAT_OP as R;
+    use super::WHITESPACE as W;
+    const F: LexemeKind = LexemeKind::IdentifierFreeword;
+
+    // Returns the Lexemes of `macro_rules! m {`, followed by `body` and `}`.
+    fn wrap(body: Vec<Lexeme>) -> Vec<Lexeme> {
+        let mut lexemes = vec![lx(M,0,"macro_rules"), lx(P,11,"!"),
+            lx(W,12," "), lx(F,13,"m"), lx(W,14," "), lx(P,15,"{")];
+        lexemes.extend(body);
+        lexemes.push(lx(P,99,"}"));
+        lexemes
+    }
+
+    #[test]
+    fn tag_macro_repetition_correct() {
+        // $($x),*
+        let body = vec![lx(P,0,"$"), lx(P,0,"("), lx(P,0,"$"), lx(F,0,"x"),
+                        lx(P,0,")"), lx(P,0,","), lx(P,0,"*")];
+        assert_eq!(kinds(tag("", wrap(body)))[6..], [P,P,P,F,P,P,R,P]);
+        // $(a)+
+        let body = vec![lx(P,0,"$"), lx(P,0,"("), lx(F,0,"a"),
+                        lx(P,0,")"), lx(P,0,"+")];
+        assert_eq!(kinds(tag("", wrap(body)))[6..], [P,P,F,P,R,P]);
+        // $(b)?
+        let body = vec![lx(P,0,"$"), lx(P,0,"("), lx(F,0,"b"),
+                        lx(P,0,")"), lx(P,0,"?")];
+        assert_eq!(kinds(tag("", wrap(body)))[6..], [P,P,F,P,R,P]);
+        // $( (c) ) *
+        let body = vec![lx(P,0,"$"), lx(P,0,"("), lx(P,0,"("), lx(F,0,"c"),
+            lx(P,0,")"), lx(P,0,")"), lx(W,0," "), lx(P,0,"*")];
+        assert_eq!(kinds(tag("", wrap(body)))[6..], [P,P,P,F,P,P,W,R,P]);
+    }
+
+    #[test]
+    fn tag_macro_repetition_incorrect() {
+        // x? outside a macro_rules!
+        let orig = vec![lx(F,0,"x"), lx(P,1,"?")];
+        assert_eq!(kinds(tag("", orig)), [F,P]);
+        // $(a)* outside a macro_rules!
+        let orig = vec![lx(P,0,"$"), lx(P,1,"("), lx(F,2,"a"),
+                        lx(P,3,")"), lx(P,4,"*")];
+        assert_eq!(kinds(tag("", orig)), [P,P,F,P,P]);
+        // $(a),? is not valid, because `?` cannot have a separator
+        let body = vec![lx(P,0,"$"), lx(P,0,"("), lx(F,0,"a"),
+                        lx(P,0,")"), lx(P,0,","), lx(P,0,"?")];
+        assert_eq!(kinds(tag("", wrap(body)))[6..], [P,P,F,P,P,P,P]);
+        // $x * 2 is not a group
+        let body = vec![lx(P,0,"$"), lx(F,0,"x"), lx(P,0,"*")];
+        assert_eq!(kinds(tag("", wrap(body)))[6..], [P,F,P,P]);
+    }
+
+    #[test]
+    fn tag_macro_repetition_will_not_panic() {
+        assert_eq!(kinds(tag("", vec![])), []); // no Lexemes
+        let orig = vec![lx(M,0,"macro_rules")]; // no body
+        assert_eq!(kinds(tag("", orig)), [M]);
+        let orig = vec![lx(M,0,"macro_rules"), lx(P,11,"{"), lx(P,12,"$"),
+                        lx(P,13,"(")]; // unbalanced
+        assert_eq!(kinds(tag("", orig)), [M,P,P,P]);
+        let body = vec![lx(P,0,"$"), lx(P,0,"(")]; // unclosed group
+        assert_eq!(kinds(tag("", wrap(body)))[6..], [P,P,P]);
+    }
+}
@@ -0,0 +1,101 @@
+//! Tags the `macro_rules` of a `macro_rules!` definition as a Keyword.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const KEYWORD: LexemeKind = LexemeKind::IdentifierKeyword;
+const MACRO: LexemeKind = LexemeKind::IdentifierMacro;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+
+/// Tags the `macro_rules` of a `macro_rules!` definition as a Keyword.
+/// 
+/// `macro_rules` is a weak keyword, which only has a special meaning when it
+/// defines a macro, like `macro_rules! foo { ... }`. So `detect_identifier()`
+/// categorises it as a Freeword, and `tag_macro_invocation()` then tags it as
+/// a Macro, because it is followed by `!`. Here, it is retagged as a Keyword
+/// if the `!` is followed by an Identifier or `{`, ignoring any trivia. A
+/// variable named `macro_rules` is left alone.
+/// 
+/// `macro_rules!` is a definition, not an invocation, but it stays a Macro
+/// until `tag_macro_repetition()` has used it to find the definition’s body.
+/// 
+/// ### Arguments
+/// * `_orig` The original Rust code, not needed by `tag_macro_rules()`
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_macro_rules()` returns `lexemes`, with the `macro_rules` of each
+/// definition retagged as `LexemeKind::IdentifierKeyword`.
+pub fn tag_macro_rules<'a>(
+    _orig: &'a str,
+    mut lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    for i in 0..lexemes.len() {
+        // If this Lexeme is not the Macro `macro_rules`, followed by a lone
+        // "!", move on to the next.
+        if lexemes[i].kind != MACRO
+        || lexemes[i].snippet != "macro_rules"
+        || ! lexemes.get(i + 1).is_some_and(|bang|
+            bang.kind == PUNCTUATION && bang.snippet == "!") {
+            continue;
+        }
+        // If the next significant Lexeme is the name or the body, this is a
+        // definition.
+        let next = lexemes[i+2..].iter().find(|lexeme| ! lexeme.is_trivia());
+        if next.is_some_and(|next| next.kind.is_identifier()
+            || next.kind == PUNCTUATION && next.snippet == "{") {
+            lexemes[i].kind = KEYWORD;
+        }
+    }
+    lexemes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_macro_rules as tag;
+    use super::super::super::lexeme::LexemeKind;
+    use super::super::super::test_helpers::{kinds,lx};
+    use super::KEYWORD as K;
+    use super::MACRO as M;
+    use super::PUNCTUATION as P;
+    const E: LexemeKind = LexemeKind::EndOfInput;
+    const F: LexemeKind = LexemeKind::IdentifierFreeword;
+    const N: LexemeKind = LexemeKind::NumberDecimal;
+    const W: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+    #[test]
+    fn tag_macro_rules_correct() {
+        // macro_rules! foo {
+        let orig = vec![lx(M,0,"macro_rules"), lx(P,11,"!"), lx(W,12," "),
+                        lx(F,13,"foo"), lx(W,16," "), lx(P,17,"{")];
+        assert_eq!(kinds(tag("", orig)), vec![K,P,W,F,W,P]);
+        // macro_rules!{
+        let orig = vec![lx(M,0,"macro_rules"), lx(P,11,"!"), lx(P,12,"{")];
+        assert_eq!(kinds(tag("", orig)), vec![K,P,P]);
+    }
+
+    #[test]
+    fn tag_macro_rules_incorrect() {
+        // let macro_rules = 1;
+        let orig = vec![lx(K,0,"let"), lx(W,3," "), lx(F,4,"macro_rules"),
+                        lx(W,15," "), lx(P,16,"="), lx(W,17," "), lx(N,18,"1")];
+        assert_eq!(kinds(tag("", orig)), vec![K,W,F,W,P,W,N]);
+        // macro_rules!(x) is an invocation, not a definition
+        let orig = vec![lx(M,0,"macro_rules"), lx(P,11,"!"), lx(P,12,"("),
+                        lx(F,13,"x"), lx(P,14,")")];
+        assert_eq!(kinds(tag("", orig)), vec![M,P,P,F,P]);
+        // foo! bar is some other Macro
+        let orig = vec![lx(M,0,"foo"), lx(P,3,"!"), lx(W,4," "), lx(F,5,"bar")];
+        assert_eq!(kinds(tag("", orig)), vec![M,P,W,F]);
+    }
+
+    #[test]
+    fn tag_macro_rules_will_not_panic() {
+        assert_eq!(kinds(tag("", vec![])), vec![]); // no Lexemes
+        let orig = vec![lx(M,0,"macro_rules")]; // no "!"
+        assert_eq!(kinds(tag("", orig)), vec![M]);
+        let orig = vec![lx(M,0,"macro_rules"), lx(P,11,"!"), lx(E,12,"")];
+        assert_eq!(kinds(tag("", orig)), vec![M,P,E]); // nothing after "!"
+    }
+}
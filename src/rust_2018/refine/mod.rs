@@ -0,0 +1,12 @@
+//! Functions for refining the Lexemes found in Rust 2018 code.
+
+pub mod attribute;
+pub mod hrtb;
+pub mod label;
+pub mod lifetime;
+pub mod macro_invocation;
+pub mod macro_repetition;
+pub mod macro_rules;
+pub mod trivia;
+pub mod tuple_index;
+pub mod union;
@@ -0,0 +1,112 @@
+//! Merges each run of Whitespace and Comments into a single Trivia Lexeme.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const TRIVIA: LexemeKind = LexemeKind::Trivia;
+
+/// Merges each run of Whitespace and Comments into a single Trivia Lexeme.
+/// 
+/// Some parsers only need to know that there was trivia between two Lexemes,
+/// not what it was made of. Merging each run into one Lexeme makes the result
+/// much shorter for heavily commented code. A lone Whitespace or Comment
+/// Lexeme also becomes Trivia, so that every gap is categorised the same way.
+/// 
+/// Trivia is found by `Lexeme::is_trivia()`. A byte order mark and the `<EOI>`
+/// sentinel are left unchanged.
+/// 
+/// ### Arguments
+/// * `orig` The original Rust code, which the merged `snippet` is sliced from
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `compact_trivia()` returns `lexemes`, with each run of Whitespace and
+/// Comments replaced by a single `LexemeKind::Trivia`.
+pub fn compact_trivia<'a>(
+    orig: &'a str,
+    lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    // The `<EOI>` sentinel should not be merged, so stop before it, if the
+    // Lexemes end with one.
+    let len = lexemes.len();
+    let has_eoi = lexemes.last()
+        .is_some_and(|last| last.kind == LexemeKind::EndOfInput);
+    let end = if has_eoi { len - 1 } else { len };
+    let mut refined: Vec<Lexeme> = Vec::with_capacity(len);
+    let mut i = 0;
+    // Step through each Lexeme, apart from the `<EOI>`.
+    while i < end {
+        // If this Lexeme is not trivia, keep it unchanged.
+        if ! is_mergeable(&lexemes[i]) {
+            refined.push(lexemes[i]);
+            i += 1;
+            continue;
+        }
+        // Otherwise, find the end of the run, and record a single Lexeme which
+        // covers all of it.
+        let start = lexemes[i].chr;
+        while i < end && is_mergeable(&lexemes[i]) { i += 1 }
+        let last = lexemes[i-1];
+        refined.push(Lexeme {
+            kind: TRIVIA,
+            chr: start,
+            snippet: &orig[start..last.chr + last.snippet.len()],
+        });
+    }
+    // Keep the `<EOI>` sentinel, if there is one.
+    if has_eoi { refined.push(lexemes[end]) }
+    refined
+}
+
+// Returns true if `lexeme` should be merged. This is `Lexeme::is_trivia()`,
+// apart from a byte order mark, which keeps its own kind.
+fn is_mergeable(lexeme: &Lexeme) -> bool {
+    lexeme.is_trivia() && lexeme.kind != LexemeKind::ByteOrderMark
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::compact_trivia as compact;
+    use super::super::super::lexeme::LexemeKind;
+    use super::super::super::test_helpers::{lx,summary};
+    use super::TRIVIA as T;
+    const B: LexemeKind = LexemeKind::ByteOrderMark;
+    const C: LexemeKind = LexemeKind::CommentMultiline;
+    const E: LexemeKind = LexemeKind::EndOfInput;
+    const F: LexemeKind = LexemeKind::IdentifierFreeword;
+    const W: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+    #[test]
+    fn compact_trivia_correct() {
+        // a /*c*/  /*d*/ b
+        let orig = "a /*c*/  /*d*/ b";
+        let lexemes = vec![lx(F,0,"a"), lx(W,1," "), lx(C,2,"/*c*/"),
+            lx(W,7,"  "), lx(C,9,"/*d*/"), lx(W,14," "), lx(F,15,"b"),
+            lx(E,16,"")];
+        assert_eq!(summary(compact(orig, lexemes)), vec![
+            (F,0,"a"), (T,1," /*c*/  /*d*/ "), (F,15,"b"), (E,16,"")]);
+        // Trailing Whitespace is merged, but the `<EOI>` is not.
+        let orig = "a /*c*/ ";
+        let lexemes = vec![lx(F,0,"a"), lx(W,1," "), lx(C,2,"/*c*/"),
+            lx(W,7," "), lx(E,8,"")];
+        assert_eq!(summary(compact(orig, lexemes)), vec![
+            (F,0,"a"), (T,1," /*c*/ "), (E,8,"")]);
+    }
+
+    #[test]
+    fn compact_trivia_will_not_panic() {
+        assert_eq!(summary(compact("", vec![])), vec![]); // no Lexemes
+        let lexemes = vec![lx(E,0,"")]; // just the `<EOI>`
+        assert_eq!(summary(compact("", lexemes)), vec![(E,0,"")]);
+        let lexemes = vec![lx(B,0,"\u{FEFF}"), lx(W,3," "), lx(E,4,"")];
+        assert_eq!(summary(compact("\u{FEFF} ", lexemes)), vec![
+            (B,0,"\u{FEFF}"), (T,3," "), (E,4,"")]); // a byte order mark
+        // With no `<EOI>`, the last Lexeme is kept, or merged if it’s trivia.
+        let lexemes = vec![lx(F,0,"a")];
+        assert_eq!(summary(compact("a", lexemes)), vec![(F,0,"a")]);
+        let lexemes = vec![lx(F,0,"a"), lx(W,1," "), lx(C,2,"/*c*/")];
+        assert_eq!(summary(compact("a /*c*/", lexemes)), vec![
+            (F,0,"a"), (T,1," /*c*/")]);
+    }
+}
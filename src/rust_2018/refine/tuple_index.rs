@@ -0,0 +1,145 @@
+//! Splits a tuple index off the rest of a Number, like the `0.1` of `x.0.1`.
+
+use alloc::vec::Vec;
+
+use super::super::detect::identifier::detect_identifier;
+use super::super::detect::number::{detect_number,detect_number_tuple_index};
+use super::super::detect::punctuation::detect_punctuation;
+use super::super::lexeme::{Lexeme,LexemeKind};
+const DECIMAL: LexemeKind = LexemeKind::NumberDecimal;
+const FLOAT: LexemeKind = LexemeKind::NumberDecimalFloat;
+const PUNCTUATION: LexemeKind = LexemeKind::Punctuation;
+const UNDETECTED: LexemeKind = LexemeKind::Undetected;
+
+/// Splits a tuple index off the rest of a Number, like the `0.1` of `x.0.1`.
+/// 
+/// `detect_number()` has no context, so it reads the `0.1` of `x.0.1` as a
+/// float, and the `1e2` of `x.1e2` as a float with an exponent. But a Number
+/// which immediately follows a lone `.` is a tuple index, which is just ascii
+/// digits, as found by `detect_number_tuple_index()`. Here, the rest of any
+/// such Number is lexemized again, on its own. A dot in the rest is followed
+/// by another tuple index, if possible. So `x.0.1` becomes `x`, `.`, `0`, `.`,
+/// `1`, the `0.` of `x.0.len()` becomes `0` and `.`, and `x.1e2` becomes `x`,
+/// `.`, `1` and the Identifier `e2`.
+/// 
+/// A float like `1.0` which does not follow a `.` is left alone.
+/// 
+/// ### Arguments
+/// * `_orig` The original Rust code, not needed by `tag_tuple_index()`
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_tuple_index()` returns `lexemes`, with any tuple indices split off.
+pub fn tag_tuple_index<'a>(
+    _orig: &'a str,
+    lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    let mut refined = Vec::with_capacity(lexemes.len());
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        // If this Lexeme is a Number which follows a lone ".", and is more
+        // than just a tuple index, lexemize it again.
+        if (lexeme.kind == DECIMAL || lexeme.kind == FLOAT)
+        && i > 0
+        && lexemes[i-1].kind == PUNCTUATION
+        && lexemes[i-1].snippet == "."
+        && detect_number_tuple_index(lexeme.snippet, 0).1 != lexeme.snippet.len() {
+            split_tuple_index(lexeme, &mut refined);
+            continue;
+        }
+        refined.push(*lexeme);
+    }
+    refined
+}
+
+// Lexemizes a Number which follows a lone ".", and pushes the parts. Only the
+// snippet is looked at, so no part can run past the end of the Number.
+fn split_tuple_index<'a>(number: &Lexeme<'a>, refined: &mut Vec<Lexeme<'a>>) {
+    let snippet = number.snippet;
+    let mut pos = 0;
+    let mut after_dot = true;
+    while pos < snippet.len() {
+        // Directly after a dot, try for a tuple index first.
+        let (mut kind, mut end) = if after_dot
+            { detect_number_tuple_index(snippet, pos) } else { (UNDETECTED, 0) };
+        for detector in [detect_identifier, detect_number, detect_punctuation] {
+            if kind != UNDETECTED { break }
+            let (k, e) = detector(snippet, pos);
+            kind = k;
+            end = e;
+        }
+        // Just in case, keep anything left over as part of the Number.
+        if kind == UNDETECTED { kind = DECIMAL; end = snippet.len() }
+        let part = &snippet[pos..end];
+        refined.push(Lexeme { kind, chr: number.chr + pos, snippet: part });
+        after_dot = kind == PUNCTUATION && part == ".";
+        pos = end;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_tuple_index as tag;
+    use super::super::super::lexeme::LexemeKind;
+    use super::super::super::test_helpers::{lx,summary};
+    use super::DECIMAL as D;
+    use super::FLOAT as R;
+    use super::PUNCTUATION as P;
+    const F: LexemeKind = LexemeKind::IdentifierFreeword;
+
+    #[test]
+    fn tag_tuple_index_correct() {
+        // x.0 is left alone.
+        let orig = vec![lx(F,0,"x"), lx(P,1,"."), lx(D,2,"0")];
+        assert_eq!(summary(tag("", orig)), vec![(F,0,"x"), (P,1,"."), (D,2,"0")]);
+        // x.0.1 is split.
+        let orig = vec![lx(F,0,"x"), lx(P,1,"."), lx(R,2,"0.1")];
+        assert_eq!(summary(tag("", orig)), vec![
+            (F,0,"x"), (P,1,"."), (D,2,"0"), (P,3,"."), (D,4,"1")]);
+        // tuple.12 is left alone.
+        let orig = vec![lx(F,0,"tuple"), lx(P,5,"."), lx(D,6,"12")];
+        assert_eq!(summary(tag("", orig)), vec![
+            (F,0,"tuple"), (P,5,"."), (D,6,"12")]);
+        // The 0. of x.0.len() is split into 0 and "."
+        let orig = vec![lx(F,0,"x"), lx(P,1,"."), lx(R,2,"0."), lx(F,4,"len")];
+        assert_eq!(summary(tag("", orig)), vec![
+            (F,0,"x"), (P,1,"."), (D,2,"0"), (P,3,"."), (F,4,"len")]);
+    }
+
+    #[test]
+    fn tag_tuple_index_exponent() {
+        // x.1e2 is the index 1 then the Identifier e2.
+        let orig = vec![lx(F,0,"x"), lx(P,1,"."), lx(R,2,"1e2")];
+        assert_eq!(summary(tag("", orig)), vec![
+            (F,0,"x"), (P,1,"."), (D,2,"1"), (F,3,"e2")]);
+        // x.1e+2 is the index 1, then e, + and 2.
+        let orig = vec![lx(F,0,"x"), lx(P,1,"."), lx(R,2,"1e+2")];
+        assert_eq!(summary(tag("", orig)), vec![
+            (F,0,"x"), (P,1,"."), (D,2,"1"), (F,3,"e"), (P,4,"+"), (D,5,"2")]);
+        // x.0.1e2 is the index 0, ".", the index 1 then the Identifier e2.
+        let orig = vec![lx(F,0,"x"), lx(P,1,"."), lx(R,2,"0.1e2")];
+        assert_eq!(summary(tag("", orig)), vec![
+            (F,0,"x"), (P,1,"."), (D,2,"0"), (P,3,"."), (D,4,"1"), (F,5,"e2")]);
+        // x.1_0 is the index 1 then the Identifier _0.
+        let orig = vec![lx(F,0,"x"), lx(P,1,"."), lx(D,2,"1_0")];
+        assert_eq!(summary(tag("", orig)), vec![
+            (F,0,"x"), (P,1,"."), (D,2,"1"), (F,3,"_0")]);
+    }
+
+    #[test]
+    fn tag_tuple_index_incorrect() {
+        // A standalone float is left alone.
+        let orig = vec![lx(R,0,"1.0")];
+        assert_eq!(summary(tag("", orig)), vec![(R,0,"1.0")]);
+        // So is a float after a range, which is not a lone ".".
+        let orig = vec![lx(P,0,".."), lx(R,2,"1.5")];
+        assert_eq!(summary(tag("", orig)), vec![(P,0,".."), (R,2,"1.5")]);
+    }
+
+    #[test]
+    fn tag_tuple_index_will_not_panic() {
+        assert_eq!(summary(tag("", vec![])), vec![]);                  // no Lexemes
+        assert_eq!(summary(tag("", vec![lx(P,0,".")])), vec![(P,0,".")]); // lone "."
+        assert_eq!(summary(tag("", vec![lx(R,0,"1.")])), vec![(R,0,"1.")]); // lone Number
+    }
+}
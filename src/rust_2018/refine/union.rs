@@ -0,0 +1,93 @@
+//! Tags the `union` of a union definition as a Keyword.
+
+use alloc::vec::Vec;
+
+use super::super::lexeme::{Lexeme,LexemeKind};
+const FREEWORD: LexemeKind = LexemeKind::IdentifierFreeword;
+const KEYWORD: LexemeKind = LexemeKind::IdentifierKeyword;
+
+/// Tags the `union` of a union definition as a Keyword.
+/// 
+/// `union` is a weak keyword, which only has a special meaning when it
+/// defines a union, like `union IntOrFloat { i: u32, f: f32 }`. So
+/// `detect_identifier()` categorises it as a Freeword, and here it is retagged
+/// as a Keyword if it is followed by the union’s name, ignoring any trivia. A
+/// variable or function named `union`, like `let union = 1;` or `a.union(b)`,
+/// is left alone.
+/// 
+/// ### Arguments
+/// * `_orig` The original Rust code, not needed by `tag_union()`
+/// * `lexemes` The Lexemes found by the `detect_*()` functions
+/// 
+/// ### Returns
+/// `tag_union()` returns `lexemes`, with the `union` of each definition
+/// retagged as `LexemeKind::IdentifierKeyword`.
+pub fn tag_union<'a>(
+    _orig: &'a str,
+    mut lexemes: Vec<Lexeme<'a>>,
+) -> Vec<Lexeme<'a>> {
+    for i in 0..lexemes.len() {
+        // If this Lexeme is not the Freeword `union`, move on to the next.
+        if lexemes[i].kind != FREEWORD || lexemes[i].snippet != "union" {
+            continue;
+        }
+        // If the next significant Lexeme is the union’s name, this is a
+        // definition. Two Freewords can not otherwise be next to each other.
+        let next = lexemes[i+1..].iter().find(|lexeme| ! lexeme.is_trivia());
+        if next.is_some_and(|next| next.kind == FREEWORD) {
+            lexemes[i].kind = KEYWORD;
+        }
+    }
+    lexemes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::tag_union as tag;
+    use super::super::super::lexeme::LexemeKind;
+    use super::super::super::test_helpers::{kinds,lx};
+    use super::FREEWORD as F;
+    use super::KEYWORD as K;
+    const C: LexemeKind = LexemeKind::CommentMultiline;
+    const E: LexemeKind = LexemeKind::EndOfInput;
+    const N: LexemeKind = LexemeKind::NumberDecimal;
+    const P: LexemeKind = LexemeKind::Punctuation;
+    const W: LexemeKind = LexemeKind::WhitespaceTrimmable;
+
+    #[test]
+    fn tag_union_correct() {
+        // union U {
+        let orig = vec![lx(F,0,"union"), lx(W,5," "), lx(F,6,"U"),
+                        lx(W,7," "), lx(P,8,"{")];
+        assert_eq!(kinds(tag("", orig)), vec![K,W,F,W,P]);
+        // pub union/*c*/U<T>
+        let orig = vec![lx(K,0,"pub"), lx(W,3," "), lx(F,4,"union"),
+                        lx(C,9,"/*c*/"), lx(F,14,"U"), lx(P,15,"<"),
+                        lx(F,16,"T"), lx(P,17,">")];
+        assert_eq!(kinds(tag("", orig)), vec![K,W,K,C,F,P,F,P]);
+    }
+
+    #[test]
+    fn tag_union_incorrect() {
+        // let union = 1;
+        let orig = vec![lx(K,0,"let"), lx(W,3," "), lx(F,4,"union"),
+                        lx(W,9," "), lx(P,10,"="), lx(W,11," "), lx(N,12,"1"),
+                        lx(P,13,";")];
+        assert_eq!(kinds(tag("", orig)), vec![K,W,F,W,P,W,N,P]);
+        // a.union(b)
+        let orig = vec![lx(F,0,"a"), lx(P,1,"."), lx(F,2,"union"),
+                        lx(P,7,"("), lx(F,8,"b"), lx(P,9,")")];
+        assert_eq!(kinds(tag("", orig)), vec![F,P,F,P,F,P]);
+        // union as u8, where `as` is a Keyword
+        let orig = vec![lx(F,0,"union"), lx(W,5," "), lx(K,6,"as")];
+        assert_eq!(kinds(tag("", orig)), vec![F,W,K]);
+    }
+
+    #[test]
+    fn tag_union_will_not_panic() {
+        assert_eq!(kinds(tag("", vec![])), vec![]); // no Lexemes
+        let orig = vec![lx(F,0,"union"), lx(E,5,"")]; // nothing after `union`
+        assert_eq!(kinds(tag("", orig)), vec![F,E]);
+    }
+}
@@ -0,0 +1,185 @@
+//! Encodes a `LexemizeResult` as LSP semantic tokens.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::lexeme::LexemeKind;
+use super::lexemize::LexemizeResult;
+
+/// A single token, in the relative encoding used by the Language Server
+/// Protocol’s `textDocument/semanticTokens` response.
+/// 
+/// Positions are zero indexed, and measured in UTF-16 code units. Each token
+/// is positioned relative to the one before it: `delta_start` is relative to
+/// the previous token’s start if they are on the same line, or to the start of
+/// the line otherwise.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct SemanticToken {
+    /// Lines since the previous token.
+    pub delta_line: u32,
+    /// Start, relative to the previous token or the start of the line.
+    pub delta_start: u32,
+    /// Length, in UTF-16 code units.
+    pub length: u32,
+    /// Index into the token types legend. See `SEMANTIC_TOKEN_TYPES`.
+    pub token_type: u32,
+    /// Bit flags into the token modifiers legend. Always `0`, for now.
+    pub token_modifiers: u32,
+}
+
+/// The token types legend, which a language server should send to the client.
+/// 
+/// `SemanticToken::token_type` is an index into this array. There is one token
+/// type for each group of `LexemeKind`s, in the same order as their bytes.
+pub const SEMANTIC_TOKEN_TYPES: [&str; 8] = [
+    "character",
+    "comment",
+    "identifier",
+    "number",
+    "punctuation",
+    "string",
+    "other",
+    "whitespace",
+];
+
+impl LexemizeResult<'_> {
+    /// Encodes the Lexemes as LSP semantic tokens.
+    /// 
+    /// Whitespace, Trivia and the `<EOI>` sentinel are left out. A Lexeme which
+    /// spans several lines, like a multiline comment, is split into one token
+    /// per line, because not every client supports multiline tokens. Like
+    /// the LSP spec, "\n", "\r\n" and a bare "\r" each end a line.
+    /// 
+    /// ### Returns
+    /// `semantic_tokens()` returns a vector of [`SemanticToken`] objects.
+    pub fn semantic_tokens(&self) -> Vec<SemanticToken> {
+        let mut tokens = vec![];
+        // The current position, and the start of the previous token.
+        let (mut line, mut col) = (0, 0);
+        let (mut prev_line, mut prev_start) = (0, 0);
+        // A "\r" at the end of one Lexeme may be followed by "\n" in the next.
+        let mut after_cr = false;
+        for lexeme in self.iter_no_eoi() {
+            let token_type = group_index(lexeme.kind);
+            // Leave out Whitespace and Trivia, wherever they are in the legend.
+            let is_token =
+                SEMANTIC_TOKEN_TYPES[token_type as usize] != "whitespace";
+            let mut start = col;
+            // Step through each char, splitting the Lexeme at each line break.
+            for c in lexeme.snippet.chars() {
+                if c == '\n' && after_cr { after_cr = false; start = col; continue }
+                after_cr = c == '\r';
+                if c == '\n' || c == '\r' {
+                    if is_token && col > start {
+                        tokens.push(encode(line, start, col - start, token_type,
+                            &mut prev_line, &mut prev_start));
+                    }
+                    line += 1;
+                    col = 0;
+                    start = 0;
+                } else {
+                    col += c.len_utf16() as u32;
+                }
+            }
+            // Record the (last part of the) Lexeme.
+            if is_token && col > start {
+                tokens.push(encode(line, start, col - start, token_type,
+                    &mut prev_line, &mut prev_start));
+            }
+        }
+        tokens
+    }
+}
+
+// Returns the index of a `LexemeKind`’s group, which is the index of its byte.
+fn group_index(kind: LexemeKind) -> u32 {
+    (kind as u64).trailing_zeros() / 8
+}
+
+// Returns a token relative to the previous one, and updates the previous one.
+fn encode(
+    line: u32,
+    start: u32,
+    length: u32,
+    token_type: u32,
+    prev_line: &mut u32,
+    prev_start: &mut u32,
+) -> SemanticToken {
+    let delta_line = line - *prev_line;
+    let delta_start = if delta_line == 0 { start - *prev_start } else { start };
+    *prev_line = line;
+    *prev_start = start;
+    SemanticToken { delta_line, delta_start, length, token_type, token_modifiers: 0 }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{group_index,SemanticToken,SEMANTIC_TOKEN_TYPES};
+    use super::super::lexeme::LexemeKind;
+    use super::super::lexemize::lexemize;
+
+    // Returns the five integers of each token, like the LSP `data` array.
+    fn data(tokens: Vec<SemanticToken>) -> Vec<[u32; 5]> {
+        tokens.iter().map(|t| [t.delta_line, t.delta_start, t.length,
+            t.token_type, t.token_modifiers]).collect()
+    }
+
+    #[test]
+    fn semantic_tokens_legend() {
+        assert_eq!(SEMANTIC_TOKEN_TYPES[2], "identifier");
+        assert_eq!(SEMANTIC_TOKEN_TYPES[5], "string");
+        // Each group of `LexemeKind`s maps to the legend entry for its byte.
+        let legend = |kind| SEMANTIC_TOKEN_TYPES[group_index(kind) as usize];
+        assert_eq!(legend(LexemeKind::CommentShebang), "comment");
+        assert_eq!(legend(LexemeKind::Unexpected), "other");
+        assert_eq!(legend(LexemeKind::WhitespaceTrimmable), "whitespace");
+        assert_eq!(legend(LexemeKind::Trivia), "whitespace");
+    }
+
+    #[test]
+    fn semantic_tokens_two_lines() {
+        // `😀` is two UTF-16 code units, and `€` is one.
+        let result = lexemize("let s = \"€😀\";\n  // hi");
+        assert_eq!(data(result.semantic_tokens()), vec![
+            [0, 0, 3, 2, 0], // let
+            [0, 4, 1, 2, 0], // s
+            [0, 2, 1, 4, 0], // =
+            [0, 2, 5, 5, 0], // "€😀"
+            [0, 5, 1, 4, 0], // ;
+            [1, 2, 5, 1, 0], // // hi
+        ]);
+    }
+
+    #[test]
+    fn semantic_tokens_multiline() {
+        // A multiline comment is split into one token per line, and a Windows
+        // line ending counts as one line break.
+        let result = lexemize("a /* b\r\n c */ d\r\ne");
+        assert_eq!(data(result.semantic_tokens()), vec![
+            [0, 0, 1, 2, 0], // a
+            [0, 2, 4, 1, 0], // /* b
+            [1, 0, 5, 1, 0], //  c */
+            [0, 6, 1, 2, 0], // d
+            [1, 0, 1, 2, 0], // e
+        ]);
+        // An inline comment ending in "\r", followed by a "\n" Lexeme.
+        let result = lexemize("//x\r\ny");
+        assert_eq!(data(result.semantic_tokens()), vec![
+            [0, 0, 3, 1, 0], // //x
+            [1, 0, 1, 2, 0], // y
+        ]);
+    }
+
+    #[test]
+    fn semantic_tokens_will_not_panic() {
+        assert_eq!(lexemize("").semantic_tokens(), vec![]);
+        assert_eq!(lexemize(" \n ").semantic_tokens(), vec![]);
+        // With no `<EOI>` sentinel, or no Lexemes at all.
+        let mut result = lexemize("x");
+        result.lexemes.pop();
+        assert_eq!(data(result.semantic_tokens()), vec![[0, 0, 1, 2, 0]]);
+        result.lexemes.clear();
+        assert_eq!(result.semantic_tokens(), vec![]);
+    }
+}
@@ -0,0 +1,29 @@
+//! Helpers shared by the unit tests of several modules.
+
+use alloc::vec::Vec;
+
+use super::lexeme::{Lexeme,LexemeKind};
+use super::lexemize::{lexemize_with_options,LexemizeOptions};
+
+// Creates a Lexeme, for a test which does not need to call `lexemize()`.
+pub fn lx(kind: LexemeKind, chr: usize, snippet: &'static str) -> Lexeme<'static> {
+    Lexeme { kind, chr, snippet }
+}
+
+// Returns the kind of each Lexeme.
+pub fn kinds(lexemes: Vec<Lexeme>) -> Vec<LexemeKind> {
+    lexemes.iter().map(|lexeme| lexeme.kind).collect()
+}
+
+// Returns the kind, `chr` and snippet of each Lexeme.
+pub fn summary<'a>(lexemes: Vec<Lexeme<'a>>) -> Vec<(LexemeKind, usize, &'a str)> {
+    lexemes.iter().map(|lexeme| (lexeme.kind, lexeme.chr, lexeme.snippet)).collect()
+}
+
+// Lexemizes `orig` with `options`, and returns the summary of each Lexeme.
+pub fn summary_with(
+    orig: &'static str,
+    options: LexemizeOptions,
+) -> Vec<(LexemeKind, usize, &'static str)> {
+    summary(lexemize_with_options(orig, options).lexemes)
+}
@@ -0,0 +1,22 @@
+//! Checks that the crate works in a `#![no_std]` program, which only has the
+//! `alloc` crate. Run `cargo test --no-default-features` to build the library
+//! as `no_std` too, because integration tests link it without `cfg(test)`.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use op8d_lexemizer::rust_2018::lexeme::LexemeKind;
+use op8d_lexemizer::rust_2018::lexemize::lexemize;
+
+#[test]
+fn lexemize_with_alloc_only() {
+    let result = lexemize("let x = 1;");
+    let kinds: Vec<LexemeKind> = result.lexemes.iter().map(|lexeme| lexeme.kind).collect();
+    assert_eq!(kinds.len(), 9);
+    assert_eq!(kinds[0], LexemeKind::IdentifierKeyword);
+    assert_eq!(result.to_source(), "let x = 1;");
+    assert!(result.to_string().starts_with("Lexemes, incl <EOI>: 9\n"));
+}